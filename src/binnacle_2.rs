@@ -1,51 +1,101 @@
 use std::{
+    collections::BTreeMap,
+    io::{self, Write},
     ops::{Bound, RangeBounds},
     time::Duration,
 };
 
 use chrono::{FixedOffset, NaiveDate, TimeZone};
 use itertools::Itertools;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use serde::Serialize;
 
+#[cfg(feature = "serde")]
+use crate::summary::deserialize_seconds;
 use crate::{
     binnacle_body_parser::{self, SessionWithBody},
-    format_util::{fmt_duration_uncertain, fmt_duration_uncertain_with_hs, fmt_month},
-    parser::{NaiveSessionIteratorExt, SessionIteratorExt, SessionTZ},
-    summary::{MonthId, NaiveDateExt},
+    budget::{Budget, BudgetPeriod},
+    cli::Rounding,
+    format_util::{
+        fmt_duration, fmt_duration_uncertain, fmt_duration_uncertain_with_hs, fmt_month,
+    },
+    i18n::catalog,
+    parser::{NaiveSession, NaiveSessionIteratorExt, SessionIteratorExt, SessionTZ},
+    rounding,
+    summary::{MonthId, NaiveDateExt, serialize_seconds},
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct Task {
     subject: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct SubProjectDayInfo {
-    total_time: Duration,
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_seconds"))]
+    pub total_time: Duration,
     tasks: Vec<Task>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct SubProjectDay {
-    sub_project_name: String,
-    info: SubProjectDayInfo,
+    /// components of the sub-project, parents come before their children
+    pub path: Vec<String>,
+    #[serde(flatten)]
+    pub info: SubProjectDayInfo,
 }
 
-#[derive(Debug)]
+impl SubProjectDay {
+    fn name(&self) -> String {
+        self.path.join("/")
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct Day {
-    date: NaiveDate,
-    sub_projects: Vec<SubProjectDay>,
+    pub date: NaiveDate,
+    pub sub_projects: Vec<SubProjectDay>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct Month {
-    id: MonthId,
-    total_time: Duration,
-    days: Vec<Day>,
+    #[serde(rename = "month")]
+    pub id: MonthId,
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_seconds"))]
+    pub total_time: Duration,
+    pub days: Vec<Day>,
 }
 
-#[derive(Debug)]
+/// Worked time by month, day and sub-project, see [`process`].
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct BinnacleData {
-    months: Vec<Month>,
+    pub months: Vec<Month>,
+}
+
+impl BinnacleData {
+    /// Rounds the time of every sub-project on each day, month totals become the sum of the
+    /// rounded top level sub-projects.
+    pub fn round_days(&mut self, rounding: &Rounding) {
+        for month in &mut self.months {
+            month.total_time = Duration::ZERO;
+            for sub_project in month.days.iter_mut().flat_map(|d| &mut d.sub_projects) {
+                let info = &mut sub_project.info;
+                info.total_time = rounding::round_std(info.total_time, rounding);
+                if sub_project.path.len() == 1 {
+                    month.total_time += info.total_time;
+                }
+            }
+        }
+    }
 }
 
 pub fn process(
@@ -82,32 +132,7 @@ pub fn process(
                         .into_iter()
                         .map(|(day, chunk)| Day {
                             date: day,
-                            sub_projects: chunk
-                                .into_grouping_map_by(|s| s.body.sub_project.clone())
-                                .fold(
-                                    SubProjectDayInfo {
-                                        total_time: Duration::ZERO,
-                                        tasks: vec![],
-                                    },
-                                    |mut acc, _sub_project, task| {
-                                        acc.total_time += task.session.duration().to_std().unwrap();
-                                        acc.tasks.push(Task {
-                                            subject: task.body.subject.to_owned(),
-                                        });
-
-                                        acc
-                                    },
-                                )
-                                .into_iter()
-                                .map(|(sub_project, info)| SubProjectDay {
-                                    sub_project_name: sub_project
-                                        .unwrap_or("sin categoría".to_owned()),
-                                    info,
-                                })
-                                .sorted_by_key(|sub_project_day| {
-                                    sub_project_day.sub_project_name.clone()
-                                })
-                                .collect_vec(),
+                            sub_projects: aggregate_sub_projects(chunk),
                         })
                         .collect_vec(),
                 }
@@ -116,6 +141,44 @@ pub fn process(
     }
 }
 
+/// Adds the time of every session to its sub-project and to each of its parents, while the
+/// tasks are only listed under the innermost one.
+fn aggregate_sub_projects(
+    sessions: impl IntoIterator<Item = SessionWithBody<NaiveSession>>,
+) -> Vec<SubProjectDay> {
+    let mut sub_projects = BTreeMap::<Vec<String>, SubProjectDayInfo>::new();
+    for task in sessions {
+        let mut path = task
+            .body
+            .sub_project_path()
+            .into_iter()
+            .map(str::to_owned)
+            .collect_vec();
+        if path.is_empty() {
+            path.push(catalog().uncategorized.to_owned());
+        }
+
+        let duration = task.session.duration().to_std().unwrap();
+        for depth in 1..=path.len() {
+            sub_projects
+                .entry(path[..depth].to_vec())
+                .or_insert_with(|| SubProjectDayInfo {
+                    total_time: Duration::ZERO,
+                    tasks: vec![],
+                })
+                .total_time += duration;
+        }
+        sub_projects.get_mut(&path).unwrap().tasks.push(Task {
+            subject: task.body.subject,
+        });
+    }
+
+    sub_projects
+        .into_iter()
+        .map(|(path, info)| SubProjectDay { path, info })
+        .collect_vec()
+}
+
 #[allow(unused)]
 fn print_sub_projects(binnacle_data: &BinnacleData) {
     dbg!(
@@ -124,28 +187,64 @@ fn print_sub_projects(binnacle_data: &BinnacleData) {
             .iter()
             .flat_map(|m| &m.days)
             .flat_map(|d| &d.sub_projects)
-            .map(|sp| &sp.sub_project_name)
+            .map(|sp| sp.name())
             .unique()
             .collect_vec()
     );
 }
 
-pub fn format(binnacle_data: BinnacleData, current_date: NaiveDate) {
+/// Writes the report, listing under each month how much of the monthly budgets was used and
+/// marking the exceeded ones.
+pub fn format(
+    binnacle_data: BinnacleData,
+    budgets: &BTreeMap<String, Budget>,
+    current_date: NaiveDate,
+    out: &mut impl Write,
+) -> io::Result<()> {
     for month in &binnacle_data.months {
-        println!(
+        writeln!(
+            out,
             "## {} ({})\n",
             fmt_month(month.id),
             fmt_duration_uncertain(&month.total_time, current_date > month.id.last_day())
-        );
+        )?;
+
+        let monthly_budgets = budgets
+            .iter()
+            .filter(|(_, budget)| budget.period == BudgetPeriod::Month)
+            .collect_vec();
+        for (name, budget) in &monthly_budgets {
+            let consumed: Duration = month
+                .days
+                .iter()
+                .flat_map(|d| &d.sub_projects)
+                .filter(|sp| &&sp.name() == name)
+                .map(|sp| sp.info.total_time)
+                .sum();
+            let marker = if consumed > budget.time { " ⚠" } else { "" };
+            writeln!(
+                out,
+                "- {} {name}: {} {} {}{marker}",
+                catalog().budget,
+                fmt_duration(&consumed),
+                catalog().of,
+                fmt_duration(&budget.time)
+            )?;
+        }
+        if !monthly_budgets.is_empty() {
+            writeln!(out)?;
+        }
 
         for day in &month.days {
-            println!("{}\n", day.date.format("%d/%m/%Y"));
+            writeln!(out, "{}\n", day.date.format(catalog().date_format))?;
             for sub_project in &day.sub_projects {
-                println!(
-                    "- ({}: {})\n",
-                    sub_project.sub_project_name,
+                let indent = "\t".repeat(sub_project.path.len() - 1);
+                writeln!(
+                    out,
+                    "{indent}- ({}: {})\n",
+                    sub_project.path.last().unwrap(),
                     fmt_duration_uncertain_with_hs(&sub_project.info.total_time, current_date > day.date)
-                );
+                )?;
                 for task in sub_project
                     .info
                     .tasks
@@ -153,11 +252,118 @@ pub fn format(binnacle_data: BinnacleData, current_date: NaiveDate) {
                     .filter(|t| !t.subject.is_empty())
                     .unique()
                 {
-                    println!("\t- {}\n", task.subject);
+                    writeln!(out, "{indent}\t- {}\n", task.subject)?;
                 }
             }
-            println!("\n");
+            writeln!(out, "\n")?;
         }
     }
     //print_sub_projects(&binnacle_data);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, ops::Bound, time::Duration};
+
+    use chrono::{FixedOffset, NaiveDate};
+
+    use super::{aggregate_sub_projects, format, process};
+    use crate::{
+        binnacle_body_parser::{self, SessionWithBody},
+        parser::NaiveSession,
+        testing::session,
+    };
+
+    #[test]
+    fn nested_sub_projects() {
+        let start = NaiveDate::from_ymd_opt(2000, 1, 1)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        let session = |hours: u64, description: &str| SessionWithBody {
+            body: binnacle_body_parser::parse(description).unwrap().to_owned(),
+            session: NaiveSession {
+                start,
+                end: start + Duration::from_secs(hours * 3600),
+                pauses: vec![],
+                description: description.to_owned(),
+                host: None,
+                id: None,
+            },
+        };
+
+        let sub_projects = aggregate_sub_projects([
+            session(1, "backend/api: fix auth"),
+            session(2, "backend: deploy"),
+            session(4, "backend-old: cleanup"),
+        ]);
+
+        assert_eq!(
+            sub_projects
+                .iter()
+                .map(|sp| (
+                    sp.name(),
+                    sp.info.total_time.as_secs() / 3600,
+                    sp.info.tasks.len()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("backend".to_owned(), 3, 1),
+                ("backend/api".to_owned(), 1, 1),
+                ("backend-old".to_owned(), 4, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_to_writer() {
+        let session = session("2000-01-03T10:00:00+00:00")
+            .end("2000-01-03T11:30:00+00:00")
+            .description("backend: deploy")
+            .build();
+        let data = process(
+            [session].into_iter(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+            &FixedOffset::east_opt(0).unwrap(),
+        );
+
+        let mut out = vec![];
+        let current_date = NaiveDate::from_ymd_opt(2000, 2, 1).unwrap();
+        format(data, &BTreeMap::new(), current_date, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "## Enero 2000 (01:30:00)\n\n03/01/2000\n\n- (backend: 01:30:00 hs)\n\n\t- deploy\n\n\n\n"
+        );
+    }
+
+    #[test]
+    fn budgets() {
+        let session = session("2000-01-03T10:00:00+00:00")
+            .end("2000-01-03T11:30:00+00:00")
+            .description("backend/api: deploy")
+            .build();
+        let data = process(
+            [session].into_iter(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+            &FixedOffset::east_opt(0).unwrap(),
+        );
+        let budgets = BTreeMap::from([
+            ("backend".to_owned(), "1h/month".parse().unwrap()),
+            ("backend/api".to_owned(), "2h/month".parse().unwrap()),
+            ("frontend".to_owned(), "1h/week".parse().unwrap()),
+        ]);
+
+        let mut out = vec![];
+        let current_date = NaiveDate::from_ymd_opt(2000, 2, 1).unwrap();
+        format(data, &budgets, current_date, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with(
+            "## Enero 2000 (01:30:00)\n\n\
+             - Presupuesto backend: 01:30:00 de 01:00:00 ⚠\n\
+             - Presupuesto backend/api: 01:30:00 de 02:00:00\n\n\
+             03/01/2000\n"
+        ));
+    }
 }
@@ -3,6 +3,20 @@ use thiserror::Error;
 pub struct OwnedBody {
     pub sub_project: Option<String>,
     pub subject: String,
+    pub tags: Vec<String>,
+    pub tickets: Vec<String>,
+}
+
+impl OwnedBody {
+    /// Components of a nested sub-project like `backend/api`, empty when there is none.
+    pub fn sub_project_path(&self) -> Vec<&str> {
+        self.sub_project
+            .iter()
+            .flat_map(|s| s.split('/'))
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .collect()
+    }
 }
 
 impl<'a> Body<'a> {
@@ -10,6 +24,8 @@ impl<'a> Body<'a> {
         OwnedBody {
             sub_project: self.sub_project.map(|s| s.to_owned()),
             subject: self.subject.to_owned(),
+            tags: self.tags.iter().map(|&t| t.to_owned()).collect(),
+            tickets: self.tickets.iter().map(|&t| t.to_owned()).collect(),
         }
     }
 }
@@ -17,6 +33,8 @@ impl<'a> Body<'a> {
 pub struct Body<'a> {
     pub sub_project: Option<&'a str>,
     pub subject: &'a str,
+    pub tags: Vec<&'a str>,
+    pub tickets: Vec<&'a str>,
 }
 
 #[derive(Error, Debug)]
@@ -27,15 +45,147 @@ pub struct SessionWithBody<Session> {
     pub body: OwnedBody,
 }
 
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Extracts the `#tag` tokens of a description, without the `#` and without repetitions. Numbers
+/// like `#456` are tickets, not tags.
+pub fn parse_tags(body_str: &str) -> Vec<&str> {
+    let mut tags = vec![];
+    for word in body_str.split_whitespace() {
+        let Some(rest) = word.strip_prefix('#') else {
+            continue;
+        };
+        let tag = &rest[..rest.find(|c| !is_tag_char(c)).unwrap_or(rest.len())];
+        let numeric = tag.chars().all(|c| c.is_ascii_digit());
+        if !numeric && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+fn is_ticket(word: &str) -> bool {
+    if let Some(number) = word.strip_prefix('#') {
+        return !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+    }
+    let Some((key, number)) = word.split_once('-') else {
+        return false;
+    };
+    key.len() >= 2
+        && key.starts_with(|c: char| c.is_ascii_uppercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Extracts issue tracker references like `PROJ-123` or `#456`, without repetitions.
+pub fn parse_tickets(body_str: &str) -> Vec<&str> {
+    let mut tickets = vec![];
+    for word in body_str.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '#')) {
+        if is_ticket(word) && !tickets.contains(&word) {
+            tickets.push(word);
+        }
+    }
+    tickets
+}
+
+/// Whether the description has every one of the given tags. A leading `#` in them is optional.
+pub fn has_tags(body_str: &str, tags: &[String]) -> bool {
+    let body_tags = parse_tags(body_str);
+    tags.iter()
+        .all(|tag| body_tags.contains(&tag.strip_prefix('#').unwrap_or(tag)))
+}
+
+/// Whether the description belongs to one of the given sub-projects or to one nested in them, so
+/// `backend` takes in `backend/api`. Every description does when none are given.
+pub fn in_sub_projects(body_str: &str, sub_projects: &[String]) -> bool {
+    if sub_projects.is_empty() {
+        return true;
+    }
+    let body = parse(body_str).unwrap().to_owned();
+    let path = body.sub_project_path();
+    sub_projects.iter().any(|sub_project| {
+        let wanted = sub_project
+            .split('/')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .collect::<Vec<_>>();
+        !wanted.is_empty() && path.starts_with(&wanted)
+    })
+}
+
 pub fn parse(body_str: &str) -> Result<Body<'_>, ParseError> {
+    let tags = parse_tags(body_str);
+    let tickets = parse_tickets(body_str);
     match body_str.find(":") {
         None => Ok(Body {
             sub_project: None,
             subject: body_str,
+            tags,
+            tickets,
         }),
         Some(colon_idx) => Ok(Body {
             sub_project: (colon_idx > 0).then_some(&body_str[..colon_idx]),
             subject: body_str[colon_idx + 1..].trim_start(),
+            tags,
+            tickets,
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{has_tags, in_sub_projects, parse, parse_tags, parse_tickets};
+
+    #[test]
+    fn tags() {
+        let body = "backend: fix login #bug, review #PR-12\n#bug again # not a tag # #456 #2fa";
+        assert_eq!(parse_tags(body), ["bug", "PR-12", "2fa"]);
+        assert!(has_tags(body, &["#bug".to_owned(), "PR-12".to_owned()]));
+        assert!(!has_tags(body, &["bug".to_owned(), "meeting".to_owned()]));
+        assert!(has_tags(body, &[]));
+    }
+
+    #[test]
+    fn tickets() {
+        assert_eq!(
+            parse_tickets("fix (PROJ-123), #456 and AB2-7; not Proj-1, X-1, #45a or ABC-"),
+            ["PROJ-123", "#456", "AB2-7"]
+        );
+    }
+
+    #[test]
+    fn sub_project_path() {
+        let body = parse("backend/ api/: fix auth").unwrap().to_owned();
+        assert_eq!(body.sub_project_path(), ["backend", "api"]);
+        assert_eq!(body.subject, "fix auth");
+        assert!(
+            parse("no category")
+                .unwrap()
+                .to_owned()
+                .sub_project_path()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn sub_projects() {
+        let sub_projects = |names: &[&str]| names.iter().map(|&n| n.to_owned()).collect::<Vec<_>>();
+        let body = "backend/api: fix auth";
+        assert!(in_sub_projects(body, &sub_projects(&["backend"])));
+        assert!(in_sub_projects(
+            body,
+            &sub_projects(&["frontend", "backend/api/"])
+        ));
+        assert!(!in_sub_projects(
+            body,
+            &sub_projects(&["back", "backend/auth"])
+        ));
+        assert!(!in_sub_projects("no category", &sub_projects(&["backend"])));
+        assert!(in_sub_projects("no category", &[]));
+    }
+}
@@ -0,0 +1,264 @@
+use std::{fmt, ops::RangeInclusive, path::Path, str::FromStr, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use chrono::{Datelike, Days, FixedOffset, Local, NaiveDate, TimeZone};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    binnacle_body_parser,
+    cli::BudgetCommand,
+    config,
+    format_util::fmt_duration,
+    i18n::catalog,
+    parser::{
+        self, NaiveSessionIteratorExt, Session, SessionIteratorClosingExt, SessionIteratorExt,
+    },
+    project_config::ProjectConfig,
+    summary::NaiveDateExt,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetPeriod {
+    Week,
+    Month,
+}
+
+impl BudgetPeriod {
+    /// The week (starting on monday) or the month that contains the date.
+    pub fn range(&self, date: NaiveDate) -> RangeInclusive<NaiveDate> {
+        match self {
+            BudgetPeriod::Week => {
+                let first = date - Days::new(date.weekday().num_days_from_monday() as u64);
+                first..=first + Days::new(6)
+            }
+            BudgetPeriod::Month => {
+                let month = date.month_id();
+                month.first_day()..=month.last_day()
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Week => "week",
+            BudgetPeriod::Month => "month",
+        }
+    }
+}
+
+/// Time allowed for a sub-project in each period, written like `40h/month` or `90min/week`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Budget {
+    pub time: Duration,
+    pub period: BudgetPeriod,
+}
+
+impl FromStr for Budget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (time, period) = s
+            .split_once('/')
+            .with_context(|| format!("invalid budget {s:?}, expected something like 40h/month"))?;
+        let period = match period.trim() {
+            "week" => BudgetPeriod::Week,
+            "month" => BudgetPeriod::Month,
+            period => bail!("unknown period {period:?}, expected week or month"),
+        };
+        let time = parse_time(time)?;
+        if time.is_zero() {
+            bail!("the budget time must be positive");
+        }
+        Ok(Budget { time, period })
+    }
+}
+
+/// Parses an amount of time in hours like `7.5h` or in minutes like `90min`.
+pub fn parse_time(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let (number, unit_seconds) = if let Some(hours) = text.strip_suffix('h') {
+        (hours, 3600.0)
+    } else if let Some(minutes) = text.strip_suffix("min") {
+        (minutes, 60.0)
+    } else {
+        bail!("invalid time {text:?}, expected hours like 8h or minutes like 90min");
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid number {number:?}"))?;
+    Duration::try_from_secs_f64(number * unit_seconds)
+        .with_context(|| format!("invalid time {text:?}"))
+}
+
+impl TryFrom<String> for Budget {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.time.as_secs();
+        if seconds.is_multiple_of(3600) {
+            write!(f, "{}h/{}", seconds / 3600, self.period.name())
+        } else {
+            write!(f, "{}min/{}", seconds / 60, self.period.name())
+        }
+    }
+}
+
+impl From<Budget> for String {
+    fn from(budget: Budget) -> Self {
+        budget.to_string()
+    }
+}
+
+/// Sub-project name with its components trimmed, like the sub-projects of the sessions.
+pub fn normalize_sub_project(name: &str) -> String {
+    name.split('/').map(str::trim).collect::<Vec<_>>().join("/")
+}
+
+/// Time worked on the sub-project, including its children, within the dates.
+pub fn consumed<Tz: TimeZone>(
+    sessions: impl Iterator<Item = Session>,
+    sub_project: &str,
+    range: RangeInclusive<NaiveDate>,
+    timezone: &Tz,
+) -> Duration {
+    let budget_path = sub_project.split('/').collect::<Vec<_>>();
+    sessions
+        .with_timezone(timezone)
+        .naive_local()
+        .cut_at_days()
+        .filter(|s| range.contains(&s.start.date()))
+        .filter(|s| {
+            let body = binnacle_body_parser::parse(&s.description)
+                .unwrap()
+                .to_owned();
+            body.sub_project_path().starts_with(&budget_path)
+        })
+        .map(|s| s.duration().to_std().unwrap())
+        .sum()
+}
+
+/// Sets or removes a budget or prints how much of each one is left, as in `clockin budget`.
+pub fn run(
+    path: &Path,
+    command: Option<BudgetCommand>,
+    timezone: Option<FixedOffset>,
+) -> Result<()> {
+    let mut config = ProjectConfig::load(path)?;
+    match command {
+        Some(BudgetCommand::Set {
+            sub_project,
+            budget,
+        }) => {
+            let budget = budget.parse()?;
+            config
+                .budgets
+                .insert(normalize_sub_project(&sub_project), budget);
+            config.save(path)?;
+        }
+        Some(BudgetCommand::Remove { sub_project }) => {
+            if config
+                .budgets
+                .remove(&normalize_sub_project(&sub_project))
+                .is_none()
+            {
+                bail!("{sub_project:?} has no budget");
+            }
+            config.save(path)?;
+        }
+        None => {
+            let timezone = config::resolve_timezone(path, timezone)?;
+            let today = Local::now().with_timezone(&timezone).date_naive();
+            let sessions = parser::parse_sessions(path)?
+                .into_iter()
+                .into_finished_now()
+                .collect_vec();
+            let catalog = catalog();
+            for (name, budget) in &config.budgets {
+                let consumed = consumed(
+                    sessions.iter().cloned(),
+                    name,
+                    budget.period.range(today),
+                    &timezone,
+                );
+                let status = match budget.time.checked_sub(consumed) {
+                    Some(left) => format!("{} {}", fmt_duration(&left), catalog.left),
+                    None => format!(
+                        "{} {} ⚠",
+                        fmt_duration(&(consumed - budget.time)),
+                        catalog.over
+                    ),
+                };
+                let period = match budget.period {
+                    BudgetPeriod::Week => catalog.this_week,
+                    BudgetPeriod::Month => catalog.this_month,
+                };
+                println!(
+                    "{name}: {} {} {} {}, {status}",
+                    fmt_duration(&consumed),
+                    catalog.of,
+                    fmt_duration(&budget.time),
+                    period.to_lowercase()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{NaiveDate, Utc};
+
+    use super::{Budget, BudgetPeriod, consumed};
+    use crate::testing::session;
+
+    #[test]
+    fn budget() {
+        let budget: Budget = "40h/month".parse().unwrap();
+        assert_eq!(budget.time, Duration::from_secs(40 * 3600));
+        assert_eq!(budget.period, BudgetPeriod::Month);
+        assert_eq!(
+            " 90min / week".parse::<Budget>().unwrap().to_string(),
+            "90min/week"
+        );
+        assert!("40/month".parse::<Budget>().is_err());
+        assert!("40h/year".parse::<Budget>().is_err());
+
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+        assert_eq!(BudgetPeriod::Week.range(date(5)), date(3)..=date(9));
+
+        let sessions = [
+            session("2000-01-03T10:00:00Z")
+                .end("2000-01-03T11:00:00Z")
+                .description("backend/api: auth")
+                .build(),
+            session("2000-01-04T10:00:00Z")
+                .end("2000-01-04T11:00:00Z")
+                .description("backend: deploy")
+                .build(),
+            session("2000-01-05T10:00:00Z")
+                .end("2000-01-05T11:00:00Z")
+                .description("backend-old: cleanup")
+                .build(),
+            session("2000-01-10T10:00:00Z")
+                .end("2000-01-10T11:00:00Z")
+                .description("backend: next week")
+                .build(),
+        ];
+        assert_eq!(
+            consumed(sessions.into_iter(), "backend", date(3)..=date(9), &Utc),
+            Duration::from_secs(2 * 3600)
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    ops::{Bound, Range},
+    path::Path,
+};
+
+use anyhow::{Result, bail};
+use chrono::{FixedOffset, Local, NaiveDate, NaiveTime, TimeDelta};
+use plotters::{coord::Shift, prelude::*};
+
+use crate::{
+    cli::ChartKind,
+    config,
+    format_util::fmt_hours_mins,
+    i18n::catalog,
+    parser,
+    summary::{self, NaiveDateExt, Summary},
+    time_analysis,
+};
+
+const SIZE: (u32, u32) = (1024, 576);
+
+/// A labeled bar of the chart, in hours.
+#[derive(Debug, PartialEq)]
+pub struct Bar {
+    pub label: String,
+    pub hours: f64,
+}
+
+fn hours(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() / 3600.0
+}
+
+/// Hours worked each day between the dates, both included.
+pub fn daily(summary: &Summary, from: NaiveDate, to: NaiveDate) -> Vec<Bar> {
+    from.iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| Bar {
+            label: date.format(catalog().short_date_format).to_string(),
+            hours: hours(summary.duration(date..=date)),
+        })
+        .collect()
+}
+
+/// Hours worked each week touching the dates, labeled by their monday.
+pub fn weekly(summary: &Summary, from: NaiveDate, to: NaiveDate) -> Vec<Bar> {
+    let last = to.real_week();
+    from.real_week()
+        .first_day()
+        .iter_weeks()
+        .map(|date| date.real_week())
+        .take_while(|week| *week <= last)
+        .map(|week| Bar {
+            label: week
+                .first_day()
+                .format(catalog().short_date_format)
+                .to_string(),
+            hours: hours(summary.week_duration(week)),
+        })
+        .collect()
+}
+
+/// Hours worked in each interval of the day, like `work-time-analysis` does.
+pub fn time_of_day(
+    intervals: impl Iterator<Item = Range<NaiveTime>>,
+    interval: TimeDelta,
+) -> Vec<Bar> {
+    time_analysis::time_of_day(intervals, interval)
+        .into_iter()
+        .enumerate()
+        .map(|(i, time)| Bar {
+            label: fmt_hours_mins(NaiveTime::MIN + interval * (i as i32)),
+            hours: time.as_seconds_f64() / 3600.0,
+        })
+        .collect()
+}
+
+fn draw<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, title: &str, bars: &[Bar]) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let max = bars.iter().map(|b| b.hours).fold(0.0, f64::max).max(1.0);
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 28))
+        .margin(16)
+        .x_label_area_size(48)
+        .y_label_area_size(56)
+        .build_cartesian_2d((0..bars.len()).into_segmented(), 0.0..max * 1.1)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .y_desc(catalog().hours.to_lowercase())
+        .x_labels(bars.len().min(31))
+        .x_label_formatter(&|value| match value {
+            SegmentValue::CenterOf(i) => bars.get(*i).map_or(String::new(), |b| b.label.clone()),
+            _ => String::new(),
+        })
+        .draw()?;
+    chart.draw_series(
+        Histogram::vertical(&chart)
+            .style(BLUE.filled())
+            .margin(2)
+            .data(bars.iter().enumerate().map(|(i, bar)| (i, bar.hours))),
+    )?;
+    root.present()?;
+    Ok(())
+}
+
+/// Writes the chart as SVG or PNG, depending on the extension of the file.
+pub fn render(path: &Path, title: &str, bars: &[Bar]) -> Result<()> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("svg") => draw(SVGBackend::new(path, SIZE).into_drawing_area(), title, bars),
+        Some("png") => draw(
+            BitMapBackend::new(path, SIZE).into_drawing_area(),
+            title,
+            bars,
+        ),
+        _ => bail!("unknown chart format for {path:?}, use a .svg or .png file"),
+    }
+}
+
+/// Draws the chart of `clockin chart` into the output image.
+pub fn write_chart(
+    path: &Path,
+    kind: ChartKind,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    interval: i64,
+    output: &Path,
+) -> Result<()> {
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let sessions = parser::sessions_in_range(path, range, &timezone)?;
+    let (title, bars) = match kind {
+        ChartKind::Daily | ChartKind::Weekly => {
+            let summary = Summary::summarize(sessions, &timezone);
+            let today = Local::now().with_timezone(&timezone).date_naive();
+            let first = *summary.days.keys().next().unwrap_or(&today);
+            let last = *summary.days.keys().last().unwrap_or(&today);
+            let (from, to) = summary::resolve_dates(range, first, last);
+            if let ChartKind::Daily = kind {
+                (catalog().hours_per_day, daily(&summary, from, to))
+            } else {
+                (catalog().hours_per_week, weekly(&summary, from, to))
+            }
+        }
+        ChartKind::TimeOfDay => {
+            let intervals = sessions
+                .map(|s| s.naive_local())
+                .flat_map(|s| s.split_at_days())
+                .flat_map(|s| s.active_intervals())
+                .map(|i| i.start.time()..i.end.time());
+            (
+                catalog().hours_per_time_of_day,
+                time_of_day(intervals, TimeDelta::minutes(interval)),
+            )
+        }
+    };
+    render(output, title, &bars)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeDelta, Utc};
+    use plotters::prelude::*;
+
+    use super::{Bar, daily, draw, weekly};
+    use crate::{summary::Summary, testing::session};
+
+    #[test]
+    fn chart() {
+        let summary = Summary::summarize(
+            [
+                session("2000-01-07T10:00:00Z")
+                    .lasting(TimeDelta::hours(2))
+                    .build(),
+                session("2000-01-10T10:00:00Z")
+                    .lasting(TimeDelta::hours(3))
+                    .build(),
+            ]
+            .into_iter(),
+            &Utc,
+        );
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+        let bar = |label: &str, hours| Bar {
+            label: label.to_owned(),
+            hours,
+        };
+
+        assert_eq!(
+            daily(&summary, date(7), date(9)),
+            [bar("07/01", 2.0), bar("08/01", 0.0), bar("09/01", 0.0)]
+        );
+        let weeks = weekly(&summary, date(7), date(10));
+        assert_eq!(weeks, [bar("03/01", 2.0), bar("10/01", 3.0)]);
+
+        let mut svg = String::new();
+        draw(
+            SVGBackend::with_string(&mut svg, (400, 300)).into_drawing_area(),
+            "semanas",
+            &weeks,
+        )
+        .unwrap();
+        assert!(svg.contains("semanas"));
+        assert!(svg.contains("10/01"));
+    }
+}
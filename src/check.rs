@@ -0,0 +1,203 @@
+use std::{io::BufRead, path::Path};
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use thiserror::Error;
+
+use crate::{
+    crypt, file,
+    parser::{clean_line, split_macro},
+};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Problem {
+    #[error("malformed macro {0:?}")]
+    MalformedMacro(String),
+    #[error("unexpected macro {0:?}")]
+    UnexpectedMacro(String),
+    #[error("timestamp is earlier than the previous one in the session")]
+    TimestampOutOfOrder,
+    #[error("session ends before it starts")]
+    EndBeforeStart,
+    #[error("session overlaps with the one starting at line {0}")]
+    Overlap(usize),
+    #[error("session starts before the one starting at line {0}")]
+    OutOfOrder(usize),
+    #[error("session is never finished")]
+    Dangling,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+struct OpenSession {
+    line: usize,
+    start: DateTime<FixedOffset>,
+    last: DateTime<FixedOffset>,
+    paused: bool,
+}
+
+struct ClosedSession {
+    line: usize,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+}
+
+pub fn check(lines: impl Iterator<Item = String>) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut report = |line, problem| diagnostics.push(Diagnostic { line, problem });
+
+    let mut current: Option<OpenSession> = None;
+    let mut previous: Option<ClosedSession> = None;
+
+    for (index, text) in lines.enumerate() {
+        let line = index + 1;
+        let Some((kind, argument)) =
+            split_macro(clean_line(&text)).filter(|(k, _)| "-+pr".contains(*k))
+        else {
+            continue;
+        };
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(argument) else {
+            report(line, Problem::MalformedMacro(text));
+            continue;
+        };
+
+        match (kind, current.as_mut()) {
+            ('-', _) => {
+                if let Some(open) = current.take() {
+                    report(open.line, Problem::Dangling);
+                    previous = Some(ClosedSession {
+                        line: open.line,
+                        start: open.start,
+                        end: open.last,
+                    });
+                }
+                if let Some(previous) = &previous {
+                    if timestamp < previous.start {
+                        report(line, Problem::OutOfOrder(previous.line));
+                    } else if timestamp < previous.end {
+                        report(line, Problem::Overlap(previous.line));
+                    }
+                }
+                current = Some(OpenSession {
+                    line,
+                    start: timestamp,
+                    last: timestamp,
+                    paused: false,
+                });
+            }
+            ('+', Some(open)) => {
+                if timestamp < open.start {
+                    report(line, Problem::EndBeforeStart);
+                } else if timestamp < open.last {
+                    report(line, Problem::TimestampOutOfOrder);
+                }
+                previous = Some(ClosedSession {
+                    line: open.line,
+                    start: open.start,
+                    end: timestamp,
+                });
+                current = None;
+            }
+            ('p', Some(open)) if !open.paused => {
+                if timestamp < open.last {
+                    report(line, Problem::TimestampOutOfOrder);
+                }
+                open.last = timestamp;
+                open.paused = true;
+            }
+            ('r', Some(open)) if open.paused => {
+                if timestamp < open.last {
+                    report(line, Problem::TimestampOutOfOrder);
+                }
+                open.last = timestamp;
+                open.paused = false;
+            }
+            _ => report(line, Problem::UnexpectedMacro(text)),
+        }
+    }
+
+    diagnostics.sort_by_key(|d| d.line);
+    diagnostics
+}
+
+pub fn check_file(path: impl AsRef<Path>) -> Result<Vec<Diagnostic>> {
+    let lines = crypt::open(path)?.lines().collect::<Result<Vec<_>, _>>()?;
+    Ok(check(lines.into_iter()))
+}
+
+/// Prints the problems of the session files of the project, as in `clockin check`. Returns
+/// whether there were any.
+pub fn print_problems(path: &Path) -> Result<bool> {
+    let files = file::session_files(path)?;
+    let mut found = false;
+    for path in &files {
+        let diagnostics = check_file(path)?;
+        for diagnostic in &diagnostics {
+            if files.len() > 1 {
+                print!("{}: ", path.file_name().unwrap().to_string_lossy());
+            }
+            println!("line {}: {}", diagnostic.line, diagnostic.problem);
+        }
+        found |= !diagnostics.is_empty();
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Problem, check};
+
+    #[test]
+    fn problems() {
+        let file = [
+            "%-2000-01-01T10:00:00+00:00",
+            "%+2000-01-01T12:00:00+00:00",
+            "",
+            "%-2000-01-01T11:00:00+00:00",
+            "%p2000-01-01T11:30:00+00:00",
+            "%p2000-01-01T11:40:00+00:00",
+            "%-2000-01-01T09:00:00+00:00",
+            "%+2000-01-01T08:00:00+00:00",
+            "%+2000-01-01T13:00:00+00:00",
+            "%-2000-01-01T1x:00:00+00:00",
+        ];
+
+        assert_eq!(
+            check(file.into_iter().map(str::to_owned)),
+            vec![
+                Diagnostic {
+                    line: 4,
+                    problem: Problem::Overlap(1)
+                },
+                Diagnostic {
+                    line: 4,
+                    problem: Problem::Dangling
+                },
+                Diagnostic {
+                    line: 6,
+                    problem: Problem::UnexpectedMacro(file[5].to_owned())
+                },
+                Diagnostic {
+                    line: 7,
+                    problem: Problem::OutOfOrder(4)
+                },
+                Diagnostic {
+                    line: 8,
+                    problem: Problem::EndBeforeStart
+                },
+                Diagnostic {
+                    line: 9,
+                    problem: Problem::UnexpectedMacro(file[8].to_owned())
+                },
+                Diagnostic {
+                    line: 10,
+                    problem: Problem::MalformedMacro(file[9].to_owned())
+                },
+            ]
+        );
+    }
+}
@@ -1,20 +1,219 @@
-use std::ops::Bound;
+use std::{ops::Bound, path::PathBuf, str::FromStr, sync::OnceLock};
 
-use chrono::{FixedOffset, Local, NaiveDate};
-use clap::{Parser, Subcommand};
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeDelta, Weekday,
+};
+use clap::{Parser, Subcommand, ValueEnum};
 
 const UNBOUNDED_VALUE: &str = "unbounded";
+const TIMEZONE_HELP: &str = "offset used to split days, defaults to the project's timezone, the configured one or the local one";
+const TAG_HELP: &str = "only count sessions with this #tag, can be repeated to require several";
+const SUB_PROJECT_HELP: &str = "only count sessions of this sub-project or of the ones nested in it, can be repeated to take in several";
+const ID_HELP: &str =
+    "the session to change instead, by the id printed by the log command or a unique prefix of it";
+const HOST_HELP: &str = "only count sessions recorded on this machine, as in their %h line, can be repeated to take in several";
+const GREP_HELP: &str = "keep only the sessions whose description matches this regular expression";
+const INCLUDE_ARCHIVED_HELP: &str = "also take in the projects marked as archived";
+const OUTPUT_HELP: &str = "write the report to this file instead of the standard output";
+const MERGE_GAP_HELP: &str = "treat sessions separated by less than this many minutes as one, defaults to the configured merge-gap or 0";
+const ROUND_HELP: &str = "round to a multiple of this step, like 15min, 30s or 1h, optionally followed by :up, :nearest (default) or :down, defaults to the configured round";
+const NOTIFY_AFTER_HELP: &str =
+    "show a desktop notification when the session runs this long without pauses, like 4h or 90min";
+const DEFAULT_TEMPLATE: &str = "{state} {elapsed} {project}";
+const TEMPLATE_HELP: &str =
+    "line to print, with the placeholders {state}, {project}, {elapsed}, {start} and {description}";
+const MONTH_RANGE_HELP: &str =
+    "only count the month, as YYYY-MM, current or last, instead of --from and --to";
+const WEEK_RANGE_HELP: &str = "only count the week, current or last starting on the configured week-start, or YYYY-Www for an ISO week from monday to sunday, instead of --from and --to";
+const ROUND_PER_HELP: &str = "what gets rounded, defaults to the configured round-per or session, days are rounded per sub-project in reports split by sub-project";
+
+static WEEK_START: OnceLock<Weekday> = OnceLock::new();
+
+/// Sets the first day of the weeks of `week-start`, `week-end` and `--week current`/`last`, only
+/// the first call has an effect.
+pub fn set_week_start(week_start: Weekday) {
+    let _ = WEEK_START.set(week_start);
+}
+
+fn week_start() -> Weekday {
+    WEEK_START.get().copied().unwrap_or(Weekday::Mon)
+}
+
+/// Parses a date as `YYYY-MM-DD` or relative to today: `today`, `yesterday`, some days, weeks,
+/// months or years ago like `-7d`, `-2w`, `-1m` or `-1y`, the last of a weekday before today
+/// like `last-monday`, or the first or last day of the current week, month or year like
+/// `month-start` or `year-end`, the weeks starting on the given day.
+fn parse_date(s: &str, today: NaiveDate, week_start: Weekday) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(date);
+    }
+    match s {
+        "today" => return Some(today),
+        "yesterday" => return today.pred_opt(),
+        _ => {}
+    }
+    if let Some(ago) = s.strip_prefix('-') {
+        let (number, unit) = ago.split_at(ago.char_indices().next_back()?.0);
+        let number: u32 = number.parse().ok()?;
+        return match unit {
+            "d" => today.checked_sub_days(Days::new(number.into())),
+            "w" => today.checked_sub_days(Days::new(7 * u64::from(number))),
+            "m" => today.checked_sub_months(Months::new(number)),
+            "y" => today.checked_sub_months(Months::new(12 * number)),
+            _ => None,
+        };
+    }
+    if let Some(weekday) = s.strip_prefix("last-") {
+        let weekday: Weekday = weekday.parse().ok()?;
+        let days = match today.weekday().days_since(weekday) {
+            0 => 7,
+            days => days,
+        };
+        return today.checked_sub_days(Days::new(days.into()));
+    }
+    let (period, end) = s.rsplit_once('-')?;
+    let (first, last) = match period {
+        "week" => {
+            let week = today.week(week_start);
+            (week.first_day(), week.last_day())
+        }
+        "month" => {
+            let first = today.with_day(1)?;
+            (first, first.checked_add_months(Months::new(1))?.pred_opt()?)
+        }
+        "year" => (
+            today.with_ordinal(1)?,
+            NaiveDate::from_ymd_opt(today.year(), 12, 31)?,
+        ),
+        _ => return None,
+    };
+    match end {
+        "start" => Some(first),
+        "end" => Some(last),
+        _ => None,
+    }
+}
 
 fn parse_bound_naive_date(s: &str) -> Result<Bound<NaiveDate>, String> {
-    if s == "unbounded" {
+    if s == UNBOUNDED_VALUE {
         Ok(Bound::Unbounded)
     } else {
-        Ok(Bound::Included(
-            NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|err| format!("{:#}", err))?,
-        ))
+        let date = parse_date(s, Local::now().date_naive(), week_start()).ok_or(format!(
+            "invalid date {s:?}, expected YYYY-MM-DD, today, yesterday, -7d, -2w, -1m, -1y, last-monday, week-start, month-end, year-start, ..."
+        ))?;
+        Ok(Bound::Included(date))
     }
 }
 
+/// Parses a month as `YYYY-MM`, returning its first day.
+fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{s}-01"), "%Y-%m-%d")
+        .map_err(|_| format!("invalid month {s:?}, expected YYYY-MM"))
+}
+
+/// Month as `YYYY-MM`, `current` or `last`.
+fn month_range(s: &str, today: NaiveDate) -> Option<DateRange> {
+    let first = match s {
+        "current" => today.with_day(1)?,
+        "last" => today.with_day(1)?.checked_sub_months(Months::new(1))?,
+        _ => parse_month(s).ok()?,
+    };
+    Some(DateRange {
+        first,
+        last: first.checked_add_months(Months::new(1))?.pred_opt()?,
+    })
+}
+
+fn parse_month_range(s: &str) -> Result<DateRange, String> {
+    month_range(s, Local::now().date_naive()).ok_or(format!(
+        "invalid month {s:?}, expected YYYY-MM, current or last"
+    ))
+}
+
+/// Week as `current` or `last`, starting on the given day, or as `YYYY-Www`, which is always the
+/// ISO week from monday to sunday.
+fn week_range(s: &str, today: NaiveDate, week_start: Weekday) -> Option<DateRange> {
+    let week = match s {
+        "current" => today.week(week_start),
+        "last" => today.checked_sub_days(Days::new(7))?.week(week_start),
+        _ => {
+            let (year, week) = s.split_once("-W")?;
+            NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon)?
+                .week(Weekday::Mon)
+        }
+    };
+    Some(DateRange {
+        first: week.first_day(),
+        last: week.last_day(),
+    })
+}
+
+fn parse_week_range(s: &str) -> Result<DateRange, String> {
+    week_range(s, Local::now().date_naive(), week_start()).ok_or(format!(
+        "invalid week {s:?}, expected YYYY-Www, current or last"
+    ))
+}
+
+/// Parses a number of minutes that splits the day in equal intervals.
+fn parse_interval(s: &str) -> Result<i64, String> {
+    let minutes: i64 = s
+        .parse()
+        .map_err(|_| format!("invalid number of minutes {s:?}"))?;
+    if minutes <= 0 || (24 * 60) % minutes != 0 {
+        return Err(format!("{minutes} minutes do not divide the day"));
+    }
+    Ok(minutes)
+}
+
+/// Parses a positive amount of time like `30s`, `50min` or `4h`.
+fn parse_duration(s: &str) -> Result<TimeDelta, String> {
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let number = number.parse().map_err(|_| format!("invalid time {s:?}"))?;
+    let duration = match unit {
+        "s" => TimeDelta::try_seconds(number),
+        "m" | "min" => TimeDelta::try_minutes(number),
+        "h" => TimeDelta::try_hours(number),
+        _ => return Err(format!("unknown unit {unit:?}, expected s, min or h")),
+    }
+    .ok_or(format!("time {s:?} is too long"))?;
+    if duration.is_zero() {
+        return Err("the time can not be zero".to_owned());
+    }
+    Ok(duration)
+}
+
+fn parse_rounding(s: &str) -> Result<Rounding, String> {
+    let (step, mode) = s.split_once(':').unwrap_or((s, "nearest"));
+    let mode = RoundingMode::from_str(mode, true)?;
+    let step = parse_duration(step).map_err(|err| format!("invalid rounding step: {err}"))?;
+    Ok(Rounding { step, mode })
+}
+
+fn parse_datetime(s: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(s).or_else(|_| {
+        [
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%dT%H:%M",
+        ]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| {
+            ["%H:%M:%S", "%H:%M"]
+                .iter()
+                .find_map(|format| NaiveTime::parse_from_str(s, format).ok())
+                .map(|time| Local::now().date_naive().and_time(time))
+        })
+        .and_then(|datetime| datetime.and_local_timezone(Local).earliest())
+        .map(|datetime| datetime.fixed_offset())
+        .ok_or(format!(
+            "invalid datetime {s:?}, expected RFC 3339, \"YYYY-MM-DD HH:MM[:SS]\" or \"HH:MM[:SS]\""
+        ))
+    })
+}
+
 #[derive(Debug, Parser)]
 #[command(name = "Clockin")]
 #[command(version)]
@@ -22,6 +221,21 @@ fn parse_bound_naive_date(s: &str) -> Result<Bound<NaiveDate>, String> {
 pub struct Args {
     #[command(subcommand)]
     pub command: Option<Command>,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "language of the reports, defaults to the configured one or Spanish"
+    )]
+    pub lang: Option<Lang>,
+    #[arg(
+        long = "file",
+        global = true,
+        env = "CLOCKIN_FILE",
+        value_name = "FILE",
+        help = "file of sessions to use instead of looking for the project"
+    )]
+    pub clockin_file: Option<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -29,42 +243,471 @@ pub enum Command {
     #[command(about = "create a project and link the current directory to it")]
     Link {
         name: String,
+        #[arg(
+            long,
+            value_enum,
+            num_args = 0..=1,
+            default_missing_value = "age",
+            help = "keep the project file encrypted, with age unless gpg is given"
+        )]
+        encrypt: Option<Encryption>,
+        #[arg(
+            long,
+            requires = "encrypt",
+            help = "age recipient or gpg key to encrypt for, gpg uses the default key without it"
+        )]
+        recipient: Option<String>,
+    },
+    #[command(about = "remove the link of the current directory, keeping the project")]
+    Unlink,
+    #[command(
+        about = "rename a project, fixing the .clockin links under the configured workspace-roots"
+    )]
+    Rename { old: String, new: String },
+    #[command(about = "delete a project, keeping a backup of its times file")]
+    Delete {
+        #[arg(help = "name of the project, the current one when omitted")]
+        project: Option<String>,
+        #[arg(short, long, help = "do not ask for confirmation")]
+        yes: bool,
     },
     #[command(about = "start a time tracking session")]
-    In,
-    WeekSummary,
+    In {
+        #[arg(long, value_parser = parse_datetime, help = "start the session at an earlier time")]
+        at: Option<DateTime<FixedOffset>>,
+        #[arg(
+            short,
+            long,
+            help = "write the session description and return without opening the editor, clock out with the out command"
+        )]
+        message: Option<String>,
+        #[arg(
+            short,
+            long,
+            help = "record the start and return without opening the editor, clock out with the out command"
+        )]
+        detach: bool,
+    },
+    #[command(about = "finish the running time tracking session")]
+    Out,
+    #[command(about = "discard the running session")]
+    Cancel {
+        #[arg(short, long, help = "do not ask for confirmation")]
+        yes: bool,
+    },
+    #[command(about = "modify the last session")]
+    Amend {
+        #[arg(long, help = ID_HELP)]
+        id: Option<String>,
+        #[arg(short, long, value_parser = parse_datetime)]
+        start: Option<DateTime<FixedOffset>>,
+        #[arg(short, long, value_parser = parse_datetime)]
+        end: Option<DateTime<FixedOffset>>,
+        #[arg(short, long, help = "replace the session description")]
+        message: Option<String>,
+    },
+    #[command(about = "split the session running at the given instant in two")]
+    Split {
+        #[arg(long, help = ID_HELP)]
+        id: Option<String>,
+        #[arg(value_parser = parse_datetime)]
+        at: DateTime<FixedOffset>,
+    },
+    #[command(about = "print the last sessions, newest first")]
+    Log {
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+        #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+        format: LogFormat,
+    },
+    #[command(about = "pause the running session")]
+    Pause,
+    #[command(about = "resume the paused session")]
+    Resume,
+    #[command(about = "pause the running session while the user is idle")]
+    IdleWatch {
+        #[arg(
+            short,
+            long,
+            default_value_t = 5,
+            help = "minutes without input before pausing"
+        )]
+        threshold: u64,
+    },
+    #[command(about = "show the running session, exits with code 3 if there is none")]
+    Status {
+        #[arg(
+            long,
+            help = "print a single line following --template, exits with code 0 even if there is no session"
+        )]
+        oneline: bool,
+        #[arg(long, requires = "oneline", default_value = DEFAULT_TEMPLATE, help = TEMPLATE_HELP)]
+        template: String,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(
+        about = "print a short segment with the running session for shell prompts, nothing if there is none"
+    )]
+    Prompt,
+    #[command(about = "print the JSON Schema of the JSON outputs")]
+    Schema,
+    #[command(about = "print the running session and its project for a status line or prompt")]
+    Widget {
+        #[arg(long, value_enum)]
+        style: WidgetStyle,
+    },
+    #[command(about = "add a finished session retroactively")]
+    Add {
+        #[arg(short, long, value_parser = parse_datetime)]
+        start: DateTime<FixedOffset>,
+        #[arg(short, long, value_parser = parse_datetime)]
+        end: DateTime<FixedOffset>,
+        #[arg(short, long, default_value = "")]
+        message: String,
+        #[arg(long, help = "add the session even if it overlaps with existing ones")]
+        force: bool,
+    },
+    WeekSummary {
+        #[arg(long, help = MERGE_GAP_HELP)]
+        merge_gap: Option<i64>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(long, help = "print only the total of each week, without the days")]
+        totals_only: bool,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
     #[command(
         alias = "bitacora",
         about = "print a report of time spent on the project broken down by month and by day"
     )]
-    Summary {
-        #[arg(short, long, default_value = UNBOUNDED_VALUE, value_parser = parse_bound_naive_date)]
+    Summary(SummaryArgs),
+    #[command(about = "write an invoice for the time worked in a month")]
+    Invoice(InvoiceArgs),
+    #[command(
+        about = "print the surplus or deficit of worked time against the schedule of the project"
+    )]
+    Overtime {
+        #[arg(
+            short,
+            long,
+            default_value = UNBOUNDED_VALUE,
+            allow_hyphen_values = true,
+            value_parser = parse_bound_naive_date,
+            help = "defaults to the first tracked day"
+        )]
         from: Bound<NaiveDate>,
-        #[arg(short, long, default_value = UNBOUNDED_VALUE, value_parser = parse_bound_naive_date)]
+        #[arg(
+            short,
+            long,
+            default_value = UNBOUNDED_VALUE,
+            allow_hyphen_values = true,
+            value_parser = parse_bound_naive_date,
+            help = "defaults to today"
+        )]
         to: Bound<NaiveDate>,
-        #[arg(long, default_value_t = Local::now().fixed_offset().timezone())]
-        timezone: FixedOffset,
-        #[arg(short, long, default_value_t = 1)]
-        version: u32,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
     },
-    #[command(about = "analyze working hours")]
-    WorkTimeAnalysis {
-        #[arg(short, long, default_value = UNBOUNDED_VALUE, value_parser = parse_bound_naive_date)]
+    #[command(
+        about = "list the workdays of the schedule without any session, skipping the holidays of the project"
+    )]
+    Gaps {
+        #[arg(
+            short,
+            long,
+            default_value = UNBOUNDED_VALUE,
+            allow_hyphen_values = true,
+            value_parser = parse_bound_naive_date,
+            help = "defaults to the first tracked day"
+        )]
         from: Bound<NaiveDate>,
-        #[arg(short, long, default_value = UNBOUNDED_VALUE, value_parser = parse_bound_naive_date)]
+        #[arg(
+            short,
+            long,
+            default_value = UNBOUNDED_VALUE,
+            allow_hyphen_values = true,
+            value_parser = parse_bound_naive_date,
+            help = "defaults to yesterday"
+        )]
         to: Bound<NaiveDate>,
-        #[arg(long, default_value_t = Local::now().fixed_offset().timezone())]
-        timezone: FixedOffset,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "print statistics about the sessions")]
+    Stats {
+        #[command(subcommand)]
+        kind: StatsCommand,
+    },
+    #[cfg(feature = "chart")]
+    #[command(about = "draw a chart of the worked time into an SVG or PNG image")]
+    Chart {
+        #[arg(long, value_enum, default_value_t = ChartKind::Daily)]
+        kind: ChartKind,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(long, default_value_t = 60, value_parser = parse_interval, help = "length in minutes of each interval of the timeofday chart, must divide the day")]
+        interval: i64,
+        #[arg(
+            short,
+            long,
+            help = "image to write, its extension (.svg or .png) picks the format"
+        )]
+        output: PathBuf,
+    },
+    #[command(about = "generate a report to share")]
+    Report {
+        #[command(subcommand)]
+        kind: ReportCommand,
+    },
+    #[command(about = "compare the time worked on several months, in total and by sub-project")]
+    Compare {
+        #[arg(long, value_delimiter = ',', value_parser = parse_month, conflicts_with = "last", help = "months to compare as YYYY-MM, separated by commas")]
+        months: Vec<NaiveDate>,
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..), help = "compare the current month with the ones before it, this many in total")]
+        last: u32,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "print a calendar of the year shaded by the hours worked each day")]
+    Heatmap {
+        #[arg(long, value_parser = clap::value_parser!(i32).range(1..=9999), help = "year to show, the current one by default")]
+        year: Option<i32>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "print the current and the longest streaks of consecutive worked days")]
+    Streaks {
+        #[arg(
+            long,
+            default_value_t = 1,
+            help = "minutes that must be worked for a day to count"
+        )]
+        min_minutes: u64,
+        #[arg(
+            long,
+            help = "only consider the workdays of the schedule, other days do not break streaks"
+        )]
+        workdays: bool,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(about = "print every session, one per line, for other programs to consume")]
+    Sessions {
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(long, value_enum, default_value_t = SessionsFormat::Ndjson)]
+        format: SessionsFormat,
+        #[arg(long, help = GREP_HELP)]
+        grep: Option<String>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "print the total time spent on each ticket, like PROJ-123 or #456")]
+    Tickets {
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "print the tasks that took the most time, longest first")]
+    Top {
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = 10,
+            help = "how many tasks to print"
+        )]
+        count: usize,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "print the total time spent on each #tag")]
+    Tags {
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "analyze working hours")]
+    WorkTimeAnalysis(WorkTimeAnalysisArgs),
+    #[command(about = "serve a JSON API and a dashboard over HTTP")]
+    Serve {
+        #[arg(long, default_value = "127.0.0.1", help = "address to listen on")]
+        address: String,
+        #[arg(short, long, default_value_t = 7878)]
+        port: u16,
+        #[arg(
+            long,
+            env = "CLOCKIN_TOKEN",
+            hide_env_values = true,
+            help = "bearer token required to start, stop or annotate sessions with POST requests, which are rejected without one"
+        )]
+        token: Option<String>,
+        #[arg(long, help = "expose Prometheus metrics on /metrics")]
+        metrics: bool,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(about = "answer JSON requests to show and control the session on a unix socket")]
+    Daemon {
+        #[arg(
+            long,
+            help = "socket to listen on, $XDG_RUNTIME_DIR/clockin.sock by default"
+        )]
+        socket: Option<PathBuf>,
+        #[arg(long, value_parser = parse_duration, help = NOTIFY_AFTER_HELP)]
+        notify_after: Option<TimeDelta>,
+    },
+    #[command(about = "publish the session on the D-Bus session bus as dev.clockin.Tracker")]
+    Dbus,
+    #[command(about = "show a desktop notification every so often while a session is running")]
+    Remind {
+        #[arg(long, value_parser = parse_duration, help = "time between reminders, like 50min")]
+        every: TimeDelta,
+        #[arg(long, default_value = "Time for a break")]
+        message: String,
     },
     #[command(about = "subscribe to events")]
-    Subscribe,
+    Subscribe {
+        #[arg(
+            long,
+            conflicts_with_all = ["on_suspend", "notify_after"],
+            help = "follow every project instead of the current one, showing the running one or else the last one worked on"
+        )]
+        all: bool,
+        #[arg(
+            long,
+            value_enum,
+            help = "what to do with the running session when the system suspends"
+        )]
+        on_suspend: Option<SuspendAction>,
+        #[arg(
+            long,
+            requires = "on_suspend",
+            help = "undo the suspend action when the system wakes up"
+        )]
+        resume_on_wake: bool,
+        #[arg(long, value_parser = parse_duration, help = NOTIFY_AFTER_HELP)]
+        notify_after: Option<TimeDelta>,
+        #[arg(long, value_enum, default_value_t = SubscribeFormat::Text)]
+        format: SubscribeFormat,
+        #[arg(long, default_value = DEFAULT_TEMPLATE, help = TEMPLATE_HELP)]
+        template: String,
+        #[arg(
+            long,
+            help = "shell command to run when the session starts, pauses or stops, with the placeholders of --template already quoted and the event in CLOCKIN_* environment variables"
+        )]
+        exec: Option<String>,
+    },
     #[command(about = "get worked time")]
-    GetWorkedTime {
+    GetWorkedTime(GetWorkedTimeArgs),
+    #[command(about = "export the sessions to other formats")]
+    Export {
+        #[command(subcommand)]
+        format: ExportCommand,
+    },
+    #[command(about = "import sessions from other time trackers into the project")]
+    Import {
+        #[command(subcommand)]
+        source: ImportCommand,
+    },
+    #[command(
+        about = "add the sessions of another copy of the project, like one edited on another machine"
+    )]
+    Merge {
+        other: PathBuf,
+        #[arg(
+            long,
+            value_enum,
+            help = "what to keep when a session of the other copy overlaps with ours, asked for each one by default"
+        )]
+        overlaps: Option<OverlapPolicy>,
+    },
+    #[command(about = "list the time budgets of the sub-projects and how much of them is left")]
+    Budget {
         #[command(subcommand)]
-        specification: GetWorkedTimeCommand,
+        command: Option<BudgetCommand>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(about = "show the flexitime balance, the time banked or owed against the schedule")]
+    Flexitime {
+        #[command(subcommand)]
+        command: Option<FlexitimeCommand>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(
+        about = "list the projects in the data directory with their last session, state and total time"
+    )]
+    Projects {
+        #[arg(long, help = INCLUDE_ARCHIVED_HELP)]
+        include_archived: bool,
+    },
+    #[command(about = "print the project the commands would use, its file and how it was found")]
+    Which,
+    #[command(about = "show or change the settings of the project")]
+    Project {
+        #[command(subcommand)]
+        command: ProjectCommand,
     },
     #[command(about = "open the project times file in the editor")]
     Edit,
+    #[command(
+        alias = "doctor",
+        about = "check the project times file for problems, exits with code 1 if any is found"
+    )]
+    Check,
+    #[command(
+        about = "repair the project times file: close dangling sessions, sort, normalize timestamps and remove duplicates"
+    )]
+    Fix {
+        #[arg(
+            long,
+            default_value_t = 0,
+            help = "minutes after their last timestamp to close dangling sessions at"
+        )]
+        close_after: i64,
+    },
+    #[command(about = "list the backups of the project times file, or restore one of them")]
+    Restore {
+        #[arg(help = "name of the backup to restore, as listed when omitted")]
+        backup: Option<String>,
+        #[arg(short, long, help = "do not ask for confirmation")]
+        yes: bool,
+    },
     #[command(
         about = "open a subshell inside the clockin data directory, respects SHELL environment variable"
     )]
@@ -75,24 +718,670 @@ pub enum Command {
     Exec {
         command: String,
     },
+    #[command(
+        about = "commit, pull and push the data directory, merging the sessions of conflicting project files"
+    )]
+    Sync,
+    #[command(about = "version the data directory with git")]
+    Git {
+        #[command(subcommand)]
+        command: GitCommand,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SummaryArgs {
+    #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+    pub from: Bound<NaiveDate>,
+    #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+    pub to: Bound<NaiveDate>,
+    #[arg(long, value_parser = parse_month_range, conflicts_with_all = ["from", "to", "week"], help = MONTH_RANGE_HELP)]
+    pub month: Option<DateRange>,
+    #[arg(long, value_parser = parse_week_range, conflicts_with_all = ["from", "to"], help = WEEK_RANGE_HELP)]
+    pub week: Option<DateRange>,
+    #[arg(long, help = TIMEZONE_HELP)]
+    pub timezone: Option<FixedOffset>,
+    #[arg(short, long, default_value_t = 1)]
+    pub version: u32,
+    #[arg(long, value_enum, default_value_t = SummaryFormat::Text)]
+    pub format: SummaryFormat,
+    #[arg(long, value_enum, default_value_t = SummaryGrouping::Day)]
+    pub group_by: SummaryGrouping,
+    #[arg(
+        long,
+        conflicts_with = "group_by",
+        help = "print only the total of each month and of the whole range"
+    )]
+    pub totals: bool,
+    #[arg(
+        long,
+        help = "add up the sessions of every project in the data directory, with a subtotal per project"
+    )]
+    pub all_projects: bool,
+    #[arg(long, requires = "all_projects", help = INCLUDE_ARCHIVED_HELP)]
+    pub include_archived: bool,
+    #[arg(long, help = MERGE_GAP_HELP)]
+    pub merge_gap: Option<i64>,
+    #[arg(long, help = TAG_HELP)]
+    pub tag: Vec<String>,
+    #[arg(long, help = SUB_PROJECT_HELP)]
+    pub sub_project: Vec<String>,
+    #[arg(long, help = HOST_HELP)]
+    pub host: Vec<String>,
+    #[arg(long, help = GREP_HELP)]
+    pub grep: Option<String>,
+    #[arg(long, value_parser = parse_rounding, help = ROUND_HELP)]
+    pub round: Option<Rounding>,
+    #[arg(long, value_enum, help = ROUND_PER_HELP)]
+    pub round_per: Option<RoundingScope>,
+    #[arg(short, long, help = OUTPUT_HELP)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct InvoiceArgs {
+    #[arg(
+        short,
+        long,
+        value_parser = parse_month,
+        help = "month to bill, as YYYY-MM, defaults to the current one"
+    )]
+    pub month: Option<NaiveDate>,
+    #[arg(long, help = "hourly rate, defaults to the project's rate")]
+    pub rate: Option<f64>,
+    #[arg(long, help = "invoice number, defaults to the billed month")]
+    pub number: Option<String>,
+    #[arg(long, value_enum, default_value_t = InvoiceGrouping::Day)]
+    pub by: InvoiceGrouping,
+    #[arg(long, value_enum, default_value_t = InvoiceFormat::Markdown)]
+    pub format: InvoiceFormat,
+    #[arg(long, value_parser = parse_rounding, help = ROUND_HELP)]
+    pub round: Option<Rounding>,
+    #[arg(long, value_enum, help = ROUND_PER_HELP)]
+    pub round_per: Option<RoundingScope>,
+    #[arg(long, help = TIMEZONE_HELP)]
+    pub timezone: Option<FixedOffset>,
+    #[arg(short, long, help = OUTPUT_HELP)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WorkTimeAnalysisArgs {
+    #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+    pub from: Bound<NaiveDate>,
+    #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+    pub to: Bound<NaiveDate>,
+    #[arg(long, value_parser = parse_month_range, conflicts_with_all = ["from", "to", "week"], help = MONTH_RANGE_HELP)]
+    pub month: Option<DateRange>,
+    #[arg(long, value_parser = parse_week_range, conflicts_with_all = ["from", "to"], help = WEEK_RANGE_HELP)]
+    pub week: Option<DateRange>,
+    #[arg(long, help = TIMEZONE_HELP)]
+    pub timezone: Option<FixedOffset>,
+    #[arg(long, default_value_t = 30, value_parser = parse_interval, help = "length in minutes of each interval, must divide the day")]
+    pub interval: i64,
+    #[arg(
+        long,
+        default_value_t = 800.0,
+        help = "length of a bar holding all the worked time"
+    )]
+    pub bar_width: f64,
+    #[arg(long, help = "print the hours worked instead of the percentage")]
+    pub absolute: bool,
+    #[arg(short, long, help = OUTPUT_HELP)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct GetWorkedTimeArgs {
+    #[arg(long, help = MERGE_GAP_HELP)]
+    pub merge_gap: Option<i64>,
+    #[arg(long, help = TAG_HELP)]
+    pub tag: Vec<String>,
+    #[arg(long, help = SUB_PROJECT_HELP)]
+    pub sub_project: Vec<String>,
+    #[arg(long, help = HOST_HELP)]
+    pub host: Vec<String>,
+    #[arg(long, value_parser = parse_rounding, help = ROUND_HELP)]
+    pub round: Option<Rounding>,
+    #[arg(long, value_enum, help = ROUND_PER_HELP)]
+    pub round_per: Option<RoundingScope>,
+    #[arg(long, value_enum, default_value_t = WorkedTimeFormat::Seconds)]
+    pub format: WorkedTimeFormat,
+    #[arg(
+        long,
+        value_enum,
+        help = "break the time down instead of printing the total"
+    )]
+    pub by: Option<WorkedTimeGrouping>,
+    #[arg(
+        long,
+        help = "add up the sessions of every project in the data directory"
+    )]
+    pub all_projects: bool,
+    #[arg(long, requires = "all_projects", help = INCLUDE_ARCHIVED_HELP)]
+    pub include_archived: bool,
+    #[command(subcommand)]
+    pub specification: GetWorkedTimeCommand,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SummaryFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SummaryGrouping {
+    /// the days of each month
+    Day,
+    /// a total per week, starting on the configured day
+    Week,
+    /// a total per month
+    Month,
+    /// a total per top level sub-project, longest first
+    SubProject,
+    /// a total per machine the sessions were recorded on, longest first
+    Host,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RoundingMode {
+    Up,
+    Nearest,
+    Down,
+}
+
+/// Dates from the first to the last day of a month or a week, both included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+}
+
+impl DateRange {
+    pub fn bounds(self) -> (Bound<NaiveDate>, Bound<NaiveDate>) {
+        (Bound::Included(self.first), Bound::Included(self.last))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rounding {
+    pub step: TimeDelta,
+    pub mode: RoundingMode,
+}
+
+/// Parsed like the `--round` argument.
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_rounding(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RoundingScope {
+    /// each session on its own
+    #[default]
+    Session,
+    /// the total of each day
+    Day,
+}
+
+/// Parsed like the `--round-per` argument.
+impl FromStr for RoundingScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    /// English
+    En,
+    /// Spanish
+    #[default]
+    Es,
+}
+
+/// Parsed like the `--lang` argument.
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum InvoiceGrouping {
+    /// a line per worked day
+    Day,
+    /// a line per top level sub-project
+    SubProject,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum InvoiceFormat {
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ChartKind {
+    /// hours worked each day
+    Daily,
+    /// hours worked each week
+    Weekly,
+    /// how the worked time spreads along the day
+    #[value(name = "timeofday")]
+    TimeOfDay,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SessionsFormat {
+    /// a JSON object per line
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum WorkedTimeFormat {
+    /// whole seconds, like 22320
+    Seconds,
+    /// like 06:12:00
+    Hms,
+    /// like 6.20
+    DecimalHours,
+    /// like 6h 12m
+    Human,
+    /// a JSON object with the seconds
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum WorkedTimeGrouping {
+    /// a line per top level sub-project, longest first
+    SubProject,
+    /// a line per project, longest first, meant for --all-projects
+    Project,
+    /// a line per machine the sessions were recorded on, longest first
+    Host,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum WidgetStyle {
+    /// a tmux format string, for status-right
+    Tmux,
+    /// colored with ANSI escapes, for a starship custom module
+    Starship,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SubscribeFormat {
+    /// started, paused or finished, followed by the start, project and description
+    Text,
+    /// a JSON object per line with the state of the project and its running session
+    Json,
+    /// a custom module for Waybar, with the elapsed time updated every second
+    Waybar,
+    /// a line following --template, updated every second
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SuspendAction {
+    Pause,
+    Out,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum GetWorkedTimeCommand {
     #[command(about = "by date range")]
     ByDateRange {
-        #[arg(short, long, default_value = UNBOUNDED_VALUE, value_parser = parse_bound_naive_date)]
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
         from: Bound<NaiveDate>,
-        #[arg(short, long, default_value = UNBOUNDED_VALUE, value_parser = parse_bound_naive_date)]
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
         to: Bound<NaiveDate>,
-        #[arg(long, default_value_t = Local::now().fixed_offset().timezone())]
-        timezone: FixedOffset,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
     },
     #[command(about = "today")]
     Today {
-        #[arg(long, default_value_t = Local::now().fixed_offset().timezone())]
-        timezone: FixedOffset,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(about = "yesterday")]
+    Yesterday {
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(about = "this week up to today, weeks start on the configured week-start or monday")]
+    ThisWeek {
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(about = "this month up to today")]
+    ThisMonth {
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+    #[command(about = "the last days, today included")]
+    LastNDays {
+        #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+        n: u64,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
     },
     #[command(about = "last session")]
     LastSession,
 }
+
+#[derive(Debug, Subcommand)]
+pub enum BudgetCommand {
+    #[command(about = "set the time budget of a sub-project")]
+    Set {
+        sub_project: String,
+        #[arg(help = "time per period, like 40h/month or 90min/week")]
+        budget: String,
+    },
+    #[command(about = "remove the time budget of a sub-project")]
+    Remove { sub_project: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FlexitimeCommand {
+    #[command(about = "start counting the balance from a date, discarding previous adjustments")]
+    Start {
+        since: NaiveDate,
+        #[arg(
+            long,
+            allow_hyphen_values = true,
+            help = "balance carried over from before, like +8h or -90min"
+        )]
+        balance: Option<String>,
+    },
+    #[command(about = "add time to the balance, or remove it with a negative time")]
+    Adjust {
+        #[arg(allow_hyphen_values = true, help = "like +8h or -90min")]
+        time: String,
+        #[arg(long, help = "defaults to today")]
+        date: Option<NaiveDate>,
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProjectCommand {
+    #[command(about = "print the settings of the project")]
+    Show,
+    #[command(about = "change a setting of the project")]
+    Set { key: ProjectKey, value: String },
+    #[command(about = "remove a setting of the project")]
+    Unset { key: ProjectKey },
+    #[command(about = "POST to this URL when a session starts or ends")]
+    AddWebhook { url: String },
+    #[command(about = "stop notifying this URL")]
+    RemoveWebhook { url: String },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ProjectKey {
+    /// name of the client, shown in invoices
+    Client,
+    /// hourly rate used by invoices
+    Rate,
+    /// currency of the rate
+    Currency,
+    /// hours expected to be worked each week
+    WeeklyHours,
+    /// time expected to be worked each weekday, like "mon-fri 8h, sat 4h"
+    Schedule,
+    /// "true" to start the sessions of each year in their own file, like `project.2024`
+    YearlyFiles,
+    /// "true" to leave the project out of `projects` and `--all-projects`
+    Archived,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GitCommand {
+    #[command(
+        about = "make the data directory a git repository, committed after every change unless git-auto-commit is false"
+    )]
+    Init,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ReportCommand {
+    #[command(about = "self-contained HTML page with the time of each month, sub-project and day")]
+    Html {
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StatsCommand {
+    #[command(about = "histogram of the length of the sessions")]
+    Lengths {
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(long, default_value_t = 40, help = "length of the longest bar")]
+        bar_width: usize,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ExportCommand {
+    #[command(about = "iCalendar file with one event per worked stretch, split at the pauses")]
+    Ics {
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "hledger timeclock entries, the account is the project and its sub-projects")]
+    Timeclock {
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+        #[arg(short, long, help = OUTPUT_HELP)]
+        output: Option<PathBuf>,
+    },
+    #[cfg(feature = "sqlite")]
+    #[command(about = "SQLite database with the sessions and their totals per day and sub-project")]
+    Sqlite {
+        #[arg(help = "database file, its clockin tables are replaced")]
+        db: PathBuf,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        from: Bound<NaiveDate>,
+        #[arg(short, long, default_value = UNBOUNDED_VALUE, allow_hyphen_values = true, value_parser = parse_bound_naive_date)]
+        to: Bound<NaiveDate>,
+        #[arg(long, help = TIMEZONE_HELP)]
+        timezone: Option<FixedOffset>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encryption {
+    /// age, reading with the age-identity of the configuration
+    Age,
+    /// gpg, reading with the keys of the agent
+    Gpg,
+}
+
+/// Parsed like the `--encrypt` argument.
+impl FromStr for Encryption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OverlapPolicy {
+    /// the sessions of this copy
+    Ours,
+    /// the session of the other copy
+    Theirs,
+    /// every session, overlapping or not
+    Both,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportCommand {
+    #[command(about = "finished intervals of timewarrior, tags become #tags")]
+    Timewarrior {
+        #[arg(
+            long,
+            help = "timewarrior data directory, found like timewarrior does when omitted"
+        )]
+        data_dir: Option<PathBuf>,
+    },
+    #[command(about = "frames of Watson, the Watson project becomes the sub-project")]
+    Watson {
+        #[arg(help = "Watson frames file, usually ~/.config/watson/frames")]
+        frames: PathBuf,
+        #[arg(
+            long,
+            help = "import each Watson project into the clockin project of the same name instead"
+        )]
+        split_projects: bool,
+    },
+    #[command(about = "CLOCK entries of an Emacs org file, described by their heading")]
+    Org { file: PathBuf },
+    #[cfg(feature = "sqlite")]
+    #[command(about = "sessions of a database written by `clockin export sqlite`")]
+    Sqlite { db: PathBuf },
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeDelta, Weekday};
+
+    use super::{DateRange, month_range, parse_date, parse_duration, parse_rounding, week_range};
+
+    #[test]
+    fn relative_dates() {
+        // a wednesday
+        let today = NaiveDate::from_ymd_opt(2000, 3, 15).unwrap();
+        let parse = |s| parse_date(s, today, Weekday::Mon).map(|date| date.to_string());
+        assert_eq!(parse("2000-01-02").as_deref(), Some("2000-01-02"));
+        assert_eq!(parse("today").as_deref(), Some("2000-03-15"));
+        assert_eq!(parse("yesterday").as_deref(), Some("2000-03-14"));
+        assert_eq!(parse("-7d").as_deref(), Some("2000-03-08"));
+        assert_eq!(parse("-2w").as_deref(), Some("2000-03-01"));
+        assert_eq!(parse("-1m").as_deref(), Some("2000-02-15"));
+        assert_eq!(parse("-1y").as_deref(), Some("1999-03-15"));
+        assert_eq!(parse("last-monday").as_deref(), Some("2000-03-13"));
+        assert_eq!(parse("last-wed").as_deref(), Some("2000-03-08"));
+        assert_eq!(parse("week-start").as_deref(), Some("2000-03-13"));
+        assert_eq!(parse("week-end").as_deref(), Some("2000-03-19"));
+        assert_eq!(parse("month-start").as_deref(), Some("2000-03-01"));
+        assert_eq!(parse("month-end").as_deref(), Some("2000-03-31"));
+        assert_eq!(parse("year-start").as_deref(), Some("2000-01-01"));
+        assert_eq!(parse("year-end").as_deref(), Some("2000-12-31"));
+        for invalid in [
+            "",
+            "-",
+            "-d",
+            "-7x",
+            "-é",
+            "-7é",
+            "last-",
+            "month",
+            "month-middle",
+            "2000-02-30",
+        ] {
+            assert_eq!(parse(invalid), None, "{invalid}");
+        }
+        let sunday_week = |s| parse_date(s, today, Weekday::Sun).map(|date| date.to_string());
+        assert_eq!(sunday_week("week-start").as_deref(), Some("2000-03-12"));
+        assert_eq!(sunday_week("week-end").as_deref(), Some("2000-03-18"));
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90min"), Ok(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("2h"), Ok(TimeDelta::hours(2)));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration(&format!("{}h", i64::MAX)).is_err());
+        assert!(parse_rounding(&format!("{}min:up", i64::MAX)).is_err());
+    }
+
+    #[test]
+    fn month_and_week_ranges() {
+        let date = |s: &str| s.parse::<NaiveDate>().unwrap();
+        let range = |first, last| {
+            Some(DateRange {
+                first: date(first),
+                last: date(last),
+            })
+        };
+        let today = date("2000-03-15");
+        assert_eq!(
+            month_range("current", today),
+            range("2000-03-01", "2000-03-31")
+        );
+        assert_eq!(
+            month_range("last", today),
+            range("2000-02-01", "2000-02-29")
+        );
+        assert_eq!(
+            month_range("1999-12", today),
+            range("1999-12-01", "1999-12-31")
+        );
+        assert_eq!(
+            week_range("current", today, Weekday::Mon),
+            range("2000-03-13", "2000-03-19")
+        );
+        assert_eq!(
+            week_range("last", today, Weekday::Mon),
+            range("2000-03-06", "2000-03-12")
+        );
+        assert_eq!(
+            week_range("current", today, Weekday::Sun),
+            range("2000-03-12", "2000-03-18")
+        );
+        assert_eq!(
+            week_range("last", today, Weekday::Sun),
+            range("2000-03-05", "2000-03-11")
+        );
+        // the first ISO week of 2000 starts on monday 3rd, whatever the week start
+        assert_eq!(
+            week_range("2000-W01", today, Weekday::Sun),
+            range("2000-01-03", "2000-01-09")
+        );
+        assert_eq!(month_range("next", today), None);
+        assert_eq!(week_range("2000-W54", today, Weekday::Mon), None);
+    }
+}
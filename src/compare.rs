@@ -0,0 +1,196 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+    ops::Bound,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{FixedOffset, Local, Months, NaiveDate, TimeDelta};
+
+use crate::{
+    binnacle_2::{self, BinnacleData},
+    config,
+    format_util::{fmt_duration, fmt_signed_duration},
+    parser::{self, SessionIteratorClosingExt},
+    summary::{MonthId, NaiveDateExt},
+    writer::ReportOutput,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct MonthTotals {
+    pub month: MonthId,
+    pub total: Duration,
+    /// time of each top level sub-project
+    pub sub_projects: BTreeMap<String, Duration>,
+}
+
+/// Totals of each of the months, months without work have none.
+pub fn month_totals(data: &BinnacleData, months: &[MonthId]) -> Vec<MonthTotals> {
+    months
+        .iter()
+        .map(|&id| {
+            let mut totals = MonthTotals {
+                month: id,
+                total: Duration::ZERO,
+                sub_projects: BTreeMap::new(),
+            };
+            if let Some(month) = data.months.iter().find(|m| m.id == id) {
+                totals.total = month.total_time;
+                for sub_project in month.days.iter().flat_map(|d| &d.sub_projects) {
+                    if let [name] = sub_project.path.as_slice() {
+                        *totals.sub_projects.entry(name.clone()).or_default() +=
+                            sub_project.info.total_time;
+                    }
+                }
+            }
+            totals
+        })
+        .collect()
+}
+
+/// Difference with the previous value, along with the percentage change.
+fn fmt_change(previous: Duration, current: Duration) -> String {
+    let difference = TimeDelta::from_std(current).unwrap() - TimeDelta::from_std(previous).unwrap();
+    let change = if previous.is_zero() {
+        "new".to_owned()
+    } else {
+        format!(
+            "{:+.1}%",
+            100.0 * difference.as_seconds_f64() / previous.as_secs_f64()
+        )
+    };
+    format!("{} ({change})", fmt_signed_duration(&difference))
+}
+
+/// A block per month with its total and the time of each sub-project, compared with the month
+/// before it in the list.
+pub fn format(months: &[MonthTotals], out: &mut impl Write) -> io::Result<()> {
+    let width = months
+        .iter()
+        .flat_map(|m| m.sub_projects.keys())
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut previous: Option<&MonthTotals> = None;
+    for month in months {
+        let label = format!("{:04}-{:02}", month.month.year(), month.month.month() + 1);
+        match previous {
+            Some(previous) => writeln!(
+                out,
+                "{label}: {}  {}",
+                fmt_duration(&month.total),
+                fmt_change(previous.total, month.total)
+            )?,
+            None => writeln!(out, "{label}: {}", fmt_duration(&month.total))?,
+        }
+
+        let names: BTreeSet<&String> = month
+            .sub_projects
+            .keys()
+            .chain(previous.iter().flat_map(|p| p.sub_projects.keys()))
+            .collect();
+        for name in names {
+            let time = |m: &MonthTotals| m.sub_projects.get(name).copied().unwrap_or_default();
+            let current = time(month);
+            match previous {
+                Some(previous) => writeln!(
+                    out,
+                    "  {name:<width$} {}  {}",
+                    fmt_duration(&current),
+                    fmt_change(time(previous), current)
+                )?,
+                None => writeln!(out, "  {name:<width$} {}", fmt_duration(&current))?,
+            }
+        }
+        previous = Some(month);
+    }
+    Ok(())
+}
+
+/// Prints the comparison of `clockin compare`, of the given months or else of the last ones.
+pub fn print_comparison(
+    path: &Path,
+    months: Vec<NaiveDate>,
+    last: u32,
+    timezone: Option<FixedOffset>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let months: Vec<MonthId> = if months.is_empty() {
+        let today = Local::now().with_timezone(&timezone).date_naive();
+        (0..last)
+            .rev()
+            .map(|i| today.checked_sub_months(Months::new(i)).unwrap().month_id())
+            .collect()
+    } else {
+        months.iter().map(|m| m.month_id()).collect()
+    };
+
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now();
+    let data = binnacle_2::process(
+        sessions,
+        Bound::Included(months.iter().min().unwrap().first_day()),
+        Bound::Included(months.iter().max().unwrap().last_day()),
+        &timezone,
+    );
+    let mut out = ReportOutput::new(output);
+    format(&month_totals(&data, &months), &mut out)?;
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use chrono::Utc;
+
+    use super::{format, month_totals};
+    use crate::{binnacle_2, summary::MonthId, testing::session};
+
+    #[test]
+    fn compare() {
+        let data = binnacle_2::process(
+            [
+                session("2000-01-03T10:00:00Z")
+                    .end("2000-01-03T14:00:00Z")
+                    .description("backend: api")
+                    .build(),
+                session("2000-01-04T10:00:00Z")
+                    .end("2000-01-04T12:00:00Z")
+                    .description("frontend: form")
+                    .build(),
+                session("2000-02-01T10:00:00Z")
+                    .end("2000-02-01T13:00:00Z")
+                    .description("backend: api")
+                    .build(),
+                session("2000-02-02T10:00:00Z")
+                    .end("2000-02-02T11:00:00Z")
+                    .description("docs: readme")
+                    .build(),
+            ]
+            .into_iter(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+            &Utc,
+        );
+        let months = month_totals(&data, &[MonthId::new(2000, 0), MonthId::new(2000, 1)]);
+
+        let mut out = vec![];
+        format(&months, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "2000-01: 06:00:00\n  \
+             backend  04:00:00\n  \
+             frontend 02:00:00\n\
+             2000-02: 04:00:00  -02:00:00 (-33.3%)\n  \
+             backend  03:00:00  -01:00:00 (-25.0%)\n  \
+             docs     01:00:00  +01:00:00 (new)\n  \
+             frontend 00:00:00  -02:00:00 (-100.0%)\n"
+        );
+    }
+}
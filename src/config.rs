@@ -0,0 +1,178 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use anyhow::{Context, Result};
+use chrono::{FixedOffset, Local, TimeDelta, Weekday};
+use serde::{Deserialize, Deserializer, de::Error};
+
+use crate::{
+    cli::{Lang, Rounding, RoundingScope},
+    file, parser,
+};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Defaults of the user for every project, kept in `$XDG_CONFIG_HOME/clockin/config.toml`. The
+/// command line arguments take precedence over them.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// offset used to split days when neither the command line nor the project sets one
+    #[serde(default, deserialize_with = "parsed")]
+    pub timezone: Option<FixedOffset>,
+    /// offset the timestamps are written in instead of the local one, like `+00:00` to keep
+    /// files shared between timezones in UTC
+    #[serde(default, deserialize_with = "parsed")]
+    pub record_timezone: Option<FixedOffset>,
+    /// first day of the weeks in the weekly summary, monday by default
+    #[serde(default, deserialize_with = "parsed")]
+    pub week_start: Option<Weekday>,
+    /// language of the reports
+    #[serde(default, deserialize_with = "parsed")]
+    pub lang: Option<Lang>,
+    /// used instead of `$EDITOR`
+    pub editor: Option<String>,
+    /// project used outside of the linked directories
+    pub default_project: Option<String>,
+    /// written like the `--round` argument, as in `15min:up`
+    #[serde(default, deserialize_with = "parsed")]
+    pub round: Option<Rounding>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub round_per: Option<RoundingScope>,
+    /// minutes, like the `--merge-gap` argument
+    pub merge_gap: Option<i64>,
+    /// directories searched for `.clockin` links to fix when a project is renamed
+    pub workspace_roots: Option<Vec<PathBuf>>,
+    /// commit the data directory after each change when it is a git repository, true by default
+    pub git_auto_commit: Option<bool>,
+    /// write the name of the machine under the start of each session, to tell apart the
+    /// sessions of each device
+    pub record_host: Option<bool>,
+    /// identity file that decrypts the projects encrypted with age
+    pub age_identity: Option<PathBuf>,
+}
+
+/// Reads a value written the same way as on the command line.
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err: Display>,
+{
+    let text = String::deserialize(deserializer)?;
+    text.parse().map(Some).map_err(D::Error::custom)
+}
+
+impl Config {
+    /// The configuration of the user, empty if there is none.
+    pub fn load() -> Result<Self> {
+        let path = file::get_config_file();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("error while reading {}", path.display()));
+            }
+        };
+        toml::from_str(&content).with_context(|| format!("error while parsing {}", path.display()))
+    }
+
+    /// Like [`Config::load`], reading the file only the first time.
+    pub fn get() -> Result<&'static Self> {
+        if let Some(config) = CONFIG.get() {
+            return Ok(config);
+        }
+        let config = Self::load()?;
+        Ok(CONFIG.get_or_init(|| config))
+    }
+}
+
+/// The timezone given on the command line, falling back to the one in the project metadata and
+/// then to the configured one.
+pub fn resolve_timezone(path: &Path, timezone: Option<FixedOffset>) -> Result<FixedOffset> {
+    resolve_global_timezone(timezone.or(parser::parse_metadata(path)?.timezone))
+}
+
+/// Like [`resolve_timezone`] for the reports spanning every project, which skip the metadata.
+pub fn resolve_global_timezone(timezone: Option<FixedOffset>) -> Result<FixedOffset> {
+    Ok(timezone
+        .or(Config::get()?.timezone)
+        .unwrap_or(Local::now().fixed_offset().timezone()))
+}
+
+/// The rounding given on the command line, falling back to the configured one.
+pub fn resolve_rounding(
+    round: Option<Rounding>,
+    per: Option<RoundingScope>,
+) -> Result<(Option<Rounding>, RoundingScope)> {
+    let config = Config::get()?;
+    Ok((
+        round.or(config.round),
+        per.or(config.round_per).unwrap_or_default(),
+    ))
+}
+
+pub fn resolve_merge_gap(merge_gap: Option<i64>) -> Result<TimeDelta> {
+    Ok(TimeDelta::minutes(
+        merge_gap.or(Config::get()?.merge_gap).unwrap_or(0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::{FixedOffset, TimeDelta, Weekday};
+
+    use super::Config;
+    use crate::cli::{Lang, RoundingMode, RoundingScope};
+
+    #[test]
+    fn config() {
+        let config: Config = toml::from_str(
+            r#"
+            timezone = "-03:00"
+            record-timezone = "+00:00"
+            week-start = "sunday"
+            lang = "en"
+            editor = "vim"
+            default-project = "acme"
+            round = "15min:up"
+            round-per = "day"
+            merge-gap = 5
+            workspace-roots = ["/home/me/code"]
+            git-auto-commit = false
+            record-host = true
+            age-identity = "/home/me/.age/key.txt"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.timezone, FixedOffset::west_opt(3 * 3600));
+        assert_eq!(config.record_timezone, FixedOffset::east_opt(0));
+        assert_eq!(config.week_start, Some(Weekday::Sun));
+        assert_eq!(config.lang, Some(Lang::En));
+        let round = config.round.unwrap();
+        assert_eq!(round.step, TimeDelta::minutes(15));
+        assert!(matches!(round.mode, RoundingMode::Up));
+        assert!(matches!(config.round_per, Some(RoundingScope::Day)));
+        assert_eq!(
+            config.workspace_roots,
+            Some(vec![PathBuf::from("/home/me/code")])
+        );
+        assert_eq!(config.git_auto_commit, Some(false));
+        assert_eq!(config.record_host, Some(true));
+        assert_eq!(
+            config.age_identity,
+            Some(PathBuf::from("/home/me/.age/key.txt"))
+        );
+
+        assert_eq!(toml::from_str::<Config>("").unwrap(), Config::default());
+        assert!(toml::from_str::<Config>("round = \"15\"").is_err());
+        assert!(toml::from_str::<Config>("timezone = \"UTC\"").is_err());
+        assert!(toml::from_str::<Config>("week-begin = \"sunday\"").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use chrono::{DateTime, FixedOffset, Local};
+use clockin::{
+    parser,
+    writer::{self, SessionWriter},
+};
+use thiserror::Error;
+
+use crate::{
+    git,
+    webhook::{self, Event},
+};
+
+/// Why a session could not be controlled, apart from the file being unreadable.
+#[derive(Error, Debug)]
+pub enum ControlError {
+    #[error("a session is already running, clock out first")]
+    AlreadyRunning,
+    #[error("there is no running session to {0}")]
+    NotRunning(&'static str),
+    #[error("the session is already paused")]
+    AlreadyPaused,
+    #[error("there is no paused session to resume")]
+    NotPaused,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+type Result<T> = std::result::Result<T, ControlError>;
+
+/// Starts a session now, the editor is left to the interactive `clockin in`.
+pub fn clock_in(path: &Path, message: Option<&str>) -> Result<()> {
+    clock_in_at(path, None, message)
+}
+
+/// Like [`clock_in`], starting the session at a time in the past if given, though never before
+/// the previous session ended.
+pub fn clock_in_at(
+    path: &Path,
+    at: Option<DateTime<FixedOffset>>,
+    message: Option<&str>,
+) -> Result<()> {
+    {
+        let _lock = writer::lock(path)?;
+        if parser::is_session_running(path)? {
+            return Err(ControlError::AlreadyRunning);
+        }
+        if let Some(at) = at {
+            if at > Local::now() {
+                return Err(anyhow!("can't clock in in the future").into());
+            }
+            if let Some(end) = parser::last_session(path)?.and_then(|s| s.end)
+                && at < end
+            {
+                return Err(
+                    anyhow!("can't clock in before the previous session ended at {end}").into(),
+                );
+            }
+        }
+        let writer = SessionWriter::new(path);
+        writer.start(at.unwrap_or(Local::now().fixed_offset()))?;
+        if let Some(message) = message {
+            writer.describe(message)?;
+        }
+    }
+    // the webhooks are posted without the lock, so a slow one doesn't hold back other writers
+    webhook::notify(path, Event::ClockIn)?;
+    Ok(())
+}
+
+pub fn clock_out(path: &Path) -> Result<()> {
+    {
+        let _lock = writer::lock(path)?;
+        if !parser::is_session_running(path)? {
+            return Err(ControlError::NotRunning("clock out of"));
+        }
+        SessionWriter::new(path).finish(Local::now().fixed_offset())?;
+        git::auto_commit(path, "clock out");
+    }
+    webhook::notify(path, Event::ClockOut)?;
+    Ok(())
+}
+
+pub fn pause(path: &Path) -> Result<()> {
+    let _lock = writer::lock(path)?;
+    match parser::last_session(path)?.filter(|s| !s.is_finished()) {
+        None => Err(ControlError::NotRunning("pause")),
+        Some(session) if session.is_paused() => Err(ControlError::AlreadyPaused),
+        Some(_) => Ok(SessionWriter::new(path).pause(Local::now().fixed_offset())?),
+    }
+}
+
+pub fn resume(path: &Path) -> Result<()> {
+    let _lock = writer::lock(path)?;
+    if !parser::last_session(path)?.is_some_and(|s| !s.is_finished() && s.is_paused()) {
+        return Err(ControlError::NotPaused);
+    }
+    Ok(SessionWriter::new(path).resume(Local::now().fixed_offset())?)
+}
+
+/// Adds a line to the description of the running session.
+pub fn annotate(path: &Path, message: &str) -> Result<()> {
+    let _lock = writer::lock(path)?;
+    if !parser::is_session_running(path)? {
+        return Err(ControlError::NotRunning("annotate"));
+    }
+    Ok(SessionWriter::new(path).describe(message)?)
+}
@@ -0,0 +1,147 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+use crate::{cli::Encryption, config::Config, project_config::ProjectConfig};
+
+const AGE_HEADER: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+const GPG_HEADER: &[u8] = b"-----BEGIN PGP MESSAGE-----";
+
+impl Serialize for Encryption {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Encryption {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(D::Error::custom)
+    }
+}
+
+/// The tool that wrote the content, none if it is not encrypted. Only armored files are written,
+/// so their header tells them apart from plain text.
+pub fn encrypted_with(content: &[u8]) -> Option<Encryption> {
+    let content = content.trim_ascii_start();
+    if content.starts_with(AGE_HEADER) {
+        Some(Encryption::Age)
+    } else if content.starts_with(GPG_HEADER) {
+        Some(Encryption::Gpg)
+    } else {
+        None
+    }
+}
+
+/// Feeds the input to the command and returns what it prints, its errors go to the terminal so
+/// gpg can still ask for the passphrase.
+fn pipe(command: &mut Command, input: Vec<u8>) -> Result<Vec<u8>> {
+    let name = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("error while running {name}, is it installed?"))?;
+    let mut stdin = child.stdin.take().unwrap();
+    // written from another thread so a large output can't block the input
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    writer.join().unwrap()?;
+    if !output.status.success() {
+        bail!("{name} failed with {}", output.status);
+    }
+    Ok(output.stdout)
+}
+
+/// The plain content, the content itself if it is not encrypted.
+pub fn decrypt(content: Vec<u8>) -> Result<Vec<u8>> {
+    match encrypted_with(&content) {
+        None => Ok(content),
+        Some(Encryption::Age) => {
+            let identity = Config::get()?.age_identity.clone().context(
+                "age-identity has to be set in the configuration to read age encrypted projects",
+            )?;
+            pipe(
+                Command::new("age")
+                    .arg("--decrypt")
+                    .arg("--identity")
+                    .arg(identity),
+                content,
+            )
+        }
+        Some(Encryption::Gpg) => pipe(Command::new("gpg").args(["--quiet", "--decrypt"]), content),
+    }
+}
+
+/// Encrypts the content as set in the project configuration, for gpg the default key is used
+/// when there is no recipient.
+pub fn encrypt(content: Vec<u8>, config: &ProjectConfig) -> Result<Vec<u8>> {
+    let recipient = config.recipient.as_deref();
+    match config.encryption {
+        None => Ok(content),
+        Some(Encryption::Age) => {
+            let recipient =
+                recipient.context("the project has no recipient to encrypt it for with age")?;
+            pipe(
+                Command::new("age").args(["--encrypt", "--armor", "--recipient", recipient]),
+                content,
+            )
+        }
+        Some(Encryption::Gpg) => {
+            let mut command = Command::new("gpg");
+            command.args(["--quiet", "--batch", "--yes", "--armor", "--encrypt"]);
+            match recipient {
+                Some(recipient) => command.args(["--recipient", recipient]),
+                None => command.arg("--default-recipient-self"),
+            };
+            pipe(&mut command, content)
+        }
+    }
+}
+
+/// Opens a session file, decrypting the whole of it first when it is encrypted.
+pub fn open(path: impl AsRef<Path>) -> Result<Box<dyn BufRead + Send>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if encrypted_with(reader.fill_buf()?).is_none() {
+        return Ok(Box::new(reader));
+    }
+    let mut content = vec![];
+    reader.read_to_end(&mut content)?;
+    Ok(Box::new(Cursor::new(decrypt(content)?)))
+}
+
+/// Contents of a session file, decrypted when it is encrypted.
+pub fn read(path: impl AsRef<Path>) -> Result<String> {
+    let mut content = String::new();
+    open(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encrypted_with;
+    use crate::cli::Encryption;
+
+    #[test]
+    fn headers() {
+        assert_eq!(
+            encrypted_with(b"-----BEGIN AGE ENCRYPTED FILE-----\nYWdl\n"),
+            Some(Encryption::Age)
+        );
+        assert_eq!(
+            encrypted_with(b"\n-----BEGIN PGP MESSAGE-----\n\nhQ\n"),
+            Some(Encryption::Gpg)
+        );
+        assert_eq!(encrypted_with(b"%-2000-01-01T10:00:00+00:00\n"), None);
+        assert_eq!(encrypted_with(b""), None);
+        assert_eq!("gpg".parse(), Ok(Encryption::Gpg));
+    }
+}
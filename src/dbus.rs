@@ -0,0 +1,94 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+};
+
+use anyhow::Result;
+use zbus::{blocking::connection, fdo, interface, object_server::SignalEmitter};
+
+use clockin::status::{self, State};
+
+use crate::{
+    control::{self, ControlError},
+    subscribe,
+};
+
+const NAME: &str = "dev.clockin.Tracker";
+const OBJECT_PATH: &str = "/dev/clockin/Tracker";
+
+struct Tracker {
+    path: PathBuf,
+}
+
+fn fdo_error(err: impl std::fmt::Display) -> fdo::Error {
+    fdo::Error::Failed(format!("{err:#}"))
+}
+
+fn control_error(err: ControlError) -> fdo::Error {
+    match err {
+        ControlError::Other(err) => fdo_error(err),
+        err => fdo_error(err),
+    }
+}
+
+#[interface(name = "dev.clockin.Tracker")]
+impl Tracker {
+    /// State ("stopped", "running" or "paused"), project, start as unix seconds, elapsed seconds
+    /// and description. The last three are zero or empty while stopped.
+    fn status(&self) -> fdo::Result<(String, String, i64, i64, String)> {
+        let status = status::status(&self.path).map_err(fdo_error)?;
+        Ok((
+            status.state.as_str().to_owned(),
+            status.project,
+            status.start.map_or(0, |start| start.timestamp()),
+            status.elapsed_seconds.unwrap_or_default(),
+            status.description.unwrap_or_default(),
+        ))
+    }
+
+    /// Starts a session, with the message as its description unless it is empty.
+    fn clock_in(&self, message: &str) -> fdo::Result<()> {
+        control::clock_in(&self.path, Some(message).filter(|m| !m.is_empty()))
+            .map_err(control_error)
+    }
+
+    fn clock_out(&self) -> fdo::Result<()> {
+        control::clock_out(&self.path).map_err(control_error)
+    }
+
+    #[zbus(signal)]
+    async fn state_changed(emitter: &SignalEmitter<'_>, state: &str) -> zbus::Result<()>;
+}
+
+fn current_state(path: &Path) -> Result<State> {
+    Ok(status::status(path)?.state)
+}
+
+/// Publishes the tracker on the session bus until cancelled, signaling every change of state.
+pub fn serve(path: PathBuf, cancel: Receiver<()>) -> Result<()> {
+    let connection = connection::Builder::session()?
+        .name(NAME)?
+        .serve_at(OBJECT_PATH, Tracker { path: path.clone() })?
+        .build()?;
+    let tracker = connection
+        .object_server()
+        .interface::<_, Tracker>(OBJECT_PATH)?;
+    eprintln!("serving {NAME} at {OBJECT_PATH}");
+
+    let mut state = current_state(&path)?;
+    subscribe::watch_file(
+        &path,
+        || match current_state(&path) {
+            Ok(new_state) if new_state != state => {
+                state = new_state;
+                let signal = Tracker::state_changed(tracker.signal_emitter(), state.as_str());
+                if let Err(err) = zbus::block_on(signal) {
+                    eprintln!("unable to signal the change: {err}");
+                }
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("error: {err:#}"),
+        },
+        cancel,
+    )
+}
@@ -0,0 +1,94 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::{Context, Result, bail};
+use clockin::{config::Config, crypt, project_config::ProjectConfig, writer};
+use itertools::Itertools;
+
+pub fn edit_files(paths: &[PathBuf]) -> Result<()> {
+    let editor = match &Config::get()?.editor {
+        Some(editor) => editor.clone(),
+        None => std::env::var("EDITOR").unwrap_or("nano".to_owned()),
+    };
+    let mut process = process::Command::new(editor)
+        .args(paths)
+        .spawn()
+        .context("error while trying to run editor")?;
+    process.wait().context("error while editing file")?;
+    Ok(())
+}
+
+/// Edits session files of the project at once. The ones of encrypted projects are edited through
+/// decrypted copies next to them, kept only while the editor is open unless their edits can't be
+/// written.
+pub fn edit_session_files(project: &Path, paths: &[PathBuf]) -> Result<()> {
+    if ProjectConfig::load(project)?.encryption.is_none() {
+        return edit_files(paths);
+    }
+
+    // (file, decrypted copy, encrypted content, plain content)
+    let mut copies = vec![];
+    // decrypted copies left for the user
+    let mut kept = vec![];
+    let result = (|| {
+        for path in paths {
+            let name = path.file_name().context("file has no name")?;
+            let temp = path.with_file_name(format!(".{}.edit.tmp", name.to_string_lossy()));
+            let encrypted = fs::read(path)?;
+            let plain = crypt::decrypt(encrypted.clone())?;
+            copies.push((path, temp.clone(), encrypted, plain.clone()));
+            File::options()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&temp)
+                .and_then(|mut file| file.write_all(&plain))
+                .context("error while writing decrypted copy")?;
+        }
+        edit_files(
+            &copies
+                .iter()
+                .map(|(_, temp, ..)| temp.clone())
+                .collect::<Vec<_>>(),
+        )?;
+        let _lock = writer::lock(project)?;
+        // the sessions may have been written from another terminal meanwhile
+        for (path, _, encrypted, _) in &copies {
+            if fs::read(path)? != *encrypted {
+                kept = copies
+                    .iter()
+                    .filter(|(_, temp, _, plain)| fs::read(temp).is_ok_and(|c| c != *plain))
+                    .map(|(_, temp, ..)| temp.clone())
+                    .collect();
+                if kept.is_empty() {
+                    bail!("{} changed while editing it", path.display());
+                }
+                bail!(
+                    "{} changed while editing it, nothing was written and the edits are kept in {}",
+                    path.display(),
+                    kept.iter().map(|temp| temp.display()).join(", ")
+                );
+            }
+        }
+        for (path, temp, _, plain) in &copies {
+            let content = fs::read_to_string(temp)?;
+            // encrypting again would change the file even if the content is the same
+            if content.as_bytes() != plain.as_slice() {
+                writer::write_session_file(project, path, content)?;
+            }
+        }
+        Ok(())
+    })();
+    for (_, temp, ..) in &copies {
+        if !kept.contains(temp) {
+            let _ = fs::remove_file(temp);
+        }
+    }
+    result
+}
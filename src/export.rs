@@ -0,0 +1,218 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+
+#[cfg(feature = "sqlite")]
+use crate::sqlite;
+use crate::{
+    binnacle_body_parser,
+    cli::ExportCommand,
+    config, file,
+    parser::{self, Session, SessionIteratorExt},
+    writer::ReportOutput,
+};
+
+/// Content lines longer than this many bytes are folded, as required by RFC 5545.
+const ICS_LINE_LIMIT: usize = 75;
+
+fn fmt_ics_datetime(time: DateTime<FixedOffset>) -> String {
+    time.with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn escape_ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn write_ics_line(out: &mut impl Write, line: &str) -> io::Result<()> {
+    let mut limit = ICS_LINE_LIMIT;
+    let mut rest = line;
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        write!(out, "{}\r\n ", &rest[..split])?;
+        rest = &rest[split..];
+        // the leading space of continuation lines counts towards the limit
+        limit = ICS_LINE_LIMIT - 1;
+    }
+    write!(out, "{rest}\r\n")
+}
+
+/// Writes the sessions as an iCalendar document with one event per worked stretch, so a paused
+/// session gives an event before and another after each pause.
+pub fn write_ics(sessions: impl Iterator<Item = Session>, out: &mut impl Write) -> io::Result<()> {
+    let stamp = fmt_ics_datetime(Utc::now().fixed_offset());
+
+    write_ics_line(out, "BEGIN:VCALENDAR")?;
+    write_ics_line(out, "VERSION:2.0")?;
+    write_ics_line(out, "PRODID:-//clockin//clockin//EN")?;
+    for session in sessions {
+        let summary = session.description.lines().next().unwrap_or_default();
+        for interval in session.active_intervals() {
+            let start = fmt_ics_datetime(interval.start);
+            let end = fmt_ics_datetime(interval.end);
+
+            write_ics_line(out, "BEGIN:VEVENT")?;
+            write_ics_line(out, &format!("UID:{start}-{end}@clockin"))?;
+            write_ics_line(out, &format!("DTSTAMP:{stamp}"))?;
+            write_ics_line(out, &format!("DTSTART:{start}"))?;
+            write_ics_line(out, &format!("DTEND:{end}"))?;
+            write_ics_line(out, &format!("SUMMARY:{}", escape_ics_text(summary)))?;
+            if !session.description.is_empty() {
+                write_ics_line(
+                    out,
+                    &format!("DESCRIPTION:{}", escape_ics_text(&session.description)),
+                )?;
+            }
+            write_ics_line(out, "END:VEVENT")?;
+        }
+    }
+    write_ics_line(out, "END:VCALENDAR")
+}
+
+fn fmt_timeclock_datetime(time: NaiveDateTime) -> String {
+    time.format("%Y/%m/%d %H:%M:%S").to_string()
+}
+
+/// Writes the sessions as hledger timeclock entries, one check in and out per active interval.
+/// The account is the project followed by the sub-project components, like `project:backend:api`.
+pub fn write_timeclock(
+    sessions: impl Iterator<Item = Session>,
+    project: &str,
+    timezone: &impl TimeZone,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for session in sessions.with_timezone(timezone).naive_local() {
+        let body = binnacle_body_parser::parse(&session.description)
+            .unwrap()
+            .to_owned();
+        let account = [project]
+            .into_iter()
+            .chain(body.sub_project_path())
+            .collect::<Vec<_>>()
+            .join(":");
+        let subject = body.subject.lines().next().unwrap_or_default();
+
+        for interval in session.active_intervals() {
+            // the account and the description must be separated by at least two spaces
+            writeln!(
+                out,
+                "i {} {account}  {subject}",
+                fmt_timeclock_datetime(interval.start)
+            )?;
+            writeln!(out, "o {}", fmt_timeclock_datetime(interval.end))?;
+        }
+    }
+    Ok(())
+}
+
+/// Exports the sessions of the project in the format of `clockin export`.
+pub fn export(path: &Path, format: ExportCommand) -> Result<()> {
+    match format {
+        ExportCommand::Ics {
+            from,
+            to,
+            timezone,
+            output,
+        } => {
+            let timezone = config::resolve_timezone(path, timezone)?;
+            let sessions = parser::sessions_in_range(path, (from, to), &timezone)?;
+            let mut out = ReportOutput::new(output);
+            write_ics(sessions, &mut out)?;
+            out.finish()
+        }
+        ExportCommand::Timeclock {
+            from,
+            to,
+            timezone,
+            output,
+        } => {
+            let timezone = config::resolve_timezone(path, timezone)?;
+            let sessions = parser::sessions_in_range(path, (from, to), &timezone)?;
+            let mut out = ReportOutput::new(output);
+            let project = file::project_name(path)?;
+            write_timeclock(sessions, &project, &timezone, &mut out)?;
+            out.finish()
+        }
+        #[cfg(feature = "sqlite")]
+        ExportCommand::Sqlite {
+            db,
+            from,
+            to,
+            timezone,
+        } => {
+            let timezone = config::resolve_timezone(path, timezone)?;
+            let sessions = parser::sessions_in_range(path, (from, to), &timezone)?;
+            sqlite::export(&db, sessions, &timezone)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::{write_ics, write_timeclock};
+    use crate::testing::session;
+
+    #[test]
+    fn ics() {
+        let description = format!("api, auth; {}\nsecond line", "ñ".repeat(40));
+        let session = session("2000-01-01T10:00:00-03:00")
+            .end("2000-01-01T11:00:00-03:00")
+            .pause("2000-01-01T10:20:00-03:00", "2000-01-01T10:30:00-03:00")
+            .description(&description)
+            .build();
+
+        let mut out = vec![];
+        write_ics([session].into_iter(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("DTSTART:20000101T130000Z\r\nDTEND:20000101T132000Z\r\n"));
+        assert!(out.contains("DTSTART:20000101T133000Z\r\nDTEND:20000101T140000Z\r\n"));
+        assert_eq!(out.matches("BEGIN:VEVENT").count(), 2);
+        assert!(out.contains("SUMMARY:api\\, auth\\; ñ"));
+        assert!(out.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(
+            out.replace("\r\n ", "")
+                .lines()
+                .find(|l| l.starts_with("DESCRIPTION:")),
+            Some(
+                format!(
+                    "DESCRIPTION:api\\, auth\\; {}\\nsecond line",
+                    "ñ".repeat(40)
+                )
+                .as_str()
+            )
+        );
+    }
+
+    #[test]
+    fn timeclock() {
+        let session = session("2000-01-01T10:00:00+00:00")
+            .end("2000-01-01T12:00:00+00:00")
+            .pause("2000-01-01T11:00:00+00:00", "2000-01-01T11:30:00+00:00")
+            .description("backend/api: fix auth\ndetails")
+            .build();
+
+        let mut out = vec![];
+        write_timeclock([session].into_iter(), "acme", &Utc, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "i 2000/01/01 10:00:00 acme:backend:api  fix auth\n\
+             o 2000/01/01 11:00:00\n\
+             i 2000/01/01 11:30:00 acme:backend:api  fix auth\n\
+             o 2000/01/01 12:00:00\n"
+        );
+    }
+}
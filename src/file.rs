@@ -1,12 +1,17 @@
 use std::{
     env::current_dir,
+    fmt,
     fs::{self, File},
     os,
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::{Once, OnceLock},
 };
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Local;
+
+use crate::{config::Config, writer};
 
 fn find_dot_clockin_file() -> Option<PathBuf> {
     let first_dir = current_dir().unwrap();
@@ -24,11 +29,51 @@ fn find_dot_clockin_file() -> Option<PathBuf> {
     None
 }
 
+static CLOCKIN_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets the file used by every command instead of looking for the project, only the first call
+/// has an effect.
+pub fn set_clockin_file(path: PathBuf) {
+    let _ = CLOCKIN_FILE.set(path);
+}
+
 fn get_var_project() -> Option<String> {
     std::env::var("CLOCKIN_PROJECT").ok()
 }
 
-fn find_closest_clockin_file() -> Option<PathBuf> {
+/// How the project file of the current invocation was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// the `--file` argument or the `CLOCKIN_FILE` environment variable
+    File,
+    /// the `CLOCKIN_PROJECT` environment variable
+    Variable,
+    /// the `.clockin` link at this path, in the current directory or one of its parents
+    Link(PathBuf),
+    /// the `default-project` of the configuration
+    DefaultProject,
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resolution::File => write!(f, "--file argument or CLOCKIN_FILE environment variable"),
+            Resolution::Variable => write!(f, "CLOCKIN_PROJECT environment variable"),
+            Resolution::Link(link) => write!(f, ".clockin link at {}", link.display()),
+            Resolution::DefaultProject => {
+                write!(f, "default-project of {}", get_config_file().display())
+            }
+        }
+    }
+}
+
+/// The project file the commands use and how it was found. A file set with
+/// [`set_clockin_file`] is used as is, otherwise `CLOCKIN_PROJECT` takes precedence over the
+/// `.clockin` links and those over the default project.
+pub fn resolve_clockin_file() -> Option<(PathBuf, Resolution)> {
+    if let Some(path) = CLOCKIN_FILE.get() {
+        return Some((path.clone(), Resolution::File));
+    }
     get_var_project()
         .map(|project_name| {
             let mut path = get_data_dir();
@@ -37,12 +82,25 @@ fn find_closest_clockin_file() -> Option<PathBuf> {
         })
         .map(|path| {
             path.exists()
-                .then_some(path)
+                .then_some((path, Resolution::Variable))
                 .ok_or(anyhow!("the specified CLOCKIN_PROJECT does not exist"))
         })
         .transpose()
         .unwrap()
-        .or_else(find_dot_clockin_file)
+        .or_else(|| find_dot_clockin_file().map(|link| (link.clone(), Resolution::Link(link))))
+        .or_else(|| find_default_project_file().map(|path| (path, Resolution::DefaultProject)))
+}
+
+fn find_closest_clockin_file() -> Option<PathBuf> {
+    resolve_clockin_file().map(|(path, _resolution)| path)
+}
+
+/// The configured default project, for directories that are not linked to any.
+fn find_default_project_file() -> Option<PathBuf> {
+    let project = Config::get().ok()?.default_project.as_ref()?;
+    let mut path = get_data_dir();
+    path.push(project);
+    path.exists().then_some(path)
 }
 
 fn find_deepest_clockin_file() -> Option<PathBuf> {
@@ -68,33 +126,193 @@ fn get_var_path(name: &str) -> Option<PathBuf> {
         .ok()
 }
 
+/// Directory of the project files, `$XDG_DATA_HOME/clockin` or else the one of the platform, like
+/// `~/.local/share/clockin` on Linux or `~/Library/Application Support/clockin` on macOS.
 pub fn get_data_dir() -> PathBuf {
     let mut data = get_var_path("XDG_DATA_HOME")
+        .or_else(dirs::data_dir)
+        .expect("unable to find the data directory");
+    data.push("clockin");
+    notice_old_data_dir(&data);
+    fs::create_dir_all(&data).unwrap();
+    data
+}
+
+/// Warns once about the project files left in `~/.local/share/clockin`, where they were kept on
+/// every platform before the platform's own data directory was used.
+fn notice_old_data_dir(data: &Path) {
+    static NOTICED: Once = Once::new();
+    // `$XDG_DATA_HOME` was followed before as well
+    if get_var_path("XDG_DATA_HOME").is_some() {
+        return;
+    }
+    let Some(mut old) = get_var_path("HOME") else {
+        return;
+    };
+    old.push(".local/share/clockin");
+    if old != data && old.is_dir() {
+        NOTICED.call_once(|| {
+            eprintln!(
+                "warning: the project files are now kept in {}, move the ones in {} there to keep \
+                 using them",
+                data.display(),
+                old.display()
+            );
+        });
+    }
+}
+
+/// Path of the configuration of the user, `$XDG_CONFIG_HOME/clockin/config.toml`.
+pub fn get_config_file() -> PathBuf {
+    let mut config = get_var_path("XDG_CONFIG_HOME")
         .or_else(|| {
             get_var_path("HOME").map(|mut home| {
-                home.push(".local/share");
+                home.push(".config");
                 home
             })
         })
         .unwrap();
-    data.push("clockin");
-    fs::create_dir_all(&data).unwrap();
-    data
+    config.push("clockin/config.toml");
+    config
 }
 
-pub fn create_clockin_file(name: &str) -> Result<PathBuf> {
+fn check_project_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains('/')
+        || name.ends_with(".toml")
+        || is_year_file_name(name)
+    {
+        bail!("invalid project name {name:?}");
+    }
+    Ok(())
+}
+
+/// Path of the project file in the data directory, created empty if it does not exist.
+pub fn create_project_file(name: &str) -> Result<PathBuf> {
+    check_project_name(name)?;
     let mut data = get_data_dir();
     data.push(name);
-    let clockin_link = PathBuf::from_str(".clockin").unwrap();
     File::options()
         .write(true)
         .create(true)
         .truncate(false)
         .open(&data)?;
+    Ok(data)
+}
+
+pub fn create_clockin_file(name: &str) -> Result<PathBuf> {
+    let data = create_project_file(name)?;
+    let clockin_link = PathBuf::from_str(".clockin").unwrap();
     os::unix::fs::symlink(&data, &clockin_link)?;
     Ok(clockin_link)
 }
 
+/// Removes the `.clockin` link of the current directory, leaving the project file untouched.
+pub fn remove_clockin_link() -> Result<()> {
+    let link = PathBuf::from_str(".clockin").unwrap();
+    if !link.is_symlink() {
+        bail!("there is no .clockin link in the current directory");
+    }
+    fs::remove_file(&link).context("removing .clockin link")
+}
+
+/// File of the project with the given name, which has to exist.
+pub fn require_project_file(name: &str) -> Result<PathBuf> {
+    let mut path = get_data_dir();
+    path.push(name);
+    if !is_project_file(&path) {
+        bail!("project {name:?} does not exist");
+    }
+    Ok(path)
+}
+
+/// Deletes the project file and its settings, returning the backup of the file kept in case the
+/// project is needed again. The settings are moved next to the backup, see [`restore_backup`].
+/// The lock file is left behind, a writer waiting for it would otherwise lock a removed file.
+pub fn delete_project(project_file: &Path) -> Result<PathBuf> {
+    let _lock = writer::lock(project_file)?;
+    let backup = backup_file(project_file)?;
+    let config = project_config_file(project_file)?;
+    if config.exists() {
+        fs::rename(config, backup.with_extension("toml")).context("moving project settings")?;
+    }
+    for year_file in session_files(project_file)?.iter().skip(1) {
+        fs::remove_file(year_file).context("removing yearly file")?;
+    }
+    fs::remove_file(project_file).context("removing project file")?;
+    Ok(backup)
+}
+
+/// Renames the project file along with its settings and backups, returning the new file. The
+/// `.clockin` links are left for [`relink`] and [`relink_under`], and the lock file for the same
+/// reason as in [`delete_project`].
+pub fn rename_project(project_file: &Path, new_name: &str) -> Result<PathBuf> {
+    check_project_name(new_name)?;
+    let _lock = writer::lock(project_file)?;
+    let mut new_file = get_data_dir();
+    new_file.push(new_name);
+    if new_file.exists() {
+        bail!("project {new_name:?} already exists");
+    }
+    let config = project_config_file(project_file)?;
+    let backups = get_backups_dir(project_file)?;
+
+    for year_file in session_files(project_file)?.iter().skip(1) {
+        let year = year_suffix(year_file);
+        fs::rename(
+            year_file,
+            new_file.with_file_name(format!("{new_name}.{year}")),
+        )
+        .context("renaming yearly file")?;
+    }
+    fs::rename(project_file, &new_file).context("renaming project file")?;
+    if config.exists() {
+        fs::rename(config, project_config_file(&new_file)?).context("renaming project settings")?;
+    }
+    if backups.exists() {
+        fs::rename(backups, get_backups_dir(&new_file)?).context("renaming backups directory")?;
+    }
+    Ok(new_file)
+}
+
+/// Points the `.clockin` link to the `new` project file if it pointed to the `old` one, returning
+/// whether it did.
+pub fn relink(link: &Path, old: &Path, new: &Path) -> Result<bool> {
+    if !link.is_symlink() || fs::read_link(link)? != old {
+        return Ok(false);
+    }
+    fs::remove_file(link).with_context(|| format!("removing {}", link.display()))?;
+    os::unix::fs::symlink(new, link).with_context(|| format!("relinking {}", link.display()))?;
+    Ok(true)
+}
+
+/// Like [`relink`] for every `.clockin` link under the directories, skipping the hidden ones and
+/// the ones that can not be read. Returns the links changed.
+pub fn relink_under(roots: &[PathBuf], old: &Path, new: &Path) -> Result<Vec<PathBuf>> {
+    let mut relinked = vec![];
+    let mut pending = roots.to_vec();
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name() == ".clockin" {
+                if relink(&path, old, new)? {
+                    relinked.push(path);
+                }
+            } else if entry.file_type().is_ok_and(|t| t.is_dir())
+                && !entry.file_name().to_string_lossy().starts_with('.')
+            {
+                pending.push(path);
+            }
+        }
+    }
+    relinked.sort();
+    Ok(relinked)
+}
+
 pub fn require_clockin_file() -> Result<PathBuf> {
     find_clockin_file().ok_or(anyhow!(".clockin file not found"))
 }
@@ -102,3 +320,327 @@ pub fn require_clockin_file() -> Result<PathBuf> {
 pub fn require_clockin_project_file() -> Result<PathBuf> {
     find_deepest_clockin_file().ok_or(anyhow!("clockin project file not found"))
 }
+
+/// Whether the path is the file of a project in the data directory, rather than its settings,
+/// backups or anything else found there.
+pub fn is_project_file(path: &Path) -> bool {
+    path.parent() == Some(get_data_dir().as_path())
+        && path.is_file()
+        && path.file_name().is_some_and(|name| {
+            let name = name.to_string_lossy();
+            !name.starts_with('.') && !name.ends_with(".toml") && !is_year_file_name(&name)
+        })
+}
+
+/// Whether the name is the one of a yearly file, like `project.2024`.
+fn is_year_file_name(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(project, year)| {
+        !project.is_empty() && year.len() == 4 && year.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+/// The file of the sessions started in the year, `<project>.<year>` next to the project file.
+pub fn year_file(project_file: &Path, year: i32) -> Result<PathBuf> {
+    let project_file = fs::canonicalize(project_file).context("resolving project file")?;
+    let name = project_file
+        .file_name()
+        .context("project file has no name")?
+        .to_string_lossy();
+    Ok(project_file.with_file_name(format!("{name}.{year:04}")))
+}
+
+/// Whether the path is the project file or one of its yearly files.
+pub fn is_session_file(project_file: &Path, path: &Path) -> bool {
+    if path == project_file {
+        return true;
+    }
+    let (Some(name), Some(project)) = (path.file_name(), project_file.file_name()) else {
+        return false;
+    };
+    let name = name.to_string_lossy();
+    path.parent() == project_file.parent()
+        && is_year_file_name(&name)
+        && name.rsplit_once('.').unwrap().0 == project.to_string_lossy()
+}
+
+/// Files holding the sessions of the project, in the order they are read: the project file
+/// followed by its yearly files, oldest first.
+pub fn session_files(project_file: &Path) -> Result<Vec<PathBuf>> {
+    let canonical = fs::canonicalize(project_file).context("resolving project file")?;
+    let dir = canonical
+        .parent()
+        .context("project file has no directory")?;
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).context("reading project directory")? {
+        let path = entry?.path();
+        if path != canonical && is_session_file(&canonical, &path) {
+            files.push(path);
+        }
+    }
+    // years have four digits, so sorting by name sorts by year
+    files.sort();
+    files.insert(0, project_file.to_owned());
+    Ok(files)
+}
+
+/// Files of every project in the data directory, sorted by name.
+pub fn list_project_files() -> Result<Vec<PathBuf>> {
+    let mut projects = vec![];
+    for entry in fs::read_dir(get_data_dir()).context("reading data directory")? {
+        let path = entry?.path();
+        if is_project_file(&path) {
+            projects.push(path);
+        }
+    }
+    projects.sort();
+    Ok(projects)
+}
+
+/// Name of the project, following the `.clockin` link to the project file.
+pub fn project_name(project_file: &Path) -> Result<String> {
+    let project_file = fs::canonicalize(project_file).context("resolving project file")?;
+    let project = project_file
+        .file_name()
+        .context("project file has no name")?;
+    Ok(project.to_string_lossy().into_owned())
+}
+
+/// Path of the `<project>.toml` configuration next to the project file.
+pub fn project_config_file(project_file: &Path) -> Result<PathBuf> {
+    let mut config = get_data_dir();
+    config.push(format!("{}.toml", project_name(project_file)?));
+    Ok(config)
+}
+
+/// Path of the file locked while the project is written, `.<project>.lock` next to the project
+/// file. The project file itself can't be locked as the atomic rewrites replace it.
+pub fn lock_file(project_file: &Path) -> Result<PathBuf> {
+    let project_file = fs::canonicalize(project_file).context("resolving project file")?;
+    let name = project_file
+        .file_name()
+        .context("project file has no name")?
+        .to_string_lossy();
+    Ok(project_file.with_file_name(format!(".{name}.lock")))
+}
+
+fn get_backups_dir(project_file: &Path) -> Result<PathBuf> {
+    let mut backups = get_data_dir();
+    backups.push(".backups");
+    backups.push(project_name(project_file)?);
+    Ok(backups)
+}
+
+/// The year in the name of a yearly file or of its backup.
+fn year_suffix(path: &Path) -> &str {
+    path.extension()
+        .and_then(|year| year.to_str())
+        .unwrap_or_default()
+}
+
+/// Copies the project file into its backups directory, named after the current time down to the
+/// millisecond. Each yearly file is copied next to it, with the year appended to the name.
+pub fn backup_file(project_file: &Path) -> Result<PathBuf> {
+    let backups = get_backups_dir(project_file)?;
+    fs::create_dir_all(&backups).context("creating backups directory")?;
+    let stamp = Local::now().format("%Y-%m-%dT%H-%M-%S-%3f").to_string();
+    let mut backup = backups.join(&stamp);
+    // a backup taken in the same millisecond gets a counter instead of replacing the other one
+    for count in 1.. {
+        if !backup.exists() {
+            break;
+        }
+        backup = backups.join(format!("{stamp}-{count}"));
+    }
+    fs::copy(project_file, &backup).context("copying project file to backup")?;
+    for year_file in session_files(project_file)?.iter().skip(1) {
+        fs::copy(year_file, backup.with_extension(year_suffix(year_file)))
+            .context("copying yearly file to backup")?;
+    }
+    Ok(backup)
+}
+
+/// Replaces the project file and its yearly files with the ones in the backup. The settings kept
+/// when the project was deleted come back too, unless the project has its own by now.
+pub fn restore_backup(project_file: &Path, backup: &Path) -> Result<()> {
+    let _lock = writer::lock(project_file)?;
+    let name = backup
+        .file_name()
+        .context("backup has no name")?
+        .to_string_lossy();
+    for year_file in session_files(project_file)?.iter().skip(1) {
+        fs::remove_file(year_file).context("removing yearly file")?;
+    }
+    for entry in fs::read_dir(backup.parent().context("backup has no directory")?)? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        if file_name
+            .rsplit_once('.')
+            .is_some_and(|(stem, _)| stem == name)
+            && is_year_file_name(&file_name)
+        {
+            writer::write_atomically(
+                year_file(project_file, year_suffix(&path).parse()?)?,
+                fs::read(&path)?,
+            )
+            .context("restoring yearly file")?;
+        }
+    }
+    writer::write_atomically(project_file, fs::read(backup)?).context("restoring project file")?;
+    let (config, config_backup) = (
+        project_config_file(project_file)?,
+        backup.with_extension("toml"),
+    );
+    if config_backup.exists() && !config.exists() {
+        fs::copy(config_backup, config).context("restoring project settings")?;
+    }
+    Ok(())
+}
+
+/// Backups of the project file, oldest first.
+pub fn list_backups(project_file: &Path) -> Result<Vec<PathBuf>> {
+    let backups = get_backups_dir(project_file)?;
+    if !backups.exists() {
+        return Ok(vec![]);
+    }
+    let mut backups = fs::read_dir(backups)?
+        .map(|entry| entry.map(|e| e.path()))
+        .filter(|path| {
+            // the copies of the yearly files and of the settings go along with the backup of the
+            // project file
+            !path.as_ref().is_ok_and(|path| {
+                is_year_file_name(&path.file_name().unwrap().to_string_lossy())
+                    || path
+                        .extension()
+                        .is_some_and(|extension| extension == "toml")
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    backups.sort();
+    Ok(backups)
+}
+
+/// Renames the project with [`rename_project`] and fixes the `.clockin` links to it under the
+/// configured workspace roots and the one in use, printing what was done.
+pub fn rename_and_relink(old: &str, new: &str) -> Result<()> {
+    let old_file = require_project_file(old)?;
+    let current_link = match resolve_clockin_file() {
+        Some((_, Resolution::Link(link))) => Some(link),
+        _ => None,
+    };
+    let new_file = rename_project(&old_file, new)?;
+    println!("renamed {old} to {new}");
+
+    let config = Config::get()?;
+    let roots = config.workspace_roots.clone().unwrap_or_default();
+    let mut relinked = relink_under(&roots, &old_file, &new_file)?;
+    if let Some(link) = current_link
+        && !relinked.contains(&link)
+        && relink(&link, &old_file, &new_file)?
+    {
+        relinked.push(link);
+    }
+    for link in relinked {
+        println!("relinked {}", link.display());
+    }
+    if roots.is_empty() {
+        eprintln!(
+            "warning: no workspace-roots are configured, other .clockin links to {old} are left dangling"
+        );
+    }
+    if config.default_project.as_deref() == Some(old) {
+        eprintln!("warning: the configured default-project is still {old}");
+    }
+    Ok(())
+}
+
+/// Prints the project in use, its file and how it was found, as in `clockin which`.
+pub fn print_resolution() -> Result<()> {
+    let Some((path, resolution)) = resolve_clockin_file() else {
+        bail!(
+            "no project: CLOCKIN_PROJECT is not set, there is no .clockin link from {} up \
+             and no default-project is configured",
+            current_dir()?.display()
+        );
+    };
+    println!("project: {}", project_name(&path)?);
+    println!("file: {}", fs::canonicalize(&path)?.display());
+    println!("resolved by: {resolution}");
+    Ok(())
+}
+
+/// Prints the backups of the project file, the latest first.
+pub fn print_backups(project_file: &Path) -> Result<()> {
+    for backup in list_backups(project_file)?.iter().rev() {
+        println!("{}", backup.file_name().unwrap().to_string_lossy());
+    }
+    Ok(())
+}
+
+/// The backup of the project file with the name, as printed by [`print_backups`].
+pub fn find_backup(project_file: &Path, name: &str) -> Result<PathBuf> {
+    list_backups(project_file)?
+        .into_iter()
+        .find(|b| b.file_name() == Some(name.as_ref()))
+        .with_context(|| format!("backup {name} not found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::symlink, sync::mpsc, thread, time::Duration};
+
+    use super::{relink_under, restore_backup};
+    use crate::writer;
+
+    #[test]
+    fn restore_waits_for_lock() {
+        let id = std::process::id();
+        let dir = std::env::temp_dir().join(format!("clockin-restore-test-{id}"));
+        fs::create_dir_all(&dir).unwrap();
+        let project = dir.join(format!("clockin-restore-test-{id}"));
+        let backup = dir.join("backup");
+        fs::write(&project, "current\n").unwrap();
+        fs::write(&backup, "restored\n").unwrap();
+
+        let (locked, wait) = mpsc::channel();
+        let holder = {
+            let project = project.clone();
+            thread::spawn(move || {
+                let _lock = writer::lock(&project).unwrap();
+                locked.send(()).unwrap();
+                thread::sleep(Duration::from_millis(300));
+                fs::write(&project, "written while locked\n").unwrap();
+            })
+        };
+        wait.recv().unwrap();
+        // the write of the holder would come after the restore if it did not wait
+        restore_backup(&project, &backup).unwrap();
+        holder.join().unwrap();
+        assert_eq!(fs::read_to_string(&project).unwrap(), "restored\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn relinking() {
+        let dir = std::env::temp_dir().join(format!("clockin-relink-test-{}", std::process::id()));
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        let roots = [dir.join("code")];
+        let (workspace, other) = (&roots[0], dir.join("other"));
+        for nested in ["a/b", "c", ".hidden"] {
+            fs::create_dir_all(workspace.join(nested)).unwrap();
+        }
+        fs::create_dir_all(&other).unwrap();
+        symlink(&old, workspace.join("a/b/.clockin")).unwrap();
+        symlink(&old, workspace.join(".hidden/.clockin")).unwrap();
+        symlink(&other, workspace.join("c/.clockin")).unwrap();
+        symlink(&old, other.join(".clockin")).unwrap();
+
+        let relinked = relink_under(&roots, &old, &new).unwrap();
+        assert_eq!(relinked, [workspace.join("a/b/.clockin")]);
+        assert_eq!(fs::read_link(&relinked[0]).unwrap(), new);
+        assert_eq!(fs::read_link(workspace.join("c/.clockin")).unwrap(), other);
+        assert_eq!(fs::read_link(other.join(".clockin")).unwrap(), old);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
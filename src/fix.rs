@@ -0,0 +1,345 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+use itertools::Itertools;
+
+use crate::{
+    check,
+    cli::OverlapPolicy,
+    file,
+    parser::{self, MaybeFinishedSessionTZ},
+    writer,
+};
+
+type MaybeFinishedSession = MaybeFinishedSessionTZ<FixedOffset>;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct FixReport {
+    pub closed: usize,
+    pub sorted: bool,
+    pub deduplicated: usize,
+}
+
+/// Closes sessions that were never finished (except the running one), sorts them and removes
+/// duplicates. Closed sessions end `close_after` their last timestamp, but never after the next
+/// session starts.
+pub fn fix_sessions(
+    mut sessions: Vec<MaybeFinishedSession>,
+    close_after: TimeDelta,
+) -> (Vec<MaybeFinishedSession>, FixReport) {
+    let mut report = FixReport::default();
+
+    let next_starts = sessions.iter().skip(1).map(|s| Some(s.start)).collect_vec();
+    for (session, next_start) in sessions.iter_mut().zip(next_starts) {
+        if session.is_finished() {
+            continue;
+        }
+        let last = session
+            .paused_at
+            .or(session.pauses.last().map(|p| p.end))
+            .unwrap_or(session.start);
+        let end = next_start.map_or(last + close_after, |next| {
+            (last + close_after).min(next.max(last))
+        });
+        if let Some(paused_at) = session.paused_at.take() {
+            session.pauses.push(paused_at..end);
+        }
+        session.end = Some(end);
+        report.closed += 1;
+    }
+
+    report.sorted = !sessions.is_sorted_by_key(|s| s.start);
+    sessions.sort_by_key(|s| s.start);
+
+    let len = sessions.len();
+    let mut kept: Vec<MaybeFinishedSession> = Vec::with_capacity(len);
+    for session in sessions {
+        // sorted, so a duplicate starts at the same time but may not be right before it
+        let duplicated = kept
+            .iter()
+            .rev()
+            .take_while(|k| k.start == session.start)
+            .any(|k| *k == session);
+        if !duplicated {
+            kept.push(session);
+        }
+    }
+    report.deduplicated = len - kept.len();
+
+    (kept, report)
+}
+
+/// How far a session went, to choose between two versions of it.
+fn progress(session: &MaybeFinishedSession) -> (bool, usize, usize) {
+    (
+        session.is_finished(),
+        session.pauses.len() + usize::from(session.paused_at.is_some()),
+        session.description.len(),
+    )
+}
+
+/// Joins two copies of the sessions of a project, like the two sides of a merge. Sessions
+/// starting at the same time are taken as the same one, keeping the version that went further
+/// (finished, paused more times or described more), or `ours` when neither did.
+pub fn merge_sessions(
+    ours: Vec<MaybeFinishedSession>,
+    theirs: Vec<MaybeFinishedSession>,
+) -> Vec<MaybeFinishedSession> {
+    let mut sessions = ours.into_iter().chain(theirs).collect_vec();
+    // stable, so ours stays before theirs when they start at the same time
+    sessions.sort_by_key(|s| s.start);
+    sessions.dedup_by(|later, earlier| {
+        if later.start != earlier.start {
+            return false;
+        }
+        if progress(later) > progress(earlier) {
+            std::mem::swap(later, earlier);
+        }
+        true
+    });
+    sessions
+}
+
+/// Like [`merge_sessions`], asking `choose` what to keep when a session of theirs overlaps with
+/// sessions of ours that start at another time. Running sessions overlap with everything after
+/// their start.
+pub fn merge_sessions_with(
+    mut ours: Vec<MaybeFinishedSession>,
+    theirs: Vec<MaybeFinishedSession>,
+    mut choose: impl FnMut(&[&MaybeFinishedSession], &MaybeFinishedSession) -> Result<OverlapPolicy>,
+) -> Result<Vec<MaybeFinishedSession>> {
+    let end =
+        |session: &MaybeFinishedSession| session.end.unwrap_or(DateTime::<Utc>::MAX_UTC.into());
+    let mut kept = vec![];
+    for session in theirs {
+        // a session of ours starting at the same time is the same one, joined by merge_sessions
+        let overlapping = ours
+            .iter()
+            .positions(|o| {
+                o.start != session.start && o.start < end(&session) && session.start < end(o)
+            })
+            .collect_vec();
+        if overlapping.is_empty() {
+            kept.push(session);
+            continue;
+        }
+        let overlapped = overlapping.iter().map(|&i| &ours[i]).collect_vec();
+        match choose(&overlapped, &session)? {
+            OverlapPolicy::Ours => {}
+            OverlapPolicy::Theirs => {
+                ours = ours
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| !overlapping.contains(i))
+                    .map(|(_, o)| o)
+                    .collect();
+                kept.push(session);
+            }
+            OverlapPolicy::Both => kept.push(session),
+        }
+    }
+    Ok(merge_sessions(ours, kept))
+}
+
+/// Rewrites the project with [`fix_sessions`] and prints what was fixed, as in `clockin fix`. The
+/// malformed macros are left to be fixed by hand.
+pub fn fix_file(path: &Path, close_after: TimeDelta) -> Result<()> {
+    let mut diagnostics = vec![];
+    for path in file::session_files(path)? {
+        diagnostics.extend(check::check_file(path)?);
+    }
+    if diagnostics
+        .iter()
+        .any(|d| matches!(d.problem, check::Problem::MalformedMacro(_)))
+    {
+        bail!("the file has malformed macros that must be fixed by hand, see `clockin check`");
+    }
+
+    let sessions = parser::parse_sessions(path)?;
+    let (sessions, report) = fix_sessions(sessions, close_after);
+    file::backup_file(path)?;
+    writer::rewrite_sessions(path, &sessions)?;
+
+    println!("closed {} dangling sessions", report.closed);
+    if report.sorted {
+        println!("sorted sessions");
+    }
+    println!("removed {} duplicated sessions", report.deduplicated);
+    Ok(())
+}
+
+/// Merges another copy of the project file into it with [`merge_sessions_with`], returning how
+/// many sessions there were before and after. The project is only locked once `choose` answered,
+/// so it can take its time, and nothing is written if the project changed meanwhile.
+pub fn merge_file(
+    path: &Path,
+    other: &Path,
+    choose: impl FnMut(&[&MaybeFinishedSession], &MaybeFinishedSession) -> Result<OverlapPolicy>,
+) -> Result<(usize, usize)> {
+    let ours = parser::parse_sessions(path)?;
+    let before = ours.len();
+    let theirs = parser::parse_sessions(other)?;
+    let sessions = merge_sessions_with(ours.clone(), theirs, choose)?;
+    let _lock = writer::lock(path)?;
+    if parser::parse_sessions(path)? != ours {
+        bail!("the project changed while merging, nothing was written, merge again");
+    }
+    file::backup_file(path)?;
+    writer::rewrite_sessions(path, &sessions)?;
+    Ok((before, sessions.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::{FixReport, fix_sessions, merge_sessions, merge_sessions_with};
+    use crate::{
+        cli::OverlapPolicy,
+        testing::{session, time},
+    };
+
+    #[test]
+    fn fix() {
+        let (sessions, report) = fix_sessions(
+            vec![
+                session("2000-01-01T12:00:00Z")
+                    .end("2000-01-01T13:00:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T10:00:00Z").maybe_finished(),
+                session("2000-01-01T10:20:00Z")
+                    .end("2000-01-01T11:00:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T12:00:00Z")
+                    .end("2000-01-01T12:30:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T12:00:00Z")
+                    .end("2000-01-01T13:00:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T14:00:00Z").maybe_finished(),
+            ],
+            TimeDelta::minutes(30),
+        );
+
+        assert_eq!(
+            sessions,
+            vec![
+                session("2000-01-01T10:00:00Z")
+                    .end("2000-01-01T10:20:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T10:20:00Z")
+                    .end("2000-01-01T11:00:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T12:00:00Z")
+                    .end("2000-01-01T13:00:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T12:00:00Z")
+                    .end("2000-01-01T12:30:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T14:00:00Z").maybe_finished(),
+            ]
+        );
+        assert_eq!(
+            report,
+            FixReport {
+                closed: 1,
+                sorted: true,
+                deduplicated: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let merged = merge_sessions(
+            vec![
+                session("2000-01-01T10:00:00Z")
+                    .end("2000-01-01T11:00:00Z")
+                    .description("a")
+                    .maybe_finished(),
+                session("2000-01-02T10:00:00Z").maybe_finished(),
+            ],
+            vec![
+                session("2000-01-01T10:00:00Z")
+                    .end("2000-01-01T11:30:00Z")
+                    .description("a")
+                    .maybe_finished(),
+                session("2000-01-01T12:00:00Z")
+                    .end("2000-01-01T13:00:00Z")
+                    .description("b")
+                    .maybe_finished(),
+                session("2000-01-02T10:00:00Z")
+                    .end("2000-01-02T11:00:00Z")
+                    .maybe_finished(),
+            ],
+        );
+        assert_eq!(
+            merged,
+            vec![
+                session("2000-01-01T10:00:00Z")
+                    .end("2000-01-01T11:00:00Z")
+                    .description("a")
+                    .maybe_finished(),
+                session("2000-01-01T12:00:00Z")
+                    .end("2000-01-01T13:00:00Z")
+                    .description("b")
+                    .maybe_finished(),
+                session("2000-01-02T10:00:00Z")
+                    .end("2000-01-02T11:00:00Z")
+                    .maybe_finished(),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_overlapping() {
+        let ours = || {
+            vec![
+                session("2000-01-01T10:00:00Z")
+                    .end("2000-01-01T11:00:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T12:00:00Z")
+                    .end("2000-01-01T13:00:00Z")
+                    .maybe_finished(),
+            ]
+        };
+        let theirs = || {
+            vec![
+                session("2000-01-01T10:30:00Z")
+                    .end("2000-01-01T12:30:00Z")
+                    .maybe_finished(),
+                session("2000-01-01T14:00:00Z")
+                    .end("2000-01-01T15:00:00Z")
+                    .maybe_finished(),
+            ]
+        };
+
+        let mut asked = vec![];
+        let merged = merge_sessions_with(ours(), theirs(), |overlapped, session| {
+            asked.push((overlapped.len(), session.start));
+            Ok(OverlapPolicy::Theirs)
+        })
+        .unwrap();
+        assert_eq!(asked, [(2, time("2000-01-01T10:30:00Z"))]);
+        assert_eq!(merged, theirs());
+
+        let merged = merge_sessions_with(ours(), theirs(), |_, _| Ok(OverlapPolicy::Ours)).unwrap();
+        assert_eq!(merged.len(), 3);
+        let merged = merge_sessions_with(ours(), theirs(), |_, _| Ok(OverlapPolicy::Both)).unwrap();
+        assert_eq!(merged.len(), 4);
+
+        // their version of a session of ours still overlaps with the next one
+        let longer = session("2000-01-01T10:00:00Z")
+            .end("2000-01-01T12:30:00Z")
+            .maybe_finished();
+        let mut asked = vec![];
+        let merged = merge_sessions_with(ours(), vec![longer], |overlapped, session| {
+            asked.push((overlapped.len(), session.start));
+            Ok(OverlapPolicy::Ours)
+        })
+        .unwrap();
+        assert_eq!(asked, [(1, time("2000-01-01T10:00:00Z"))]);
+        assert_eq!(merged, ours());
+    }
+}
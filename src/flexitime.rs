@@ -0,0 +1,205 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{FixedOffset, Local, NaiveDate, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    budget::parse_time, cli::FlexitimeCommand, config, format_util::fmt_signed_duration, overtime,
+    parser, parser::SessionIteratorClosingExt, project_config::ProjectConfig, schedule::Schedule,
+    summary::Summary, writer,
+};
+
+/// Hours banked or owed since a date, carried over from one week to the next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Flexitime {
+    /// first day counted in the balance
+    pub since: NaiveDate,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adjustments: Vec<Adjustment>,
+}
+
+/// Time added to or removed from the balance by hand, like a carried over balance or comp time
+/// that was paid out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Adjustment {
+    pub date: NaiveDate,
+    pub hours: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Adjustment {
+    pub fn time(&self) -> TimeDelta {
+        TimeDelta::milliseconds((self.hours * 3_600_000.0).round() as i64)
+    }
+}
+
+/// Parses a time like `+8h`, `-90min` or `2.5h`.
+pub fn parse_signed_time(text: &str) -> Result<TimeDelta> {
+    let text = text.trim();
+    let (sign, time) = match text.strip_prefix('-') {
+        Some(time) => (-1, time),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    Ok(TimeDelta::from_std(parse_time(time)?)? * sign)
+}
+
+impl Flexitime {
+    /// Balance of the days since `since` that are over by `today`, along with the adjustments.
+    pub fn balance(&self, summary: &Summary, schedule: &Schedule, today: NaiveDate) -> TimeDelta {
+        let days: TimeDelta = match today.pred_opt() {
+            Some(yesterday) if self.since <= yesterday => {
+                overtime::balances(summary, schedule, self.since, yesterday)
+                    .iter()
+                    .map(|d| d.difference())
+                    .sum()
+            }
+            _ => TimeDelta::zero(),
+        };
+        days + self.adjustments.iter().map(Adjustment::time).sum()
+    }
+}
+
+/// Flexitime balance of the project up to today, if it keeps one.
+pub fn project_balance(
+    path: &Path,
+    config: &ProjectConfig,
+    timezone: &FixedOffset,
+) -> Result<Option<TimeDelta>> {
+    let Some(flexitime) = &config.flexitime else {
+        return Ok(None);
+    };
+    // there is nothing to compare against until a schedule is set
+    if config.schedule.is_none() && config.weekly_hours.is_none() {
+        return Ok(None);
+    }
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now();
+    let summary = Summary::summarize(sessions, timezone);
+    let today = Local::now().with_timezone(timezone).date_naive();
+    Ok(Some(flexitime.balance(
+        &summary,
+        &config.schedule()?,
+        today,
+    )))
+}
+
+/// Starts tracking the balance, adjusts it or prints it, as in `clockin flexitime`.
+pub fn run(
+    path: &Path,
+    command: Option<FlexitimeCommand>,
+    timezone: Option<FixedOffset>,
+) -> Result<()> {
+    // starting and adjusting rewrite the settings, which must not change in between
+    let _lock = command.is_some().then(|| writer::lock(path)).transpose()?;
+    let mut config = ProjectConfig::load(path)?;
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let today = Local::now().with_timezone(&timezone).date_naive();
+    match command {
+        Some(FlexitimeCommand::Start { since, balance }) => {
+            let mut flexitime = Flexitime {
+                since,
+                adjustments: vec![],
+            };
+            if let Some(balance) = balance {
+                let balance = parse_signed_time(&balance)?;
+                flexitime.adjustments.push(Adjustment {
+                    date: since,
+                    hours: balance.as_seconds_f64() / 3600.0,
+                    note: Some("carried over".to_owned()),
+                });
+            }
+            config.flexitime = Some(flexitime);
+            config.save(path)?;
+        }
+        Some(FlexitimeCommand::Adjust {
+            time,
+            date,
+            message,
+        }) => {
+            let time = parse_signed_time(&time)?;
+            let flexitime = config
+                .flexitime
+                .as_mut()
+                .context("flexitime is not tracked, start it with `clockin flexitime start`")?;
+            flexitime.adjustments.push(Adjustment {
+                date: date.unwrap_or(today),
+                hours: time.as_seconds_f64() / 3600.0,
+                note: message,
+            });
+            config.save(path)?;
+        }
+        None => {
+            let flexitime = config
+                .flexitime
+                .as_ref()
+                .context("flexitime is not tracked, start it with `clockin flexitime start`")?;
+            let sessions = parser::parse_sessions(path)?
+                .into_iter()
+                .into_finished_now();
+            let summary = Summary::summarize(sessions, &timezone);
+            let balance = flexitime.balance(&summary, &config.schedule()?, today);
+            println!(
+                "balance since {}: {}",
+                flexitime.since,
+                fmt_signed_duration(&balance)
+            );
+            for adjustment in &flexitime.adjustments {
+                println!(
+                    "- {} {} {}",
+                    adjustment.date,
+                    fmt_signed_duration(&adjustment.time()),
+                    adjustment.note.as_deref().unwrap_or_default()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeDelta, Utc};
+
+    use super::{Adjustment, Flexitime, parse_signed_time};
+    use crate::{summary::Summary, testing::session};
+
+    #[test]
+    fn balance() {
+        assert_eq!(
+            parse_signed_time("-90min").unwrap(),
+            TimeDelta::minutes(-90)
+        );
+        assert_eq!(parse_signed_time("+2.5h").unwrap(), TimeDelta::minutes(150));
+
+        let summary = Summary::summarize(
+            [session("2000-01-03T08:00:00Z")
+                .end("2000-01-03T18:00:00Z")
+                .build()]
+            .into_iter(),
+            &Utc,
+        );
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+        let flexitime = Flexitime {
+            since: date(3),
+            adjustments: vec![Adjustment {
+                date: date(3),
+                hours: 5.0,
+                note: Some("carried over".to_owned()),
+            }],
+        };
+        let schedule = "mon-fri 8h".parse().unwrap();
+
+        // monday +2h, tuesday -8h, wednesday is not over yet
+        assert_eq!(
+            flexitime.balance(&summary, &schedule, date(5)),
+            TimeDelta::hours(5 + 2 - 8)
+        );
+        assert_eq!(
+            flexitime.balance(&summary, &schedule, date(3)),
+            TimeDelta::hours(5)
+        );
+    }
+}
@@ -1,8 +1,8 @@
 use std::time::Duration;
 
-use chrono::{NaiveTime, Timelike, Weekday};
+use chrono::{NaiveTime, TimeDelta, Timelike, Weekday};
 
-use crate::summary::MonthId;
+use crate::{i18n::catalog, summary::MonthId};
 
 
 
@@ -15,10 +15,25 @@ pub fn fmt_duration(duration: &Duration) -> String {
     format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
 }
 
+/// Hours and minutes like `6h 12m`, just the minutes under an hour.
+pub fn fmt_duration_human(duration: &Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, minutes) => format!("{hours}h {minutes}m"),
+    }
+}
+
+/// Like [`fmt_duration`], always with a sign, for differences and balances.
+pub fn fmt_signed_duration(delta: &TimeDelta) -> String {
+    let sign = if *delta < TimeDelta::zero() { '-' } else { '+' };
+    format!("{}{}", sign, fmt_duration(&delta.abs().to_std().unwrap()))
+}
+
 pub fn fmt_duration_uncertain(duration: &Duration, completed: bool) -> String {
     let mut out = fmt_duration(duration);
     if !completed {
-        out.push_str(" (incompleto)");
+        out.push_str(&format!(" ({})", catalog().incomplete));
     }
 
     out
@@ -26,44 +41,94 @@ pub fn fmt_duration_uncertain(duration: &Duration, completed: bool) -> String {
 
 pub fn fmt_duration_uncertain_with_hs(duration: &Duration, completed: bool) -> String {
     let mut out = fmt_duration(duration);
-    out.push_str(" hs");
+    out.push(' ');
+    out.push_str(catalog().hours_abbreviation);
     if !completed {
-        out.push_str(" (incompleto)");
+        out.push_str(&format!(" ({})", catalog().incomplete));
     }
 
     out
 }
 
 pub fn fmt_month(month: MonthId) -> String {
-    let month_name = [
-        "Enero",
-        "Febrero",
-        "Marzo",
-        "Abril",
-        "Mayo",
-        "Junio",
-        "Julio",
-        "Agosto",
-        "Septiembre",
-        "Octubre",
-        "Noviembre",
-        "Diciembre",
-    ][month.month() as usize];
+    let month_name = catalog().months[month.month() as usize];
     format!("{} {}", month_name, month.year())
 }
 
 pub fn fmt_weekday(day: Weekday) -> &'static str {
-    match day {
-        Weekday::Mon => "Lunes",
-        Weekday::Tue => "Martes",
-        Weekday::Wed => "Miércoles",
-        Weekday::Thu => "Jueves",
-        Weekday::Fri => "Viernes",
-        Weekday::Sat => "Sabado",
-        Weekday::Sun => "Domingo",
-    }
+    catalog().weekdays[day.num_days_from_monday() as usize]
+}
+
+/// The first three letters of the weekday, for narrow columns.
+pub fn fmt_short_weekday(day: Weekday) -> String {
+    fmt_weekday(day).chars().take(3).collect()
+}
+
+/// The first three letters of the month, without the year.
+pub fn fmt_short_month(month: MonthId) -> String {
+    catalog().months[month.month() as usize]
+        .chars()
+        .take(3)
+        .collect()
+}
+
+/// A bar of `#` proportional to the fraction, `width` being the length of a whole bar.
+pub fn fmt_bar(fraction: f64, width: f64) -> String {
+    "#".repeat((width * fraction).round() as usize)
+}
+
+/// A block per value, as high as the value relative to the largest one. Zeros are left blank.
+pub fn fmt_sparkline(values: &[Duration]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().max().copied().unwrap_or_default();
+    values
+        .iter()
+        .map(|value| {
+            if value.is_zero() {
+                ' '
+            } else {
+                let fraction = value.as_secs_f64() / max.as_secs_f64();
+                let level = (fraction * BLOCKS.len() as f64).ceil() as usize;
+                BLOCKS[level.clamp(1, BLOCKS.len()) - 1]
+            }
+        })
+        .collect()
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 pub fn fmt_hours_mins(t: NaiveTime) -> String {
     format!("{:02}:{:02}", t.hour(), t.minute())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{fmt_duration_human, fmt_sparkline};
+
+    #[test]
+    fn sparkline() {
+        let hours = |h: u64| Duration::from_secs(h * 3600);
+        assert_eq!(
+            fmt_sparkline(&[hours(8), hours(1), hours(4), hours(0), hours(6)]),
+            "█▁▄ ▆"
+        );
+        assert_eq!(fmt_sparkline(&[hours(0); 2]), "  ");
+    }
+
+    #[test]
+    fn human_duration() {
+        assert_eq!(
+            fmt_duration_human(&Duration::from_secs(6 * 3600 + 12 * 60 + 59)),
+            "6h 12m"
+        );
+        assert_eq!(fmt_duration_human(&Duration::from_secs(3600)), "1h 0m");
+        assert_eq!(fmt_duration_human(&Duration::from_secs(59)), "0m");
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::FixedOffset;
+
+use clockin::{
+    config::Config,
+    crypt, file, fix,
+    parser::{self, MaybeFinishedSessionTZ},
+    writer,
+};
+
+/// Files of the data directory that are not worth versioning: the locks, the temporary files
+/// of the rewrites and the backups, which git makes redundant.
+const GITIGNORE: &str = ".*.lock\n.*.tmp\n.backups/\n";
+
+fn command(args: &[&str]) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(file::get_data_dir()).args(args);
+    command
+}
+
+fn git(args: &[&str]) -> Result<()> {
+    let status = command(args)
+        .status()
+        .context("error while trying to run git")?;
+    if !status.success() {
+        bail!("git {} failed with {status}", args.join(" "));
+    }
+    Ok(())
+}
+
+/// Like [`git`], returning what it printed.
+fn git_output(args: &[&str]) -> Result<String> {
+    let output = command(args)
+        .stderr(Stdio::inherit())
+        .output()
+        .context("error while trying to run git")?;
+    if !output.status.success() {
+        bail!("git {} failed with {}", args.join(" "), output.status);
+    }
+    String::from_utf8(output.stdout).context("git printed invalid UTF-8")
+}
+
+fn is_repository() -> bool {
+    file::get_data_dir().join(".git").exists()
+}
+
+/// Commits whatever changed in the data directory, returning whether there was anything.
+fn commit_all(message: &str) -> Result<bool> {
+    git(&["add", "--all"])?;
+    if git(&["diff", "--cached", "--quiet"]).is_ok() {
+        return Ok(false);
+    }
+    git(&["commit", "--quiet", "--message", message])?;
+    Ok(true)
+}
+
+pub fn init() -> Result<()> {
+    if is_repository() {
+        bail!("the data directory is already a git repository");
+    }
+    git(&["init", "--quiet"])?;
+    let gitignore = file::get_data_dir().join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(gitignore, GITIGNORE).context("error while writing .gitignore")?;
+    }
+    commit_all("clockin: start versioning")?;
+    Ok(())
+}
+
+/// Commits the change just made to the project, when the data directory is a git repository and
+/// the configuration does not turn it off. The change is already written, so failures are only
+/// reported.
+pub fn auto_commit(path: &Path, action: &str) {
+    if !is_repository() {
+        return;
+    }
+    let commit = || -> Result<()> {
+        if Config::get()?.git_auto_commit == Some(false) {
+            return Ok(());
+        }
+        commit_all(&format!("{}: {action}", file::project_name(path)?))?;
+        Ok(())
+    };
+    if let Err(err) = commit() {
+        eprintln!("unable to commit the change: {err:#}");
+    }
+}
+
+/// The plain content and the sessions of a version of a file that git keeps while merging, 2
+/// being ours and 3 theirs.
+fn staged_sessions(
+    path: &str,
+    stage: u8,
+) -> Result<(String, Vec<MaybeFinishedSessionTZ<FixedOffset>>)> {
+    let content = git_output(&["show", &format!(":{stage}:{path}")])?;
+    let plain = String::from_utf8(crypt::decrypt(content.into_bytes())?)
+        .with_context(|| format!("{path} is not valid UTF-8"))?;
+    let sessions = parser::parse_reader(plain.as_bytes())?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("error while parsing {path}"))?;
+    Ok((plain, sessions))
+}
+
+/// Replaces the conflicting file of a project with the sessions of both sides, the header being
+/// the one of our side.
+fn resolve_conflict(path: &str) -> Result<()> {
+    let full_path = file::get_data_dir().join(path);
+    let project = file::list_project_files()?
+        .into_iter()
+        .find(|project| file::is_session_file(project, &full_path))
+        .with_context(|| format!("{path} is not a project file"))?;
+    let (ours, our_sessions) = staged_sessions(path, 2)?;
+    let (_, their_sessions) = staged_sessions(path, 3)?;
+    let _lock = writer::lock(&project)?;
+    let sessions = fix::merge_sessions(our_sessions, their_sessions);
+    writer::write_session_file(
+        &project,
+        &full_path,
+        writer::replace_sessions(&ours, &sessions),
+    )?;
+    git(&["add", "--", path])
+}
+
+/// Runs a git command that merges into the current branch, resolving the conflicts in project
+/// files as [`resolve_conflict`] does.
+fn merge(args: &[&str]) -> Result<()> {
+    let Err(err) = git(args) else {
+        return Ok(());
+    };
+    let conflicts = git_output(&["diff", "--name-only", "--diff-filter=U"])?;
+    if conflicts.is_empty() {
+        return Err(err);
+    }
+    for path in conflicts.lines() {
+        resolve_conflict(path).with_context(|| {
+            format!(
+                "unable to merge {path}, resolve the conflicts by hand in the data directory \
+                 (see `clockin cd`) and commit them"
+            )
+        })?;
+        println!("merged the sessions of {path}");
+    }
+    git(&["commit", "--no-edit", "--quiet"])
+}
+
+/// Commits the pending changes, pulls and pushes. Conflicts in project files are resolved by
+/// joining the sessions of both sides, any other one is left for the user.
+pub fn sync() -> Result<()> {
+    if !is_repository() {
+        bail!("the data directory is not a git repository, start with `clockin git init`");
+    }
+    let remote = git_output(&["remote"])?
+        .lines()
+        .next()
+        .map(str::to_owned)
+        .context("the repository has no remote, add one with `clockin exec 'git remote add origin <url>'`")?;
+    commit_all("clockin: sync")?;
+
+    let has_upstream = command(&["rev-parse", "--abbrev-ref", "@{upstream}"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !has_upstream {
+        // another machine may have published its own history to the branch already
+        let branch = git_output(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+        let remote_branch = format!("{remote}/{}", branch.trim());
+        git(&["fetch", "--quiet", &remote])?;
+        let published = command(&["rev-parse", "--verify", "--quiet", &remote_branch])
+            .stdout(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if published {
+            merge(&[
+                "merge",
+                "--allow-unrelated-histories",
+                "--no-edit",
+                "--quiet",
+                &remote_branch,
+            ])?;
+        }
+        return git(&["push", "--quiet", "--set-upstream", &remote, "HEAD"]);
+    }
+    merge(&["pull", "--no-rebase", "--no-edit", "--quiet"])?;
+    git(&["push", "--quiet"])
+}
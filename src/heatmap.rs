@@ -0,0 +1,154 @@
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{Datelike, FixedOffset, Local, NaiveDate, TimeDelta, Weekday};
+
+use crate::{
+    config,
+    format_util::{fmt_duration, fmt_short_month, fmt_short_weekday},
+    i18n::catalog,
+    parser::{self, SessionIteratorClosingExt},
+    summary::{NaiveDateExt, Summary},
+    writer::ReportOutput,
+};
+
+/// From no work at all to the busiest days of the year.
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+const LABEL_WIDTH: usize = 4;
+
+fn shade(worked: Duration, max: Duration) -> char {
+    if worked.is_zero() {
+        return SHADES[0];
+    }
+    let fraction = worked.as_secs_f64() / max.as_secs_f64();
+    SHADES[((fraction * 4.0).ceil() as usize).clamp(1, 4)]
+}
+
+/// A row per weekday and a column per week of the year, each day shaded relative to the busiest
+/// one.
+pub fn format(summary: &Summary, year: i32, out: &mut impl Write) -> io::Result<()> {
+    let first = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+    let last = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+    let start = first.week(Weekday::Mon).first_day();
+    let weeks = (last - start).num_days() as usize / 7 + 1;
+    let worked = |date| {
+        summary
+            .days
+            .get(&date)
+            .map_or(Duration::ZERO, |d| d.duration)
+    };
+    let max = first
+        .iter_days()
+        .take_while(|date| *date <= last)
+        .map(worked)
+        .max()
+        .unwrap();
+
+    let mut header = " ".repeat(LABEL_WIDTH);
+    for month in 1..=12 {
+        let date = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        let column = LABEL_WIDTH + (date - start).num_days() as usize / 7;
+        if header.chars().count() <= column {
+            header.push_str(&" ".repeat(column - header.chars().count()));
+            header.push_str(&fmt_short_month(date.month_id()));
+        }
+    }
+    writeln!(out, "{}", header.trim_end())?;
+
+    for row in 0..7 {
+        let weekday = Weekday::try_from(row as u8).unwrap();
+        let mut line = format!("{:<LABEL_WIDTH$}", fmt_short_weekday(weekday));
+        for week in 0..weeks {
+            let date = start + TimeDelta::days((week * 7 + row) as i64);
+            line.push(if date.year() == year {
+                shade(worked(date), max)
+            } else {
+                ' '
+            });
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    let total: Duration = first
+        .iter_days()
+        .take_while(|date| *date <= last)
+        .map(worked)
+        .sum();
+    writeln!(
+        out,
+        "\n{} {} {} {}, {} {} {year}",
+        " ".repeat(LABEL_WIDTH),
+        catalog().less,
+        SHADES.iter().collect::<String>(),
+        catalog().more,
+        fmt_duration(&total),
+        catalog().worked_in
+    )
+}
+
+/// Prints the heatmap of `clockin heatmap` for the year, the current one by default.
+pub fn print_heatmap(
+    path: &Path,
+    year: Option<i32>,
+    timezone: Option<FixedOffset>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let year = year.unwrap_or_else(|| Local::now().with_timezone(&timezone).date_naive().year());
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now();
+    let summary = Summary::summarize(sessions, &timezone);
+
+    let mut out = ReportOutput::new(output);
+    format(&summary, year, &mut out)?;
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+
+    use super::format;
+    use crate::{summary::Summary, testing::session};
+
+    #[test]
+    fn heatmap() {
+        // 2001 starts on a monday
+        let summary = Summary::summarize(
+            [
+                session("2001-01-01T10:00:00Z")
+                    .lasting(TimeDelta::hours(8))
+                    .build(),
+                session("2001-01-02T10:00:00Z")
+                    .lasting(TimeDelta::hours(1))
+                    .build(),
+                session("2001-01-03T10:00:00Z")
+                    .lasting(TimeDelta::hours(4))
+                    .build(),
+                session("2001-01-09T10:00:00Z")
+                    .lasting(TimeDelta::hours(6))
+                    .build(),
+            ]
+            .into_iter(),
+            &Utc,
+        );
+        let mut out = vec![];
+        format(&summary, 2001, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+
+        assert!(lines[0].starts_with("    Ene Feb Mar Abr"));
+        assert!(lines[1].starts_with("Lun █··"));
+        assert!(lines[2].starts_with("Mar ░▓·"));
+        assert!(lines[3].starts_with("Mié ▒··"));
+        // the last week ends on monday 31st
+        assert_eq!(lines[1].chars().count(), 4 + 53);
+        assert_eq!(lines[2].chars().count(), 4 + 52);
+        assert_eq!(lines[9], "     menos ·░▒▓█ más, 19:00:00 trabajado en 2001");
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::OnceLock;
+
+use crate::cli::Lang;
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Texts of the reports in one language.
+pub struct Catalog {
+    pub months: [&'static str; 12],
+    /// starting on monday
+    pub weekdays: [&'static str; 7],
+    pub date_format: &'static str,
+    /// without the year
+    pub short_date_format: &'static str,
+    pub incomplete: &'static str,
+    pub hours_abbreviation: &'static str,
+    /// sub-project of the sessions without one
+    pub uncategorized: &'static str,
+    /// machine of the sessions without a `%h` line
+    pub unknown_host: &'static str,
+    pub budget: &'static str,
+    /// as in "10 of 20 hours"
+    pub of: &'static str,
+    pub invoice: &'static str,
+    pub client: &'static str,
+    pub period: &'static str,
+    pub rate: &'static str,
+    pub per_hour: &'static str,
+    pub amount: &'static str,
+    pub date: &'static str,
+    pub sub_project: &'static str,
+    pub sub_projects: &'static str,
+    pub projects: &'static str,
+    pub month: &'static str,
+    pub months_heading: &'static str,
+    pub days: &'static str,
+    pub hours: &'static str,
+    pub total: &'static str,
+    pub report_title: &'static str,
+    /// as in "from 01/01 to 31/01"
+    pub from: &'static str,
+    pub to: &'static str,
+    pub hours_per_day: &'static str,
+    pub hours_per_week: &'static str,
+    pub hours_per_time_of_day: &'static str,
+    pub this_month: &'static str,
+    /// states of the project in the dashboard
+    pub running: &'static str,
+    pub paused: &'static str,
+    pub stopped: &'static str,
+    pub no_sessions: &'static str,
+    /// as in "worked 08:00:00  expected 07:00:00"
+    pub worked: &'static str,
+    pub expected: &'static str,
+    pub balance: &'static str,
+    pub week: &'static str,
+    /// ends of the heatmap legend
+    pub less: &'static str,
+    pub more: &'static str,
+    /// as in "19:00:00 worked in 2001"
+    pub worked_in: &'static str,
+    pub current_streak: &'static str,
+    pub longest_streak: &'static str,
+    /// left and over a budget
+    pub left: &'static str,
+    pub over: &'static str,
+    pub this_week: &'static str,
+    pub flexitime_balance: &'static str,
+}
+
+const ES: Catalog = Catalog {
+    months: [
+        "Enero",
+        "Febrero",
+        "Marzo",
+        "Abril",
+        "Mayo",
+        "Junio",
+        "Julio",
+        "Agosto",
+        "Septiembre",
+        "Octubre",
+        "Noviembre",
+        "Diciembre",
+    ],
+    weekdays: [
+        "Lunes",
+        "Martes",
+        "Miércoles",
+        "Jueves",
+        "Viernes",
+        "Sabado",
+        "Domingo",
+    ],
+    date_format: "%d/%m/%Y",
+    short_date_format: "%d/%m",
+    incomplete: "incompleto",
+    hours_abbreviation: "hs",
+    uncategorized: "sin categoría",
+    unknown_host: "equipo desconocido",
+    budget: "Presupuesto",
+    of: "de",
+    invoice: "Factura",
+    client: "Cliente",
+    period: "Período",
+    rate: "Tarifa",
+    per_hour: "por hora",
+    amount: "Importe",
+    date: "Fecha",
+    sub_project: "Subproyecto",
+    sub_projects: "Subproyectos",
+    projects: "Proyectos",
+    month: "Mes",
+    months_heading: "Meses",
+    days: "Días",
+    hours: "Horas",
+    total: "Total",
+    report_title: "Reporte de horas",
+    from: "Del",
+    to: "al",
+    hours_per_day: "Horas por día",
+    hours_per_week: "Horas por semana",
+    hours_per_time_of_day: "Horas por horario del día",
+    this_month: "Este mes",
+    running: "En curso",
+    paused: "En pausa",
+    stopped: "Detenido",
+    no_sessions: "Sin sesiones",
+    worked: "trabajado",
+    expected: "esperado",
+    balance: "saldo",
+    week: "Semana",
+    less: "menos",
+    more: "más",
+    worked_in: "trabajado en",
+    current_streak: "Racha actual",
+    longest_streak: "Racha más larga",
+    left: "restante",
+    over: "excedido",
+    this_week: "Esta semana",
+    flexitime_balance: "Saldo de horario flexible",
+};
+
+const EN: Catalog = Catalog {
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    weekdays: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    date_format: "%m/%d/%Y",
+    short_date_format: "%m/%d",
+    incomplete: "incomplete",
+    hours_abbreviation: "h",
+    uncategorized: "uncategorized",
+    unknown_host: "unknown host",
+    budget: "Budget",
+    of: "of",
+    invoice: "Invoice",
+    client: "Client",
+    period: "Period",
+    rate: "Rate",
+    per_hour: "per hour",
+    amount: "Amount",
+    date: "Date",
+    sub_project: "Sub-project",
+    sub_projects: "Sub-projects",
+    projects: "Projects",
+    month: "Month",
+    months_heading: "Months",
+    days: "Days",
+    hours: "Hours",
+    total: "Total",
+    report_title: "Time report",
+    from: "From",
+    to: "to",
+    hours_per_day: "Hours per day",
+    hours_per_week: "Hours per week",
+    hours_per_time_of_day: "Hours per time of day",
+    this_month: "This month",
+    running: "Running",
+    paused: "Paused",
+    stopped: "Stopped",
+    no_sessions: "No sessions",
+    worked: "worked",
+    expected: "expected",
+    balance: "balance",
+    week: "Week",
+    less: "less",
+    more: "more",
+    worked_in: "worked in",
+    current_streak: "Current streak",
+    longest_streak: "Longest streak",
+    left: "left",
+    over: "over",
+    this_week: "This week",
+    flexitime_balance: "Flexitime balance",
+};
+
+impl Lang {
+    pub fn catalog(self) -> &'static Catalog {
+        match self {
+            Lang::En => &EN,
+            Lang::Es => &ES,
+        }
+    }
+}
+
+/// Sets the language of the reports, only the first call has an effect.
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+/// The texts in the language of the reports, Spanish unless set otherwise.
+pub fn catalog() -> &'static Catalog {
+    LANG.get().copied().unwrap_or_default().catalog()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+
+    use crate::cli::Lang;
+
+    #[test]
+    fn catalogs() {
+        assert_eq!("en".parse(), Ok(Lang::En));
+        assert!("fr".parse::<Lang>().is_err());
+        let sunday = Weekday::Sun.num_days_from_monday() as usize;
+        assert_eq!(Lang::En.catalog().weekdays[sunday], "Sunday");
+        assert_eq!(Lang::Es.catalog().weekdays[sunday], "Domingo");
+        assert_eq!(Lang::En.catalog().months[11], "December");
+    }
+}
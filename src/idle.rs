@@ -0,0 +1,198 @@
+use std::{
+    env,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local, TimeDelta};
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, delegate_noop,
+    protocol::{wl_registry, wl_seat::WlSeat},
+};
+use wayland_protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+    ext_idle_notifier_v1::ExtIdleNotifierV1,
+};
+use x11rb::{connection::Connection as _, protocol::screensaver};
+
+use clockin::{parser, writer};
+
+const X11_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+enum IdleEvent {
+    /// the user has been idle since the given instant
+    Idle(DateTime<Local>),
+    Active,
+}
+
+fn idle_since(idle_time: Duration) -> DateTime<Local> {
+    Local::now() - TimeDelta::from_std(idle_time).unwrap_or_default()
+}
+
+fn watch_x11(threshold: Duration, events: Sender<IdleEvent>) -> Result<()> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let mut idle = false;
+    loop {
+        let idle_time = Duration::from_millis(
+            screensaver::query_info(&conn, root)?
+                .reply()?
+                .ms_since_user_input
+                .into(),
+        );
+
+        let event = if !idle && idle_time >= threshold {
+            Some(IdleEvent::Idle(idle_since(idle_time)))
+        } else if idle && idle_time < threshold {
+            Some(IdleEvent::Active)
+        } else {
+            None
+        };
+        if let Some(event) = event {
+            idle = !idle;
+            if events.send(event).is_err() {
+                return Ok(());
+            }
+        }
+
+        thread::sleep(X11_POLL_INTERVAL);
+    }
+}
+
+struct WaylandState {
+    seat: Option<WlSeat>,
+    notifier: Option<ExtIdleNotifierV1>,
+    threshold: Duration,
+    events: Sender<IdleEvent>,
+    stopped: bool,
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _data: &(),
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global {
+            name, interface, ..
+        } = event
+        {
+            match interface.as_str() {
+                "wl_seat" if state.seat.is_none() => {
+                    state.seat = Some(registry.bind(name, 1, qh, ()));
+                }
+                "ext_idle_notifier_v1" => {
+                    state.notifier = Some(registry.bind(name, 1, qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Dispatch<ExtIdleNotificationV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _notification: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let event = match event {
+            ext_idle_notification_v1::Event::Idled => IdleEvent::Idle(idle_since(state.threshold)),
+            ext_idle_notification_v1::Event::Resumed => IdleEvent::Active,
+            _ => return,
+        };
+        state.stopped |= state.events.send(event).is_err();
+    }
+}
+
+delegate_noop!(WaylandState: ignore WlSeat);
+delegate_noop!(WaylandState: ExtIdleNotifierV1);
+
+fn watch_wayland(threshold: Duration, events: Sender<IdleEvent>) -> Result<()> {
+    let conn = Connection::connect_to_env()?;
+    let mut queue = conn.new_event_queue();
+    let qh = queue.handle();
+    conn.display().get_registry(&qh, ());
+
+    let mut state = WaylandState {
+        seat: None,
+        notifier: None,
+        threshold,
+        events,
+        stopped: false,
+    };
+    queue.roundtrip(&mut state)?;
+
+    let (Some(seat), Some(notifier)) = (&state.seat, &state.notifier) else {
+        bail!("the compositor does not support the ext-idle-notify protocol");
+    };
+    notifier.get_idle_notification(
+        threshold.as_millis().try_into().unwrap_or(u32::MAX),
+        seat,
+        &qh,
+        (),
+    );
+
+    while !state.stopped {
+        queue.blocking_dispatch(&mut state)?;
+    }
+    Ok(())
+}
+
+pub fn idle_watch(path: &PathBuf, threshold: Duration, cancel: Receiver<()>) -> Result<()> {
+    let watcher = if env::var_os("WAYLAND_DISPLAY").is_some() {
+        watch_wayland
+    } else if env::var_os("DISPLAY").is_some() {
+        watch_x11
+    } else {
+        bail!("no graphical session found to detect idleness");
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let watcher = thread::spawn(move || watcher(threshold, tx));
+
+    // only resume the pauses inserted by this process, not the ones made by the user
+    let mut auto_paused = false;
+    while cancel.try_recv().is_err() {
+        match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(IdleEvent::Idle(since)) => {
+                let Some(session) =
+                    parser::last_session(path)?.filter(|s| !s.is_finished() && !s.is_paused())
+                else {
+                    continue;
+                };
+                let earliest = session.pauses.last().map_or(session.start, |p| p.end);
+                writer::write_datetime(path, since.fixed_offset().max(earliest), false, 'p')?;
+                auto_paused = true;
+                println!("paused");
+            }
+            Ok(IdleEvent::Active) => {
+                if auto_paused
+                    && parser::last_session(path)?
+                        .is_some_and(|s| !s.is_finished() && s.is_paused())
+                {
+                    writer::write_date(path, false, 'r')?;
+                    println!("resumed");
+                }
+                auto_paused = false;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return watcher.join().expect("idle watcher thread panicked");
+            }
+        }
+    }
+
+    Ok(())
+}
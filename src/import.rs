@@ -0,0 +1,416 @@
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, de::IgnoredAny};
+
+use itertools::Itertools;
+
+#[cfg(feature = "sqlite")]
+use crate::sqlite;
+use crate::{
+    cli::ImportCommand,
+    file,
+    parser::{self, Session},
+    writer,
+};
+
+const TIMEWARRIOR_DATETIME: &str = "%Y%m%dT%H%M%SZ";
+
+/// Where timewarrior keeps its data, following the same lookup as timewarrior itself.
+pub fn timewarrior_data_dir() -> Option<PathBuf> {
+    if let Some(db) = env::var_os("TIMEWARRIORDB") {
+        return Some(PathBuf::from(db).join("data"));
+    }
+    let home = PathBuf::from(env::var_os("HOME")?);
+    let legacy = home.join(".timewarrior/data");
+    if legacy.is_dir() {
+        return Some(legacy);
+    }
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or(home.join(".local/share"));
+    Some(data_home.join("timewarrior/data"))
+}
+
+/// Splits a line in whitespace separated words, keeping quoted strings together. The flag tells
+/// whether the word was quoted.
+fn split_words(line: &str) -> Vec<(String, bool)> {
+    let mut words = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut word = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => word.extend(chars.next()),
+                    c => word.push(c),
+                }
+            }
+            words.push((word, true));
+        } else {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+            words.push((word, false));
+        }
+    }
+    words
+}
+
+/// Writes a tag of other tracker as a `#tag` token, tags with spaces would not be recognized
+/// as tags in the description otherwise.
+fn tag_token(tag: &str) -> String {
+    format!("#{}", tag.split_whitespace().collect::<Vec<_>>().join("-"))
+}
+
+fn parse_timewarrior_datetime(text: &str) -> Result<DateTime<Local>> {
+    let naive = NaiveDateTime::parse_from_str(text, TIMEWARRIOR_DATETIME)
+        .with_context(|| format!("invalid timestamp {text:?}"))?;
+    Ok(Utc.from_utc_datetime(&naive).with_timezone(&Local))
+}
+
+/// Parses an interval like `inc 20240101T100000Z - 20240101T110000Z # tag "other tag" # "note"`.
+/// Open intervals are skipped.
+fn parse_timewarrior_line(line: &str) -> Result<Option<Session>> {
+    let words = split_words(line);
+    let mut words = words.iter().map(|(w, quoted)| (w.as_str(), *quoted));
+
+    match words.next() {
+        None => return Ok(None),
+        Some(("inc", false)) => {}
+        Some((word, _)) => bail!("unknown interval kind {word:?}"),
+    }
+    let start = parse_timewarrior_datetime(words.next().context("missing start")?.0)?;
+    let end = match words.next() {
+        Some(("-", false)) => parse_timewarrior_datetime(words.next().context("missing end")?.0)?,
+        Some(("#", false)) | None => return Ok(None),
+        Some((word, _)) => bail!("unexpected {word:?}"),
+    };
+
+    let mut tags = vec![];
+    let mut annotation = None;
+    if let Some(word) = words.next() {
+        if word != ("#", false) {
+            bail!("unexpected {:?}", word.0);
+        }
+        for word in words.by_ref() {
+            if word == ("#", false) {
+                break;
+            }
+            tags.push(tag_token(word.0));
+        }
+        annotation = words.next().map(|(w, _)| w);
+    }
+
+    let description = annotation
+        .into_iter()
+        .map(str::to_owned)
+        .chain((!tags.is_empty()).then(|| tags.join(" ")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(Some(Session {
+        start: start.fixed_offset(),
+        end: end.fixed_offset(),
+        pauses: vec![],
+        description,
+        host: None,
+        id: None,
+    }))
+}
+
+/// Reads the finished intervals of every `YYYY-MM.data` file in the timewarrior data directory.
+pub fn read_timewarrior(dir: &Path) -> Result<Vec<Session>> {
+    let mut files = fs::read_dir(dir)
+        .with_context(|| format!("error while reading {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.retain(|f| f.extension().is_some_and(|e| e == "data"));
+    files.sort();
+
+    let mut sessions = vec![];
+    for file in files {
+        let content = fs::read_to_string(&file)?;
+        for (index, line) in content.lines().enumerate() {
+            sessions.extend(parse_timewarrior_line(line).with_context(|| {
+                format!("error while parsing {} line {}", file.display(), index + 1)
+            })?);
+        }
+    }
+    Ok(sessions)
+}
+
+/// A frame of Watson: start, stop, project, id, tags and last update.
+#[derive(Deserialize)]
+struct WatsonFrame(i64, i64, String, IgnoredAny, Vec<String>, IgnoredAny);
+
+fn from_timestamp(timestamp: i64) -> Result<DateTime<FixedOffset>> {
+    Ok(Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .with_context(|| format!("invalid timestamp {timestamp}"))?
+        .fixed_offset())
+}
+
+/// Reads a Watson frames file, returning the sessions along with their Watson project. Tags
+/// become the description.
+pub fn read_watson(frames: &Path) -> Result<Vec<(String, Session)>> {
+    let content = fs::read_to_string(frames)
+        .with_context(|| format!("error while reading {}", frames.display()))?;
+    let frames: Vec<WatsonFrame> = serde_json::from_str(&content)
+        .with_context(|| format!("error while parsing {}", frames.display()))?;
+
+    frames
+        .into_iter()
+        .map(|WatsonFrame(start, stop, project, _, tags, _)| {
+            let session = Session {
+                start: from_timestamp(start)?,
+                end: from_timestamp(stop)?,
+                pauses: vec![],
+                description: tags
+                    .iter()
+                    .map(|t| tag_token(t))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                host: None,
+                id: None,
+            };
+            Ok((project, session))
+        })
+        .collect()
+}
+
+fn parse_org_timestamp(text: &str) -> Result<DateTime<FixedOffset>> {
+    // the weekday in between may be localized, so only the date and the time are used
+    let inner = text
+        .trim()
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .with_context(|| format!("invalid timestamp {text:?}"))?;
+    let words = inner.split_whitespace().collect::<Vec<_>>();
+    let (Some(date), Some(time)) = (words.first(), words.last()) else {
+        bail!("invalid timestamp {text:?}");
+    };
+    NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M")
+        .ok()
+        .and_then(|t| t.and_local_timezone(Local).earliest())
+        .map(|t| t.fixed_offset())
+        .with_context(|| format!("invalid timestamp {text:?}"))
+}
+
+/// Description for the clocks under an org heading: its title without the stars and the
+/// TODO/DONE keyword, and its `:tags:` as `#tag` tokens.
+fn org_heading_description(heading: &str) -> String {
+    let mut title = heading.trim_start_matches('*').trim();
+    for keyword in ["TODO ", "DONE "] {
+        title = title.strip_prefix(keyword).unwrap_or(title);
+    }
+
+    let mut tags = vec![];
+    if let Some((rest, last)) = title.rsplit_once(char::is_whitespace)
+        && last.len() > 1
+        && last.starts_with(':')
+        && last.ends_with(':')
+    {
+        title = rest.trim_end();
+        tags = last
+            .split(':')
+            .filter(|t| !t.is_empty())
+            .map(tag_token)
+            .collect();
+    }
+
+    [title.to_owned()]
+        .into_iter()
+        .chain(tags)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses the `CLOCK: [start]--[end]` lines of an org document, using the enclosing heading as
+/// the description. Running clocks are skipped.
+fn parse_org(content: &str) -> Result<Vec<Session>> {
+    let mut sessions = vec![];
+    let mut description = String::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.starts_with('*') {
+            description = org_heading_description(line);
+        } else if let Some(clock) = line.trim_start().strip_prefix("CLOCK:") {
+            let clock = clock.split("=>").next().unwrap_or_default();
+            let Some((start, end)) = clock.split_once("--") else {
+                continue;
+            };
+            let parse =
+                |text| parse_org_timestamp(text).with_context(|| format!("line {}", index + 1));
+            sessions.push(Session {
+                start: parse(start)?,
+                end: parse(end)?,
+                pauses: vec![],
+                description: description.clone(),
+                host: None,
+                id: None,
+            });
+        }
+    }
+    Ok(sessions)
+}
+
+pub fn read_org(path: &Path) -> Result<Vec<Session>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("error while reading {}", path.display()))?;
+    parse_org(&content).with_context(|| format!("error while parsing {}", path.display()))
+}
+
+/// Adds the sessions that are not in the project file yet, judging by their start, and returns
+/// how many were added.
+pub fn import_sessions(path: &Path, sessions: Vec<Session>) -> Result<usize> {
+    let existing = parser::parse_sessions(path)?
+        .into_iter()
+        .map(|s| s.start)
+        .collect::<HashSet<_>>();
+    let sessions = sessions
+        .into_iter()
+        .filter(|s| !existing.contains(&s.start))
+        .collect::<Vec<_>>();
+    if sessions.is_empty() {
+        return Ok(0);
+    }
+
+    let count = sessions.len();
+    file::backup_file(path)?;
+    writer::insert_sessions(path, sessions)?;
+    Ok(count)
+}
+
+/// The sessions of the source of `clockin import` along with the project file each one goes to,
+/// the current one unless the Watson projects are split into their own.
+pub fn read_source(source: ImportCommand) -> Result<Vec<(PathBuf, Vec<Session>)>> {
+    Ok(match source {
+        ImportCommand::Timewarrior { data_dir } => {
+            let data_dir = data_dir
+                .or_else(timewarrior_data_dir)
+                .context("timewarrior data directory not found, use --data-dir")?;
+            vec![(file::require_clockin_file()?, read_timewarrior(&data_dir)?)]
+        }
+        ImportCommand::Org { file: org } => {
+            vec![(file::require_clockin_file()?, read_org(&org)?)]
+        }
+        #[cfg(feature = "sqlite")]
+        ImportCommand::Sqlite { db } => {
+            vec![(file::require_clockin_file()?, sqlite::import(&db)?)]
+        }
+        ImportCommand::Watson {
+            frames,
+            split_projects: false,
+        } => {
+            let sessions = read_watson(&frames)?
+                .into_iter()
+                .map(|(project, mut session)| {
+                    session.description = format!("{project}: {}", session.description)
+                        .trim_end()
+                        .to_owned();
+                    session
+                })
+                .collect();
+            vec![(file::require_clockin_file()?, sessions)]
+        }
+        ImportCommand::Watson {
+            frames,
+            split_projects: true,
+        } => read_watson(&frames)?
+            .into_iter()
+            .into_group_map()
+            .into_iter()
+            .map(|(project, sessions)| Ok((file::create_project_file(&project)?, sessions)))
+            .collect::<Result<_>>()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::{WatsonFrame, parse_org, parse_timewarrior_line};
+
+    #[test]
+    fn timewarrior() {
+        let session = parse_timewarrior_line(
+            r#"inc 20240101T100000Z - 20240101T113000Z # work "code review" # "PR \"42\"""#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            session.start,
+            DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap()
+        );
+        assert_eq!(
+            session.end,
+            DateTime::parse_from_rfc3339("2024-01-01T11:30:00Z").unwrap()
+        );
+        assert_eq!(session.description, r#"PR "42" #work #code-review"#);
+
+        let untagged = parse_timewarrior_line("inc 20240101T100000Z - 20240101T113000Z")
+            .unwrap()
+            .unwrap();
+        assert_eq!(untagged.description, "");
+
+        assert!(
+            parse_timewarrior_line("inc 20240101T100000Z # work")
+                .unwrap()
+                .is_none()
+        );
+        assert!(parse_timewarrior_line("exc monday").is_err());
+    }
+
+    #[test]
+    fn watson_frame() {
+        let frames: Vec<WatsonFrame> = serde_json::from_str(
+            r#"[[1704103200, 1704108600, "acme", "f00", ["code review", "api"], 1704108600]]"#,
+        )
+        .unwrap();
+        let WatsonFrame(start, stop, project, _, tags, _) = &frames[0];
+        assert_eq!(
+            (*start, *stop, project.as_str()),
+            (1704103200, 1704108600, "acme")
+        );
+        assert_eq!(tags, &["code review", "api"]);
+    }
+
+    #[test]
+    fn org() {
+        let sessions = parse_org(
+            "* Project\n\
+             ** TODO Fix login   :backend:bug:\n\
+             :LOGBOOK:\n\
+             CLOCK: [2024-01-02 Tue 10:00]--[2024-01-02 Tue 11:30] =>  1:30\n\
+             CLOCK: [2024-01-03 mié 09:00]--[2024-01-03 mié 09:15] =>  0:15\n\
+             CLOCK: [2024-01-04 Thu 09:00]\n\
+             :END:\n\
+             ** Meetings\n\
+             CLOCK: [2024-01-05 Fri 14:00]--[2024-01-05 Fri 15:00] =>  1:00\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            sessions
+                .iter()
+                .map(|s| (s.duration().num_minutes(), s.description.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (90, "Fix login #backend #bug"),
+                (15, "Fix login #backend #bug"),
+                (60, "Meetings")
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    ops::Bound,
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+
+use crate::{
+    binnacle_2::{self, BinnacleData},
+    cli::{InvoiceArgs, InvoiceFormat, InvoiceGrouping},
+    config,
+    format_util::{escape_html, fmt_month},
+    i18n::catalog,
+    parser::{self, SessionIteratorClosingExt},
+    project_config::ProjectConfig,
+    rounding,
+    summary::{MonthId, NaiveDateExt},
+    writer::ReportOutput,
+};
+
+#[derive(Debug)]
+pub struct LineItem {
+    pub description: String,
+    pub duration: Duration,
+}
+
+impl LineItem {
+    fn hours(&self) -> f64 {
+        self.duration.as_secs_f64() / 3600.0
+    }
+}
+
+#[derive(Debug)]
+pub struct Invoice {
+    pub number: String,
+    pub client: Option<String>,
+    pub month: MonthId,
+    pub rate: f64,
+    pub currency: Option<String>,
+    pub items: Vec<LineItem>,
+}
+
+/// Rounded to cents, so the total matches the sum of the printed line amounts.
+fn amount(hours: f64, rate: f64) -> f64 {
+    (hours * rate * 100.0).round() / 100.0
+}
+
+impl Invoice {
+    fn amount_header(&self) -> String {
+        match &self.currency {
+            Some(currency) => format!("{} ({currency})", catalog().amount),
+            None => catalog().amount.to_owned(),
+        }
+    }
+
+    fn rate_text(&self) -> String {
+        match &self.currency {
+            Some(currency) => format!("{:.2} {currency} {}", self.rate, catalog().per_hour),
+            None => format!("{:.2} {}", self.rate, catalog().per_hour),
+        }
+    }
+
+    fn total_hours(&self) -> f64 {
+        self.items.iter().map(LineItem::hours).sum()
+    }
+
+    fn total_amount(&self) -> f64 {
+        self.items
+            .iter()
+            .map(|i| amount(i.hours(), self.rate))
+            .sum()
+    }
+}
+
+/// One item per day, or per top level sub-project over the whole period.
+pub fn line_items(data: &BinnacleData, grouping: InvoiceGrouping) -> Vec<LineItem> {
+    let days = data.months.iter().flat_map(|m| &m.days);
+    match grouping {
+        InvoiceGrouping::Day => days
+            .map(|day| LineItem {
+                description: day.date.format(catalog().date_format).to_string(),
+                duration: day
+                    .sub_projects
+                    .iter()
+                    .filter(|sp| sp.path.len() == 1)
+                    .map(|sp| sp.info.total_time)
+                    .sum(),
+            })
+            .collect(),
+        InvoiceGrouping::SubProject => {
+            let mut sub_projects = BTreeMap::<&str, Duration>::new();
+            for sub_project in days.flat_map(|d| &d.sub_projects) {
+                if let [name] = sub_project.path.as_slice() {
+                    *sub_projects.entry(name).or_default() += sub_project.info.total_time;
+                }
+            }
+            sub_projects
+                .into_iter()
+                .map(|(name, duration)| LineItem {
+                    description: name.to_owned(),
+                    duration,
+                })
+                .collect()
+        }
+    }
+}
+
+fn item_header(grouping: InvoiceGrouping) -> &'static str {
+    match grouping {
+        InvoiceGrouping::Day => catalog().date,
+        InvoiceGrouping::SubProject => catalog().sub_project,
+    }
+}
+
+pub fn format_markdown(
+    invoice: &Invoice,
+    grouping: InvoiceGrouping,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let catalog = catalog();
+    writeln!(out, "# {} {}\n", catalog.invoice, invoice.number)?;
+    if let Some(client) = &invoice.client {
+        writeln!(out, "{}: {client}  ", catalog.client)?;
+    }
+    writeln!(out, "{}: {}  ", catalog.period, fmt_month(invoice.month))?;
+    writeln!(out, "{}: {}\n", catalog.rate, invoice.rate_text())?;
+
+    writeln!(
+        out,
+        "| {} | {} | {} |",
+        item_header(grouping),
+        catalog.hours,
+        invoice.amount_header()
+    )?;
+    writeln!(out, "|---|---:|---:|")?;
+    for item in &invoice.items {
+        writeln!(
+            out,
+            "| {} | {:.2} | {:.2} |",
+            item.description.replace('|', "\\|"),
+            item.hours(),
+            amount(item.hours(), invoice.rate)
+        )?;
+    }
+    writeln!(
+        out,
+        "| **{}** | **{:.2}** | **{:.2}** |",
+        catalog.total,
+        invoice.total_hours(),
+        invoice.total_amount()
+    )
+}
+
+pub fn format_html(
+    invoice: &Invoice,
+    grouping: InvoiceGrouping,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let catalog = catalog();
+    let title = format!("{} {}", catalog.invoice, escape_html(&invoice.number));
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{title}</title>\n</head>\n<body>")?;
+    writeln!(out, "<h1>{title}</h1>")?;
+    if let Some(client) = &invoice.client {
+        writeln!(out, "<p>{}: {}</p>", catalog.client, escape_html(client))?;
+    }
+    writeln!(
+        out,
+        "<p>{}: {}</p>",
+        catalog.period,
+        fmt_month(invoice.month)
+    )?;
+    writeln!(
+        out,
+        "<p>{}: {}</p>",
+        catalog.rate,
+        escape_html(&invoice.rate_text())
+    )?;
+
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>{}</th><th>{}</th><th>{}</th></tr>",
+        item_header(grouping),
+        catalog.hours,
+        escape_html(&invoice.amount_header())
+    )?;
+    for item in &invoice.items {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{:.2}</td><td>{:.2}</td></tr>",
+            escape_html(&item.description),
+            item.hours(),
+            amount(item.hours(), invoice.rate)
+        )?;
+    }
+    writeln!(
+        out,
+        "<tr><th>{}</th><th>{:.2}</th><th>{:.2}</th></tr>",
+        catalog.total,
+        invoice.total_hours(),
+        invoice.total_amount()
+    )?;
+    writeln!(out, "</table>\n</body>\n</html>")
+}
+
+/// Writes the invoice of `clockin invoice` for a month of the project.
+pub fn write_invoice(path: &Path, args: InvoiceArgs) -> Result<()> {
+    let InvoiceArgs {
+        month,
+        rate,
+        number,
+        by,
+        format,
+        round,
+        round_per,
+        timezone,
+        output,
+    } = args;
+    let (round, round_per) = config::resolve_rounding(round, round_per)?;
+    let config = ProjectConfig::load_with_metadata(path)?;
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let month = month
+        .unwrap_or(Local::now().with_timezone(&timezone).date_naive())
+        .month_id();
+    let rate = rate
+        .or(config.rate)
+        .context("no hourly rate, pass --rate or set it with `clockin project set rate`")?;
+
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now();
+    let mut data = binnacle_2::process(
+        rounding::round_sessions(sessions, round, round_per),
+        Bound::Included(month.first_day()),
+        Bound::Included(month.last_day()),
+        &timezone,
+    );
+    if let Some(round) = rounding::day_rounding(round, round_per) {
+        data.round_days(&round);
+    }
+    let invoice = Invoice {
+        number: number.unwrap_or(format!("{:04}-{:02}", month.year(), month.month() + 1)),
+        client: config.client,
+        month,
+        rate,
+        currency: config.currency,
+        items: line_items(&data, by),
+    };
+
+    let mut out = ReportOutput::new(output);
+    match format {
+        InvoiceFormat::Markdown => format_markdown(&invoice, by, &mut out)?,
+        InvoiceFormat::Html => format_html(&invoice, by, &mut out)?,
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use chrono::FixedOffset;
+
+    use super::{Invoice, format_markdown, line_items};
+    use crate::{binnacle_2, cli::InvoiceGrouping, summary::MonthId, testing::session};
+
+    #[test]
+    fn markdown() {
+        let data = binnacle_2::process(
+            [
+                session("2024-05-02T10:00:00Z")
+                    .end("2024-05-02T11:30:00Z")
+                    .description("backend/api: auth")
+                    .build(),
+                session("2024-05-02T14:00:00Z")
+                    .end("2024-05-02T14:20:00Z")
+                    .description("frontend: login")
+                    .build(),
+                session("2024-05-03T10:00:00Z")
+                    .end("2024-05-03T11:00:00Z")
+                    .description("backend: deploy")
+                    .build(),
+            ]
+            .into_iter(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+            &FixedOffset::east_opt(0).unwrap(),
+        );
+
+        let by_day = line_items(&data, InvoiceGrouping::Day);
+        assert_eq!(
+            by_day
+                .iter()
+                .map(|i| (i.description.as_str(), i.duration.as_secs() / 60))
+                .collect::<Vec<_>>(),
+            [("02/05/2024", 110), ("03/05/2024", 60)]
+        );
+
+        let invoice = Invoice {
+            number: "2024-05".to_owned(),
+            client: Some("ACME".to_owned()),
+            month: MonthId::new(2024, 4),
+            rate: 30.0,
+            currency: Some("USD".to_owned()),
+            items: line_items(&data, InvoiceGrouping::SubProject),
+        };
+        let mut out = vec![];
+        format_markdown(&invoice, InvoiceGrouping::SubProject, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# Factura 2024-05\n\n\
+             Cliente: ACME  \n\
+             Período: Mayo 2024  \n\
+             Tarifa: 30.00 USD por hora\n\n\
+             | Subproyecto | Horas | Importe (USD) |\n\
+             |---|---:|---:|\n\
+             | backend | 2.50 | 75.00 |\n\
+             | frontend | 0.33 | 10.00 |\n\
+             | **Total** | **2.83** | **85.00** |\n"
+        );
+    }
+}
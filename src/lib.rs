@@ -0,0 +1,100 @@
+//! Time tracking on plain text files.
+//!
+//! A project file holds one session after another, each one started by a `%-` line, finished by
+//! a `%+` line and described by the lines in between. [`parser`] reads the sessions,
+//! [`Summary`] adds them up by day, [`BinnacleData`] breaks them down by sub-project and
+//! [`SessionWriter`] appends to the file the way the `clockin` commands do.
+//!
+//! ```
+//! use chrono::Utc;
+//! use clockin::{Summary, parser::{self, SessionIteratorClosingExt}};
+//!
+//! let file = "%-2000-01-01T10:00:00+00:00\n\
+//!             backend: fix auth\n\
+//!             %+2000-01-01T11:30:00+00:00\n";
+//! let sessions = parser::parse_reader(file.as_bytes())?.collect::<Result<Vec<_>, _>>()?;
+//! let summary = Summary::summarize(sessions.into_iter().into_finished_now(), &Utc);
+//! assert_eq!(summary.duration(..).as_secs(), 90 * 60);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+/// Sessions grouped by sub-project and task, from the `sub-project: task` descriptions.
+pub mod binnacle_2;
+/// Sub-projects, tags and tickets in session descriptions.
+pub mod binnacle_body_parser;
+/// Time budgets per sub-project.
+pub mod budget;
+/// SVG and PNG charts.
+#[cfg(feature = "chart")]
+pub mod chart;
+/// Problems found in project files.
+pub mod check;
+/// Command line arguments, shared with the build script that generates the completions.
+pub mod cli;
+/// Month against month comparisons.
+pub mod compare;
+/// Defaults of the user for every project.
+pub mod config;
+/// Encryption of project files with age or gpg.
+pub mod crypt;
+/// iCalendar and timeclock exports.
+pub mod export;
+/// Project files in the data directory and the `.clockin` links to them.
+pub mod file;
+/// Repairs for sessions that overlap or are out of order.
+pub mod fix;
+/// Hours banked or owed against a schedule.
+pub mod flexitime;
+/// Durations, months and weekdays as written in the reports.
+pub mod format_util;
+/// Calendar heatmap of the worked time.
+pub mod heatmap;
+/// Texts of the reports in English and Spanish.
+pub mod i18n;
+/// Sessions from Timewarrior, Watson and Org mode.
+pub mod import;
+/// Invoices for the time worked in a month.
+pub mod invoice;
+/// Listings of the last sessions.
+pub mod log;
+/// Prometheus metrics.
+pub mod metrics;
+/// Time worked against the time expected by a schedule.
+pub mod overtime;
+/// Reading of project files.
+pub mod parser;
+/// Settings of a project, kept in `<project>.toml` next to its file.
+pub mod project_config;
+/// HTML reports.
+pub mod report;
+/// Rounding of worked time.
+pub mod rounding;
+/// Expected time per weekday.
+pub mod schedule;
+/// Version and JSON Schema of the JSON outputs.
+pub mod schema;
+/// SQLite exports and imports.
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+/// Distribution of session lengths.
+pub mod stats;
+/// State of the last session, for the integrations with other programs.
+pub mod status;
+/// Streaks of worked days.
+pub mod streaks;
+/// Worked time by day, week and month.
+pub mod summary;
+/// Session builders for the tests.
+#[cfg(test)]
+mod testing;
+/// Worked time by time of day.
+pub mod time_analysis;
+/// Worked time by tag or sub-project.
+pub mod totals;
+/// Writing of project files and reports.
+pub mod writer;
+
+pub use binnacle_2::BinnacleData;
+pub use parser::Session;
+pub use summary::Summary;
+pub use writer::SessionWriter;
@@ -0,0 +1,221 @@
+use std::{
+    fmt,
+    io::Write,
+    ops::{Bound, RangeBounds},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    cli::LogFormat,
+    config, file,
+    format_util::fmt_duration,
+    parser::{self, MaybeFinishedSessionTZ, ParseError, Session},
+    schema::Versioned,
+    writer::ReportOutput,
+};
+
+#[derive(Debug, Serialize)]
+struct LogEntry {
+    id: String,
+    start: DateTime<FixedOffset>,
+    end: Option<DateTime<FixedOffset>>,
+    duration_seconds: i64,
+    description: String,
+}
+
+#[derive(Debug, Serialize)]
+struct LogEntries {
+    sessions: Vec<LogEntry>,
+}
+
+impl LogEntry {
+    fn new(session: MaybeFinishedSessionTZ<FixedOffset>, project: &str) -> Self {
+        let id = session.short_id(project);
+        let end = session.end;
+        let session: Session = session.into_finished_now();
+        LogEntry {
+            id,
+            start: session.start,
+            end,
+            duration_seconds: session.duration().num_seconds(),
+            description: session.description,
+        }
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {} - {}  {}  {}",
+            self.id,
+            self.start.format("%Y-%m-%d %H:%M"),
+            self.end.map_or("running".to_owned(), |end| {
+                if end.date_naive() == self.start.date_naive() {
+                    end.format("%H:%M").to_string()
+                } else {
+                    end.format("%Y-%m-%d %H:%M").to_string()
+                }
+            }),
+            fmt_duration(&Duration::from_secs(self.duration_seconds.max(0) as u64)),
+            first_line(&self.description)
+        )
+    }
+}
+
+fn first_line(description: &str) -> &str {
+    description.lines().next().unwrap_or_default()
+}
+
+/// The session as a line of the text log.
+pub fn fmt_session(session: MaybeFinishedSessionTZ<FixedOffset>, project: &str) -> String {
+    LogEntry::new(session, project).to_string()
+}
+
+pub fn print_log(
+    sessions: impl Iterator<Item = MaybeFinishedSessionTZ<FixedOffset>>,
+    project: &str,
+    count: usize,
+    format: LogFormat,
+) -> Result<()> {
+    let entries = sessions
+        .tail(count)
+        .rev()
+        .map(|session| LogEntry::new(session, project))
+        .collect_vec();
+
+    match format {
+        LogFormat::Text => {
+            for entry in entries {
+                println!("{entry}");
+            }
+        }
+        LogFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&Versioned::new(LogEntries { sessions: entries }))?
+        ),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionRecord<'a> {
+    start: DateTime<FixedOffset>,
+    /// `null` while the session is running
+    end: Option<DateTime<FixedOffset>>,
+    duration_seconds: i64,
+    description: String,
+    project: &'a str,
+}
+
+/// A record for every session starting within the dates, as they are parsed.
+pub fn session_records<'a>(
+    sessions: impl Iterator<Item = Result<MaybeFinishedSessionTZ<FixedOffset>, ParseError>>,
+    project: &'a str,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: &FixedOffset,
+) -> impl Iterator<Item = Result<SessionRecord<'a>, ParseError>> {
+    sessions
+        .filter(move |session| {
+            session.as_ref().map_or(true, |s| {
+                range.contains(&s.start.with_timezone(timezone).date_naive())
+            })
+        })
+        .map_ok(move |session| {
+            let end = session.end;
+            let session = session.into_finished_now();
+            SessionRecord {
+                start: session.start,
+                end,
+                duration_seconds: session.duration().num_seconds(),
+                description: session.description,
+                project,
+            }
+        })
+}
+
+/// Writes one JSON object per line for every session starting within the dates, as they are
+/// parsed.
+pub fn write_ndjson(
+    sessions: impl Iterator<Item = Result<MaybeFinishedSessionTZ<FixedOffset>, ParseError>>,
+    project: &str,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: &FixedOffset,
+    out: &mut impl Write,
+) -> Result<()> {
+    for record in session_records(sessions, project, range, timezone) {
+        serde_json::to_writer(&mut *out, &Versioned::new(record?))?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Writes the sessions of `clockin sessions` as NDJSON, keeping the ones matching `grep`.
+pub fn write_sessions(
+    path: &Path,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    grep: Option<String>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let grep = parser::parse_grep(grep)?;
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let project = file::project_name(path)?;
+    let mut out = ReportOutput::new(output);
+    write_ndjson(
+        parser::parse_file(path)?.filter(|s| {
+            grep.as_ref()
+                .zip(s.as_ref().ok())
+                .is_none_or(|(grep, s)| grep.is_match(&s.description))
+        }),
+        &project,
+        range,
+        &timezone,
+        &mut out,
+    )?;
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use chrono::{FixedOffset, NaiveDate};
+
+    use super::write_ndjson;
+    use crate::parser::parse_reader;
+
+    #[test]
+    fn ndjson() {
+        let file = "%-2000-01-01T10:00:00+00:00\n\
+                    fix login\n\
+                    %+2000-01-01T11:00:00+00:00\n\
+                    \n\
+                    %-2000-01-02T10:00:00+00:00\n\
+                    %+2000-01-02T10:30:00+00:00\n\
+                    \n";
+        let mut out = vec![];
+        write_ndjson(
+            parse_reader(file.as_bytes()).unwrap(),
+            "acme",
+            (
+                Bound::Unbounded,
+                Bound::Excluded(NaiveDate::from_ymd_opt(2000, 1, 2).unwrap()),
+            ),
+            &FixedOffset::east_opt(0).unwrap(),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"schema_version\":1,\"start\":\"2000-01-01T10:00:00Z\",\"end\":\"2000-01-01T11:00:00Z\",\
+             \"duration_seconds\":3600,\"description\":\"fix login\",\"project\":\"acme\"}\n"
+        );
+    }
+}
@@ -1,227 +1,477 @@
 use std::{
-    ops::RangeBounds,
     os::unix::process::CommandExt,
-    path::Path,
     process::{self, exit},
     sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
 };
 
-use anyhow::{Context, Result};
-use chrono::{Datelike, Local, NaiveTime, TimeDelta};
+use anyhow::{Context, Result, bail};
+use chrono::{TimeDelta, Weekday};
 use clap::Parser;
-use cli::Command;
-use file::get_data_dir;
-use summary::{NaiveDateExt, Summary};
-use writer::write_date;
-
-use crate::{
-    format_util::{fmt_duration, fmt_duration_uncertain, fmt_hours_mins, fmt_month, fmt_weekday}, parser::{NaiveSessionIteratorExt, SessionIteratorClosingExt, SessionIteratorExt}
+#[cfg(feature = "chart")]
+use clockin::chart;
+use clockin::{
+    budget, check, cli,
+    cli::{Command, Encryption, SessionsFormat},
+    compare,
+    config::{self, Config},
+    export, file,
+    file::get_data_dir,
+    fix, flexitime, heatmap, i18n, import, invoice, log, overtime, parser, project_config, report,
+    schema, stats, status, streaks, summary, time_analysis, totals, writer,
 };
 
-mod binnacle_2;
-mod binnacle_body_parser;
-mod cli;
-mod file;
-mod format_util;
-mod parser;
+mod control;
+mod dbus;
+mod editor;
+mod git;
+mod idle;
+mod notification;
+mod prompt;
+mod serve;
+mod socket;
 mod subscribe;
-mod summary;
-mod writer;
+mod suspend;
+// the tests of the binary share the builders of the library, using only some of them
+#[cfg(test)]
+#[path = "testing.rs"]
+#[allow(dead_code)]
+mod testing;
+mod webhook;
+mod widget;
+
+const NO_RUNNING_SESSION_EXIT_CODE: i32 = 3;
 
 fn get_shell() -> String {
     std::env::var("SHELL").unwrap_or("sh".to_owned())
 }
 
-fn edit_file(path: impl AsRef<Path>) -> Result<()> {
-    let editor = std::env::var("EDITOR").unwrap_or("nano".to_owned());
-    let mut process = process::Command::new(editor)
-        .arg(path.as_ref())
-        .spawn()
-        .context("error while trying to run editor")?;
-    process.wait().context("error while editing file")?;
-    Ok(())
-}
-
 fn run(command: Command, cancel: Receiver<()>) -> Result<()> {
     match command {
-        Command::Link { name } => {
+        Command::Link {
+            name,
+            encrypt,
+            recipient,
+        } => {
+            if let Some(encryption) = encrypt {
+                if encryption == Encryption::Age && recipient.is_none() {
+                    bail!("age needs a --recipient to encrypt the project for");
+                }
+                let project = file::create_project_file(&name)?;
+                writer::encrypt_project(&project, encryption, recipient)?;
+                git::auto_commit(&project, "encrypt");
+            }
             file::create_clockin_file(&name)?;
         }
+        Command::Unlink => file::remove_clockin_link()?,
+        Command::Rename { old, new } => file::rename_and_relink(&old, &new)?,
+        Command::Delete { project, yes } => {
+            let path = match project {
+                Some(name) => file::require_project_file(&name)?,
+                None => file::require_clockin_project_file()?,
+            };
+            let name = file::project_name(&path)?;
+            if parser::is_session_running(&path)? {
+                bail!("a session of {name} is running, clock out first");
+            }
+            if !yes && !prompt::confirm(&format!("delete project {name}?"))? {
+                return Ok(());
+            }
+            let backup = file::delete_project(&path)?;
+            println!(
+                "deleted {name}, its times and settings were kept in {}; `clockin link {name}` and `clockin restore` bring them back",
+                backup.display()
+            );
+        }
+        Command::Export { format } => export::export(&file::require_clockin_file()?, format)?,
+        Command::Import { source } => {
+            for (file, sessions) in import::read_source(source)? {
+                let total = sessions.len();
+                let imported = import::import_sessions(&file, sessions)?;
+                git::auto_commit(&file, &format!("import {imported} sessions"));
+                println!(
+                    "{}: imported {imported} sessions, skipped {} already present",
+                    file.display(),
+                    total - imported
+                );
+            }
+        }
+        Command::Merge { other, overlaps } => {
+            let file = file::require_clockin_file()?;
+            let project = file::project_name(&file)?;
+            let (before, after) = fix::merge_file(&file, &other, |overlapped, session| {
+                overlaps.map_or_else(|| prompt::ask_overlap(&project, overlapped, session), Ok)
+            })?;
+            git::auto_commit(&file, &format!("merge {}", other.display()));
+            println!("{after} sessions, {before} before merging");
+        }
+        Command::Budget { command, timezone } => {
+            budget::run(&file::require_clockin_project_file()?, command, timezone)?
+        }
+        Command::Flexitime { command, timezone } => {
+            flexitime::run(&file::require_clockin_project_file()?, command, timezone)?
+        }
+        Command::Project { command } => {
+            project_config::run(&file::require_clockin_project_file()?, command)?
+        }
         Command::Edit => {
             let file = file::require_clockin_file()?;
-            edit_file(file)?;
+            // other commands fail to write while the file is open instead of being overwritten
+            let _lock = writer::lock(&file)?;
+            file::backup_file(&file)?;
+            // the file the current sessions are written to comes first
+            let mut files = file::session_files(&file)?;
+            files.reverse();
+            editor::edit_session_files(&file, &files)?;
+            git::auto_commit(&file, "edit");
+        }
+        Command::Check => {
+            if check::print_problems(&file::require_clockin_file()?)? {
+                exit(1);
+            }
         }
-        Command::In => {
+        Command::Fix { close_after } => {
+            let file = file::require_clockin_file()?;
+            fix::fix_file(&file, TimeDelta::minutes(close_after))?;
+            git::auto_commit(&file, "fix");
+        }
+        Command::Restore { backup, yes } => {
+            let file = file::require_clockin_file()?;
+            let Some(name) = backup else {
+                return file::print_backups(&file);
+            };
+            let backup = file::find_backup(&file, &name)?;
+            if !yes && !prompt::confirm(&format!("replace the project file with backup {name}?"))? {
+                return Ok(());
+            }
+            let _lock = writer::lock(&file)?;
+            // the current state gets its own backup so the restore can be undone
+            file::backup_file(&file)?;
+            file::restore_backup(&file, &backup).context("error while restoring backup")?;
+            git::auto_commit(&file, &format!("restore backup {name}"));
+        }
+        Command::In {
+            at,
+            message,
+            detach,
+        } => {
+            let file = file::require_clockin_file()?;
+            control::clock_in_at(&file, at, message.as_deref())?;
             println!(
                 "{}",
                 concat!("==============\n", "= CLOCKED IN =\n", "==============")
             );
+            if detach || message.is_some() {
+                println!("the session keeps running until `clockin out`");
+                return Ok(());
+            }
+            file::backup_file(&file)?;
+            // the file the session was written to
+            let current = file::session_files(&file)?.pop().unwrap();
+            // the session is clocked out even if the edit could not be saved
+            let edited = editor::edit_session_files(&file, &[current]);
+            match control::clock_out(&file) {
+                // the session may have been closed from another terminal meanwhile
+                Err(control::ControlError::NotRunning(_)) => {}
+                result => result?,
+            }
+            edited?;
+        }
+        Command::Out => {
+            let file = file::require_clockin_file()?;
+            control::clock_out(&file)?;
 
+            println!(
+                "{}",
+                concat!("===============\n", "= CLOCKED OUT =\n", "===============")
+            );
+        }
+        Command::Cancel { yes } => {
             let file = file::require_clockin_file()?;
-            write_date(&file, false, '-')?;
-            edit_file(&file)?;
-            write_date(&file, true, '+')?;
+            let Some(session) = parser::last_session(&file)?.filter(|s| !s.is_finished()) else {
+                bail!("there is no running session to cancel");
+            };
+            if !yes
+                && !prompt::confirm(&format!(
+                    "discard the session started at {}?",
+                    session.start.format("%Y-%m-%d %H:%M:%S")
+                ))?
+            {
+                return Ok(());
+            }
+            writer::remove_last_session(&file)?;
+            git::auto_commit(&file, "cancel session");
         }
-        Command::WeekSummary => {
-            let path = file::require_clockin_file()?;
-            let sessions = parser::parse_file(path).unwrap().as_finished_now();
-            let summary = Summary::summarize(sessions, &Local);
-
-            let mut last_week = None;
-            for (date, day) in &summary.days {
-                let week = date.real_week();
-
-                if last_week.is_none_or(|last_week| last_week != week) {
-                    last_week = Some(week);
-                    println!(
-                        "Week {}: {}",
-                        week.first_day(),
-                        fmt_duration(&summary.week_duration(week))
-                    );
-                }
-
-                println!("- {}: {}", date, fmt_duration(&day.duration));
+        Command::Amend {
+            id,
+            start,
+            end,
+            message,
+        } => {
+            let file = file::require_clockin_file()?;
+            writer::amend_session(&file, id, start, end, message)?;
+            git::auto_commit(&file, "amend session");
+        }
+        Command::Split { id, at } => {
+            let file = file::require_clockin_file()?;
+            writer::split_session(&file, id, at)?;
+            git::auto_commit(&file, "split session");
+        }
+        Command::Log { count, format } => {
+            let file = file::require_clockin_file()?;
+            log::print_log(
+                parser::parse_sessions(&file)?.into_iter(),
+                &file::project_name(&file)?,
+                count,
+                format,
+            )?;
+        }
+        Command::Pause => control::pause(&file::require_clockin_file()?)?,
+        Command::Resume => control::resume(&file::require_clockin_file()?)?,
+        Command::Add {
+            start,
+            end,
+            message,
+            force,
+        } => {
+            let file = file::require_clockin_file()?;
+            writer::add_session(&file, start, end, message, force)?;
+            git::auto_commit(&file, "add session");
+        }
+        Command::IdleWatch { threshold } => {
+            let path = file::require_clockin_project_file()?;
+            idle::idle_watch(&path, Duration::from_secs(threshold * 60), cancel)?;
+        }
+        Command::Schema => print!("{}", schema::SCHEMA),
+        Command::Prompt => {
+            // outside of a project the prompt just shows nothing
+            let Ok(path) = file::require_clockin_file() else {
+                return Ok(());
+            };
+            if let Some(segment) = status::prompt(parser::last_session_from_tail(&path)?) {
+                println!("{segment}");
+            }
+        }
+        Command::Widget { style } => {
+            let Ok(path) = file::require_clockin_file() else {
+                return Ok(());
+            };
+            let session = parser::last_session_from_tail(&path)?;
+            if let Some(widget) = widget::format(style, &file::project_name(&path)?, session) {
+                println!("{widget}");
             }
         }
-        Command::Summary {
+        Command::Status {
+            oneline,
+            template,
+            timezone,
+        } => {
+            let file = file::require_clockin_file()?;
+            if oneline {
+                let template: status::Template = template.parse()?;
+                println!("{}", template.render(&status::status(&file)?));
+            } else if !status::print_status(&file, timezone)? {
+                exit(NO_RUNNING_SESSION_EXIT_CODE);
+            }
+        }
+        Command::WeekSummary {
+            merge_gap,
+            timezone,
+            totals_only,
+            output,
+        } => summary::print_week_summary(
+            &file::require_clockin_file()?,
+            merge_gap,
+            timezone,
+            totals_only,
+            output,
+        )?,
+        Command::Summary(args) => summary::print_summary(args)?,
+        Command::Top {
+            count,
+            from,
+            to,
+            timezone,
+            output,
+        } => totals::print_top(
+            &file::require_clockin_file()?,
+            count,
+            (from, to),
+            timezone,
+            output,
+        )?,
+        Command::Tags {
+            from,
+            to,
+            timezone,
+            output,
+        } => totals::print_tags(&file::require_clockin_file()?, (from, to), timezone, output)?,
+        Command::Invoice(args) => invoice::write_invoice(&file::require_clockin_file()?, args)?,
+        Command::Overtime {
+            from,
+            to,
+            timezone,
+            output,
+        } => {
+            overtime::print_overtime(&file::require_clockin_file()?, (from, to), timezone, output)?
+        }
+        Command::Gaps {
+            from,
+            to,
+            timezone,
+            output,
+        } => overtime::print_gaps(&file::require_clockin_file()?, (from, to), timezone, output)?,
+        Command::Stats {
+            kind:
+                cli::StatsCommand::Lengths {
+                    from,
+                    to,
+                    timezone,
+                    bar_width,
+                    output,
+                },
+        } => stats::print_lengths(
+            &file::require_clockin_file()?,
+            (from, to),
+            timezone,
+            bar_width,
+            output,
+        )?,
+        #[cfg(feature = "chart")]
+        Command::Chart {
+            kind,
             from,
             to,
             timezone,
-            version,
+            interval,
+            output,
+        } => chart::write_chart(
+            &file::require_clockin_file()?,
+            kind,
+            (from, to),
+            timezone,
+            interval,
+            &output,
+        )?,
+        Command::Report {
+            kind:
+                cli::ReportCommand::Html {
+                    from,
+                    to,
+                    timezone,
+                    output,
+                },
+        } => report::write_html(&file::require_clockin_file()?, (from, to), timezone, output)?,
+        Command::Compare {
+            months,
+            last,
+            timezone,
+            output,
+        } => compare::print_comparison(
+            &file::require_clockin_file()?,
+            months,
+            last,
+            timezone,
+            output,
+        )?,
+        Command::Heatmap {
+            year,
+            timezone,
+            output,
+        } => heatmap::print_heatmap(&file::require_clockin_file()?, year, timezone, output)?,
+        Command::Streaks {
+            min_minutes,
+            workdays,
+            timezone,
+        } => streaks::print_streaks(
+            &file::require_clockin_file()?,
+            min_minutes,
+            workdays,
+            timezone,
+        )?,
+        Command::Sessions {
+            from,
+            to,
+            timezone,
+            format: SessionsFormat::Ndjson,
+            grep,
+            output,
+        } => log::write_sessions(
+            &file::require_clockin_file()?,
+            (from, to),
+            timezone,
+            grep,
+            output,
+        )?,
+        Command::Tickets {
+            from,
+            to,
+            timezone,
+            output,
+        } => totals::print_tickets(&file::require_clockin_file()?, (from, to), timezone, output)?,
+        Command::WorkTimeAnalysis(args) => {
+            time_analysis::print_analysis(&file::require_clockin_file()?, args)?
+        }
+        Command::Serve {
+            address,
+            port,
+            token,
+            metrics,
+            timezone,
         } => {
             let path = file::require_clockin_file()?;
-            let sessions = parser::parse_file(path).unwrap().as_finished_now();
-            let current_date = Local::now().with_timezone(&timezone).date_naive();
-
-            match version {
-                1 => {
-                    let summary = Summary::summarize(sessions, &timezone);
-
-                    let mut last_month = None;
-                    for (date, day) in summary.days.range((from, to)) {
-                        let month = date.month_id();
-
-                        let month_changed = last_month.is_none_or(|last_month| last_month != month);
-                        if month_changed {
-                            last_month = Some(month);
-                            println!(
-                                "## {} ({})\n",
-                                fmt_month(month),
-                                fmt_duration_uncertain(
-                                    &summary.duration(month.first_day()..=month.last_day()),
-                                    current_date > month.last_day()
-                                )
-                            );
-                        }
-
-                        println!(
-                            "- {} {:02}/{:02} ({})\n",
-                            fmt_weekday(date.weekday()),
-                            date.day(),
-                            date.month(),
-                            fmt_duration_uncertain(&day.duration, &current_date > date)
-                        );
-                        for description in &day.descriptions {
-                            println!("\t- {}\n", description);
-                        }
-                    }
-                }
-                2 => {
-                    let data = binnacle_2::process(sessions, from, to, &timezone);
-                    binnacle_2::format(data, current_date);
-                }
-                _ => {
-                    println!("unknown version {version}");
-                    exit(1);
-                }
-            }
+            let timezone = config::resolve_timezone(&path, timezone)?;
+            let options = serve::Options { token, metrics };
+            serve::serve(path, timezone, &address, port, options, cancel)?;
         }
-        Command::WorkTimeAnalysis { from, to, timezone } => {
+        Command::Daemon {
+            socket,
+            notify_after,
+        } => {
             let path = file::require_clockin_file()?;
-
-            const ANALYSIS_INTERVAL: TimeDelta = TimeDelta::minutes(30);
-            const SLOTS_PER_DAY: usize =
-                (TimeDelta::days(1).num_minutes() / ANALYSIS_INTERVAL.num_minutes()) as usize;
-            // one counter every interval
-            let mut results = [TimeDelta::zero(); SLOTS_PER_DAY];
-
-            let sessions = parser::parse_file(path)
-                .unwrap()
-                .as_finished_now()
-                .filter(|s| (from, to).contains(&s.start.with_timezone(&timezone).date_naive()))
-                .map(|s| s.naive_local())
-                .flat_map(|s| s.split_at_days())
-                .map(|s| s.start.time()..s.end.time());
-
-            for session in sessions {
-                for (i, result) in results.iter_mut().enumerate() {
-                    let interval_start = NaiveTime::MIN + ANALYSIS_INTERVAL * (i as i32);
-                    let interval_end = interval_start + ANALYSIS_INTERVAL;
-                    // this fix is needed because "session end" is exclusive but NaiveTime wraps
-                    // around at "24:00:00"
-                    let fix_end = |t| {
-                        if t == NaiveTime::MIN {
-                            NaiveTime::MIN - TimeDelta::nanoseconds(1)
-                        } else {
-                            t
-                        }
-                    };
-                    let overlap = (fix_end(session.end).min(fix_end(interval_end))
-                        - session.start.max(interval_start))
-                    .max(TimeDelta::zero());
-                    *result += overlap;
-                }
-            }
-
-            let total: TimeDelta = results.iter().sum();
-
-            for (i, result) in results.iter().enumerate() {
-                let interval_start = NaiveTime::MIN + ANALYSIS_INTERVAL * (i as i32);
-                let interval_end = interval_start + ANALYSIS_INTERVAL;
-                let _total_hours = result.num_seconds() as f64 / 3600f64;
-                let percentage = result.num_seconds() as f64 / total.num_seconds() as f64;
-                println!(
-                    "{}-{}: {:.02}% {}",
-                    fmt_hours_mins(interval_start),
-                    fmt_hours_mins(interval_end),
-                    100.0 * percentage,
-                    "#".repeat((800.0 * percentage).round() as usize)
-                );
+            if let Some(after) = notify_after {
+                notification::spawn_long_session_watch(path.clone(), after);
             }
+            let socket = socket.unwrap_or_else(socket::default_socket_path);
+            socket::run(path, &socket, cancel)?;
         }
-        Command::Subscribe => {
-            let path = file::require_clockin_project_file()?;
-            subscribe::subscribe(&path, cancel)?;
+        Command::Dbus => dbus::serve(file::require_clockin_project_file()?, cancel)?,
+        Command::Remind { every, message } => {
+            notification::remind(&file::require_clockin_file()?, every, &message, cancel)?
         }
-        Command::GetWorkedTime { specification } => {
-            let path = file::require_clockin_file()?;
-            let sessions = parser::parse_file(path).unwrap().as_finished_now();
-
-            let worked_time: TimeDelta = match specification {
-                cli::GetWorkedTimeCommand::Today { timezone } => {
-                    let today = Local::now().with_timezone(&timezone).date_naive();
-                    sessions
-                        .with_timezone(&timezone)
-                        .naive_local()
-                        .cut_at_days()
-                        .filter(|s| s.start.date() == today)
-                        .map(|s| s.duration())
-                        .sum()
-                }
-                cli::GetWorkedTimeCommand::ByDateRange { from, to, timezone } => sessions
-                    .with_timezone(&timezone)
-                    .naive_local()
-                    .cut_at_days()
-                    .filter(|s| (from, to).contains(&s.start.date()))
-                    .map(|s| s.duration())
-                    .sum(),
-                cli::GetWorkedTimeCommand::LastSession => {
-                    sessions.last().into_iter().map(|s| s.duration()).sum()
-                }
-            };
-
-            println!("{}", worked_time.as_seconds_f64() as u64);
+        Command::Subscribe {
+            all,
+            on_suspend,
+            resume_on_wake,
+            notify_after,
+            format,
+            template,
+            exec,
+        } => {
+            let output = subscribe::Output::new(format, &template)?;
+            let hook = exec.as_deref().map(subscribe::Hook::new).transpose()?;
+            if all {
+                subscribe::subscribe(subscribe::Source::All, output, hook, cancel)?;
+                return Ok(());
+            }
+            let path = file::require_clockin_project_file()?;
+            if let Some(after) = notify_after {
+                notification::spawn_long_session_watch(path.clone(), after);
+            }
+            if let Some(action) = on_suspend {
+                let path = path.clone();
+                thread::spawn(move || {
+                    if let Err(err) = suspend::watch_suspend(&path, action, resume_on_wake) {
+                        eprintln!("suspend watch error: {err:#}");
+                    }
+                });
+            }
+            subscribe::subscribe(subscribe::Source::Project(path), output, hook, cancel)?;
         }
+        Command::GetWorkedTime(args) => totals::print_worked_time(args)?,
+        Command::Projects { include_archived } => project_config::print_projects(include_archived)?,
+        Command::Which => file::print_resolution()?,
         Command::Cd => {
             Err(process::Command::new(get_shell())
                 .current_dir(get_data_dir())
@@ -234,14 +484,31 @@ fn run(command: Command, cancel: Receiver<()>) -> Result<()> {
                 .current_dir(get_data_dir())
                 .exec())?;
         }
+        Command::Sync => git::sync()?,
+        Command::Git { command } => match command {
+            cli::GitCommand::Init => git::init()?,
+        },
     };
 
     Ok(())
 }
 
 fn main() -> Result<()> {
+    // relative dates are resolved while parsing the arguments
+    cli::set_week_start(Config::get()?.week_start.unwrap_or(Weekday::Mon));
     let args = cli::Args::parse();
-    let command = args.command.unwrap_or(Command::In);
+    i18n::set_lang(args.lang.or(Config::get()?.lang).unwrap_or_default());
+    if let Some(path) = args.clockin_file {
+        if !path.is_file() {
+            bail!("{} is not a file", path.display());
+        }
+        file::set_clockin_file(path);
+    }
+    let command = args.command.unwrap_or(Command::In {
+        at: None,
+        message: None,
+        detach: false,
+    });
 
     let (canceller, cancel) = mpsc::channel();
     ctrlc::set_handler(move || {
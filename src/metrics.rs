@@ -0,0 +1,130 @@
+use std::io::{self, Write};
+
+use chrono::NaiveDate;
+
+use crate::{
+    status::{State, Status},
+    summary::{NaiveDateExt, Summary},
+};
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Writes the metrics of the project in the Prometheus text format. The summary must include the
+/// running session up to now.
+pub fn format(
+    status: &Status,
+    summary: &Summary,
+    today: NaiveDate,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let project = escape_label(&status.project);
+    let week = today.real_week();
+    let metrics = [
+        (
+            "clockin_worked_seconds",
+            "gauge",
+            "Seconds worked on the project, it goes down when sessions are removed or amended.",
+            summary.duration(..).as_secs(),
+        ),
+        (
+            "clockin_worked_today_seconds",
+            "gauge",
+            "Seconds worked today.",
+            summary.duration(today..=today).as_secs(),
+        ),
+        (
+            "clockin_worked_week_seconds",
+            "gauge",
+            "Seconds worked this week, since monday.",
+            summary.week_duration(week).as_secs(),
+        ),
+        (
+            "clockin_session_running",
+            "gauge",
+            "Whether a session is running and not paused.",
+            u64::from(status.state == State::Running),
+        ),
+        (
+            "clockin_session_paused",
+            "gauge",
+            "Whether the session is paused.",
+            u64::from(status.state == State::Paused),
+        ),
+        (
+            "clockin_session_worked_seconds",
+            "gauge",
+            "Seconds worked in the running session, without its pauses, zero without one.",
+            status.elapsed_seconds.unwrap_or_default().max(0) as u64,
+        ),
+    ];
+    for (name, kind, help, value) in metrics {
+        writeln!(out, "# HELP {name} {help}")?;
+        writeln!(out, "# TYPE {name} {kind}")?;
+        writeln!(out, "{name}{{project=\"{project}\"}} {value}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+
+    use super::format;
+    use crate::{
+        status::{State, Status},
+        summary::Summary,
+        testing::session,
+    };
+
+    #[test]
+    fn metrics() {
+        let summary = Summary::summarize(
+            [
+                session("1999-12-31T10:00:00Z")
+                    .lasting(TimeDelta::minutes(60))
+                    .build(),
+                session("2000-01-03T10:00:00Z")
+                    .lasting(TimeDelta::minutes(60))
+                    .build(),
+                session("2000-01-04T10:00:00Z")
+                    .lasting(TimeDelta::minutes(30))
+                    .build(),
+            ]
+            .into_iter(),
+            &Utc,
+        );
+        let status = Status {
+            project: "say \"hi\"".to_owned(),
+            state: State::Running,
+            start: Some(DateTime::parse_from_rfc3339("2000-01-04T10:00:00Z").unwrap()),
+            elapsed_seconds: Some(1800),
+            description: None,
+        };
+
+        let mut out = vec![];
+        let today = NaiveDate::from_ymd_opt(2000, 1, 4).unwrap();
+        format(&status, &summary, today, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let values: Vec<_> = out.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(
+            values,
+            [
+                r#"clockin_worked_seconds{project="say \"hi\""} 9000"#,
+                r#"clockin_worked_today_seconds{project="say \"hi\""} 1800"#,
+                r#"clockin_worked_week_seconds{project="say \"hi\""} 5400"#,
+                r#"clockin_session_running{project="say \"hi\""} 1"#,
+                r#"clockin_session_paused{project="say \"hi\""} 0"#,
+                r#"clockin_session_worked_seconds{project="say \"hi\""} 1800"#,
+            ]
+        );
+        assert!(out.starts_with(
+            "# HELP clockin_worked_seconds Seconds worked on the project, it goes down when sessions are removed or amended.\n\
+             # TYPE clockin_worked_seconds gauge\n"
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use std::{path::PathBuf, sync::mpsc::Receiver, thread, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Local, TimeDelta};
+use notify_rust::Notification;
+
+use clockin::parser::{self, MaybeFinishedSessionTZ};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+pub fn send(summary: &str, body: &str) -> Result<()> {
+    Notification::new()
+        .appname("clockin")
+        .summary(summary)
+        .body(body)
+        .show()?;
+    Ok(())
+}
+
+/// When the session was started or last resumed, if it is running.
+fn stretch_start(session: &MaybeFinishedSessionTZ<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    if session.is_finished() || session.is_paused() {
+        return None;
+    }
+    Some(
+        session
+            .pauses
+            .iter()
+            .map(|pause| pause.end)
+            .max()
+            .map_or(session.start, |resumed| resumed.max(session.start)),
+    )
+}
+
+fn fmt_elapsed(elapsed: TimeDelta) -> String {
+    match (elapsed.num_hours(), elapsed.num_minutes() % 60) {
+        (0, minutes) => format!("{minutes}min"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h {minutes}min"),
+    }
+}
+
+/// Notifies once for every stretch worked without pauses that goes over `after`.
+fn watch_long_sessions(path: &PathBuf, after: TimeDelta) -> ! {
+    let mut notified = None;
+    loop {
+        match parser::last_session(path) {
+            Ok(session) => {
+                let now = Local::now().fixed_offset();
+                if let Some(start) = session.as_ref().and_then(stretch_start)
+                    && now - start >= after
+                    && notified != Some(start)
+                {
+                    notified = Some(start);
+                    let body = format!("You've been clocked in for {}", fmt_elapsed(now - start));
+                    if let Err(err) = send("Still working?", &body) {
+                        eprintln!("notification error: {err:#}");
+                    }
+                }
+            }
+            Err(err) => eprintln!("error: {err:#}"),
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Watches the project in the background, for the commands that keep running.
+pub fn spawn_long_session_watch(path: PathBuf, after: TimeDelta) {
+    thread::spawn(move || watch_long_sessions(&path, after));
+}
+
+/// Number of reminders that are due `every` so often since the stretch started.
+fn reminders_due(
+    start: DateTime<FixedOffset>,
+    now: DateTime<FixedOffset>,
+    every: TimeDelta,
+) -> i64 {
+    (now - start).num_seconds() / every.num_seconds()
+}
+
+/// Shows `message` every so often while a session is running, the count starts over when the
+/// session is started or resumed.
+pub fn remind(path: &PathBuf, every: TimeDelta, message: &str, cancel: Receiver<()>) -> Result<()> {
+    let mut sent = None;
+    loop {
+        let mut wait = POLL_INTERVAL;
+        match parser::last_session(path) {
+            Ok(session) => {
+                if let Some(start) = session.as_ref().and_then(stretch_start) {
+                    let now = Local::now().fixed_offset();
+                    let due = reminders_due(start, now, every);
+                    if due > 0 && sent != Some((start, due)) {
+                        sent = Some((start, due));
+                        let body = format!("Clocked in for {}", fmt_elapsed(now - start));
+                        if let Err(err) = send(message, &body) {
+                            eprintln!("notification error: {err:#}");
+                        }
+                    }
+                    // a reminder too far away to compute is never due, it just keeps polling
+                    let next = i32::try_from(due + 1)
+                        .ok()
+                        .and_then(|count| every.checked_mul(count))
+                        .and_then(|after| start.checked_add_signed(after));
+                    if let Some(next) = next {
+                        wait = wait.min((next - now).to_std().unwrap_or_default());
+                    }
+                }
+            }
+            Err(err) => eprintln!("error: {err:#}"),
+        }
+        if cancel.recv_timeout(wait).is_ok() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::{fmt_elapsed, reminders_due, stretch_start};
+    use crate::testing::{session, time};
+
+    #[test]
+    fn stretch() {
+        let mut session = session("2000-01-01T08:00:00Z")
+            .pause("2000-01-01T10:00:00Z", "2000-01-01T10:30:00Z")
+            .maybe_finished();
+        assert_eq!(stretch_start(&session), Some(time("2000-01-01T10:30:00Z")));
+        session.paused_at = Some(time("2000-01-01T12:00:00Z"));
+        assert_eq!(stretch_start(&session), None);
+
+        assert_eq!(fmt_elapsed(TimeDelta::minutes(4 * 60)), "4h");
+        assert_eq!(fmt_elapsed(TimeDelta::minutes(4 * 60 + 5)), "4h 5min");
+        assert_eq!(fmt_elapsed(TimeDelta::minutes(50)), "50min");
+
+        let start = time("2000-01-01T10:30:00Z");
+        let every = TimeDelta::minutes(50);
+        assert_eq!(reminders_due(start, time("2000-01-01T11:19:59Z"), every), 0);
+        assert_eq!(reminders_due(start, time("2000-01-01T11:20:00Z"), every), 1);
+        assert_eq!(reminders_due(start, time("2000-01-01T12:15:00Z"), every), 2);
+    }
+}
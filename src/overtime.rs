@@ -0,0 +1,187 @@
+use std::{
+    io::{self, Write},
+    ops::Bound,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{Datelike, FixedOffset, Local, NaiveDate, TimeDelta};
+
+use crate::{
+    config,
+    format_util::{fmt_duration, fmt_short_weekday, fmt_signed_duration},
+    i18n::catalog,
+    parser::{self, SessionIteratorClosingExt},
+    project_config::ProjectConfig,
+    schedule::Schedule,
+    summary::{self, NaiveDateExt, Summary},
+    writer::ReportOutput,
+};
+
+#[derive(Debug)]
+pub struct DayBalance {
+    pub date: NaiveDate,
+    pub worked: Duration,
+    pub expected: Duration,
+}
+
+impl DayBalance {
+    /// Surplus of the day when positive, deficit when negative.
+    pub fn difference(&self) -> TimeDelta {
+        TimeDelta::from_std(self.worked).unwrap() - TimeDelta::from_std(self.expected).unwrap()
+    }
+}
+
+/// Worked and expected time of every day between the dates, both included.
+pub fn balances(
+    summary: &Summary,
+    schedule: &Schedule,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<DayBalance> {
+    from.iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| DayBalance {
+            date,
+            worked: summary
+                .days
+                .get(&date)
+                .map_or(Duration::ZERO, |d| d.duration),
+            expected: schedule.expected_on(date),
+        })
+        .collect()
+}
+
+/// A line per day with work or expected work, and a line closing each week, along with the
+/// balance accumulated since the first day.
+pub fn format(balances: &[DayBalance], out: &mut impl Write) -> io::Result<()> {
+    let catalog = catalog();
+    let mut balance = TimeDelta::zero();
+    let mut week_difference = TimeDelta::zero();
+    for (index, day) in balances.iter().enumerate() {
+        balance += day.difference();
+        week_difference += day.difference();
+        if !day.worked.is_zero() || !day.expected.is_zero() {
+            writeln!(
+                out,
+                "{} {}  {} {}  {} {}  {}  {} {}",
+                day.date,
+                fmt_short_weekday(day.date.weekday()),
+                catalog.worked,
+                fmt_duration(&day.worked),
+                catalog.expected,
+                fmt_duration(&day.expected),
+                fmt_signed_duration(&day.difference()),
+                catalog.balance,
+                fmt_signed_duration(&balance)
+            )?;
+        }
+
+        let week = day.date.real_week();
+        let week_ends = balances
+            .get(index + 1)
+            .is_none_or(|next| next.date.real_week() != week);
+        if week_ends {
+            writeln!(
+                out,
+                "{} {}: {}  {} {}\n",
+                catalog.week,
+                week.first_day(),
+                fmt_signed_duration(&week_difference),
+                catalog.balance,
+                fmt_signed_duration(&balance)
+            )?;
+            week_difference = TimeDelta::zero();
+        }
+    }
+    Ok(())
+}
+
+/// The schedule of the project, the summary of its sessions and today in the given timezone or
+/// else in the one resolved for it.
+fn scheduled_summary(
+    path: &Path,
+    timezone: Option<FixedOffset>,
+) -> Result<(Schedule, Summary, NaiveDate)> {
+    let schedule = ProjectConfig::load(path)?.schedule()?;
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now();
+    let summary = Summary::summarize(sessions, &timezone);
+    let today = Local::now().with_timezone(&timezone).date_naive();
+    Ok((schedule, summary, today))
+}
+
+/// Prints the balance of each day against the schedule, as in `clockin overtime`.
+pub fn print_overtime(
+    path: &Path,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let (schedule, summary, today) = scheduled_summary(path, timezone)?;
+    let first = *summary.days.keys().next().unwrap_or(&today);
+    let (from, to) = summary::resolve_dates(range, first, today);
+    let mut out = ReportOutput::new(output);
+    format(&balances(&summary, &schedule, from, to), &mut out)?;
+    out.finish()
+}
+
+/// Prints the workdays without sessions up to yesterday, as in `clockin gaps`.
+pub fn print_gaps(
+    path: &Path,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let (schedule, summary, today) = scheduled_summary(path, timezone)?;
+    let yesterday = today.pred_opt().unwrap();
+    let first = *summary.days.keys().next().unwrap_or(&today);
+    let (from, to) = summary::resolve_dates(range, first, yesterday);
+    let mut out = ReportOutput::new(output);
+    for date in from.iter_days().take_while(|date| *date <= to) {
+        if schedule.is_workday(date) && !summary.days.contains_key(&date) {
+            writeln!(out, "{} {}", date, fmt_short_weekday(date.weekday()))?;
+        }
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Utc};
+
+    use super::{balances, format};
+    use crate::{summary::Summary, testing::session};
+
+    #[test]
+    fn overtime() {
+        let summary = Summary::summarize(
+            [
+                session("2000-01-07T08:00:00Z")
+                    .end("2000-01-07T17:00:00Z")
+                    .build(),
+                session("2000-01-08T10:00:00Z")
+                    .end("2000-01-08T11:00:00Z")
+                    .build(),
+            ]
+            .into_iter(),
+            &Utc,
+        );
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+        let days = balances(&summary, &"mon-fri 8h".parse().unwrap(), date(7), date(10));
+
+        let mut out = vec![];
+        format(&days, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "2000-01-07 Vie  trabajado 09:00:00  esperado 08:00:00  +01:00:00  saldo +01:00:00\n\
+             2000-01-08 Sab  trabajado 01:00:00  esperado 00:00:00  +01:00:00  saldo +02:00:00\n\
+             Semana 2000-01-03: +02:00:00  saldo +02:00:00\n\n\
+             2000-01-10 Lun  trabajado 00:00:00  esperado 08:00:00  -08:00:00  saldo -06:00:00\n\
+             Semana 2000-01-10: -08:00:00  saldo -06:00:00\n\n"
+        );
+    }
+}
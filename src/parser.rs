@@ -1,100 +1,458 @@
 use std::{
+    collections::{BTreeMap, VecDeque},
     fs::File,
-    io::{BufRead, BufReader, Lines},
-    path::Path,
+    io::{self, BufRead, Cursor, Lines, Read, Seek, SeekFrom},
+    iter::{Enumerate, Peekable},
+    ops::{Bound, Range, RangeBounds},
+    path::{Path, PathBuf},
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use chrono::{
-    DateTime, Duration, FixedOffset, Local, NaiveDateTime, NaiveTime, TimeDelta, TimeZone,
+    DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta,
+    TimeZone,
 };
+use itertools::Itertools;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::{crypt, file};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NaiveSession {
     pub start: NaiveDateTime,
     pub end: NaiveDateTime,
+    pub pauses: Vec<Range<NaiveDateTime>>,
     pub description: String,
+    /// machine the session was recorded on, from its `%h` line
+    pub host: Option<String>,
+    /// identifier given by a `%id` line, otherwise it is derived from the start
+    pub id: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "DateTime<TZ>: serde::Serialize",
+        deserialize = "DateTime<TZ>: serde::Deserialize<'de>"
+    ))
+)]
 pub struct SessionTZ<TZ: TimeZone> {
     pub start: DateTime<TZ>,
     pub end: DateTime<TZ>,
+    pub pauses: Vec<Range<DateTime<TZ>>>,
     pub description: String,
+    /// machine the session was recorded on, from its `%h` line
+    pub host: Option<String>,
+    /// identifier given by a `%id` line, otherwise it is derived from the start
+    pub id: Option<String>,
 }
 
+/// A finished session, with the offsets it was written with.
 pub type Session = SessionTZ<FixedOffset>;
 
 impl Session {
+    pub fn active_intervals(&self) -> Vec<Range<DateTime<FixedOffset>>> {
+        active_intervals(&self.start, &self.end, &self.pauses)
+    }
+
     pub fn duration(&self) -> Duration {
-        self.end - self.start
+        self.active_intervals()
+            .into_iter()
+            .map(|i| i.end - i.start)
+            .sum()
+    }
+
+    /// Joins a later session into this one, counting the gap between them as worked time.
+    pub fn merge(mut self, other: Session) -> Session {
+        self.end = other.end;
+        self.host = self.host.or(other.host);
+        self.id = self.id.or(other.id);
+        self.pauses.extend(other.pauses);
+        // the same description left in several sessions is kept once
+        let known = self.description.lines().collect::<Vec<_>>();
+        if !other.description.lines().all(|line| known.contains(&line)) {
+            if !self.description.is_empty() {
+                self.description.push('\n');
+            }
+            self.description.push_str(&other.description);
+        }
+        self
+    }
+
+    /// Moves the end so the session lasts `duration`, when shortening it the pauses after the
+    /// new end are dropped.
+    pub fn with_duration(mut self, duration: Duration) -> Session {
+        let extra = duration - self.duration();
+        if extra >= Duration::zero() {
+            self.end += extra;
+            return self;
+        }
+
+        let mut remaining = duration;
+        for interval in active_intervals(&self.start, &self.end, &self.pauses) {
+            let length = interval.end - interval.start;
+            if length >= remaining {
+                self.end = interval.start + remaining;
+                break;
+            }
+            remaining -= length;
+        }
+        let end = self.end;
+        self.pauses.retain(|p| p.start < end);
+        if let Some(pause) = self.pauses.last_mut() {
+            pause.end = pause.end.min(end);
+        }
+        self
+    }
+}
+
+/// Intervals between `start` and `end` not covered by the (chronologically ordered) pauses.
+fn active_intervals<T: Ord + Clone>(start: &T, end: &T, pauses: &[Range<T>]) -> Vec<Range<T>> {
+    let mut intervals = vec![];
+    let mut cursor = start.clone();
+    for pause in pauses {
+        let pause_start = pause.start.clone().max(cursor.clone()).min(end.clone());
+        if cursor < pause_start {
+            intervals.push(cursor.clone()..pause_start);
+        }
+        cursor = cursor.max(pause.end.clone().min(end.clone()));
+    }
+    if cursor < *end {
+        intervals.push(cursor..end.clone());
+    }
+    intervals
+}
+
+const SUPPORTED_VERSIONS: [u32; 2] = [1, 2];
+
+/// Project settings stored in the header block of the file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Metadata {
+    pub version: Option<u32>,
+    pub client: Option<String>,
+    pub timezone: Option<FixedOffset>,
+    pub rate: Option<f64>,
+    /// keys unknown to this version, kept for forward compatibility
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    fn parse_line(&mut self, (index, line): (usize, &str)) -> Result<(), ParseError> {
+        let invalid = || ParseError::Metadata {
+            line: index + 1,
+            text: line.to_owned(),
+        };
+
+        if let Some(header) = line.strip_prefix("%!") {
+            let version = header
+                .strip_prefix("clockin v")
+                .and_then(|v| v.parse().ok())
+                .filter(|v| SUPPORTED_VERSIONS.contains(v))
+                .ok_or(ParseError::Version {
+                    line: index + 1,
+                    text: line.to_owned(),
+                })?;
+            self.version = Some(version);
+        } else if let Some(entry) = line.strip_prefix("%meta ") {
+            let (key, value) = entry.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+            match key.trim() {
+                "client" => self.client = Some(value.to_owned()),
+                "timezone" => self.timezone = Some(value.parse().map_err(|_| invalid())?),
+                "rate" => self.rate = Some(value.parse().map_err(|_| invalid())?),
+                key => {
+                    self.extra.insert(key.to_owned(), value.to_owned());
+                }
+            }
+        }
+        Ok(())
     }
 }
 
-pub struct SessionIterator {
-    lines: Lines<BufReader<File>>,
+fn is_header_line(line: &str) -> bool {
+    line.is_empty() || is_comment_line(line) || line.starts_with("%!") || line.starts_with("%meta ")
+}
+
+pub struct SessionIterator<R: BufRead = Box<dyn BufRead + Send>> {
+    lines: Peekable<Enumerate<Lines<R>>>,
+    metadata: Metadata,
+    /// yearly file being read, none while reading the project file
+    year_file: Option<PathBuf>,
+    /// yearly files to read after the current one
+    year_files: VecDeque<(PathBuf, R)>,
+}
+
+#[derive(Error, Debug)]
+pub enum ParseError {
+    #[error("line {line}: invalid timestamp in {text:?}")]
+    Timestamp {
+        line: usize,
+        text: String,
+        source: chrono::ParseError,
+    },
+    #[error("line {line}: error while reading")]
+    Io { line: usize, source: io::Error },
+    #[error("line {line}: unsupported file format {text:?}")]
+    Version { line: usize, text: String },
+    #[error("line {line}: invalid metadata {text:?}")]
+    Metadata { line: usize, text: String },
+    #[error("{}", path.display())]
+    YearFile {
+        path: PathBuf,
+        source: Box<ParseError>,
+    },
+}
+
+/// First hex digits of the FNV-1a hash of the project and the start, which unlike the hasher of
+/// the standard library is the same on every version and machine.
+fn hashed_id(project: &str, start: i64) -> String {
+    let hash = project
+        .bytes()
+        .chain([0])
+        .chain(start.to_be_bytes())
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{:07x}", hash >> 36)
+}
+
+/// Splits a macro line into its kind and its argument.
+pub fn split_macro(line: &str) -> Option<(char, &str)> {
+    let mut chars = line.strip_prefix('%')?.chars();
+    let kind = chars.next()?;
+    Some((kind, chars.as_str()))
+}
+
+pub fn is_macro_line(line: impl AsRef<str>, prefix: char) -> bool {
+    split_macro(line.as_ref()).is_some_and(|(kind, _)| kind == prefix)
+}
+
+pub fn extract_macro(
+    line: &str,
+    prefix: char,
+) -> Option<Result<DateTime<FixedOffset>, chrono::ParseError>> {
+    split_macro(line)
+        .filter(|(kind, _)| *kind == prefix)
+        .map(|(_, argument)| DateTime::parse_from_rfc3339(argument))
+}
+
+fn parse_macro(
+    (index, line): (usize, &str),
+    prefix: char,
+) -> Result<Option<DateTime<FixedOffset>>, ParseError> {
+    extract_macro(line, prefix)
+        .transpose()
+        .map_err(|source| ParseError::Timestamp {
+            line: index + 1,
+            text: line.to_owned(),
+            source,
+        })
+}
+
+/// Strips the byte order mark and carriage returns left by files written on other systems.
+pub fn clean_line(line: &str) -> &str {
+    line.strip_prefix('\u{feff}')
+        .unwrap_or(line)
+        .trim_end_matches(['\r', '\n'])
 }
 
-fn is_macro_line(line: impl AsRef<str>, prefix: char) -> bool {
-    line.as_ref().starts_with(['%', prefix])
+/// Comment lines start with `# `, so hashtags at the beginning of a line are still part of the
+/// description.
+pub fn is_comment_line(line: &str) -> bool {
+    line == "#" || line.starts_with("# ")
 }
 
-fn extract_macro(line: &str, prefix: char) -> Option<DateTime<FixedOffset>> {
-    is_macro_line(line, prefix)
-        .then(|| &line[2..])
-        .map(|d| DateTime::parse_from_rfc3339(d).unwrap())
+fn read_line((index, line): (usize, io::Result<String>)) -> Result<(usize, String), ParseError> {
+    line.map(|line| (index, clean_line(&line).to_owned()))
+        .map_err(|source| ParseError::Io {
+            line: index + 1,
+            source,
+        })
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct MaybeFinishedSessionTZ<TZ: TimeZone> {
     pub start: DateTime<TZ>,
     pub end: Option<DateTime<TZ>>,
+    pub pauses: Vec<Range<DateTime<TZ>>>,
+    pub paused_at: Option<DateTime<TZ>>,
     pub description: String,
+    /// machine the session was recorded on, from its `%h` line
+    pub host: Option<String>,
+    /// identifier given by a `%id` line, otherwise it is derived from the start
+    pub id: Option<String>,
+}
+
+impl<TZ: TimeZone> From<SessionTZ<TZ>> for MaybeFinishedSessionTZ<TZ> {
+    fn from(session: SessionTZ<TZ>) -> Self {
+        MaybeFinishedSessionTZ {
+            start: session.start,
+            end: Some(session.end),
+            pauses: session.pauses,
+            paused_at: None,
+            description: session.description,
+            host: session.host,
+            id: session.id,
+        }
+    }
 }
 
 impl MaybeFinishedSessionTZ<FixedOffset> {
-    fn into_finished_now(self) -> SessionTZ<FixedOffset> {
+    pub fn into_finished_now(self) -> SessionTZ<FixedOffset> {
         let MaybeFinishedSessionTZ {
             start,
             end,
+            mut pauses,
+            paused_at,
             description,
+            host,
+            id,
         } = self;
+        let end = end.unwrap_or(Local::now().fixed_offset());
+        // a pause that was never resumed lasts until the end of the session
+        pauses.extend(paused_at.map(|paused_at| paused_at..end));
         SessionTZ {
             start,
-            end: end.unwrap_or(Local::now().fixed_offset()),
+            end,
+            pauses,
             description,
+            host,
+            id,
         }
     }
 
     pub fn is_finished(&self) -> bool {
         self.end.is_some()
     }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Short identifier of the session, the one of its `%id` line or else a hash of the project
+    /// and the start.
+    pub fn short_id(&self, project: &str) -> String {
+        self.id
+            .clone()
+            .unwrap_or_else(|| hashed_id(project, self.start.timestamp()))
+    }
+
+    /// Splits the session in two at the given instant, both halves keep the description and only
+    /// the first one keeps the `%id`.
+    pub fn split_at(self, at: DateTime<FixedOffset>) -> (Self, Self) {
+        let (mut first_pauses, mut second_pauses): (Vec<_>, Vec<_>) =
+            self.pauses.iter().cloned().partition(|p| p.start < at);
+        if let Some(pause) = first_pauses.last_mut()
+            && pause.end > at
+        {
+            second_pauses.insert(0, at..pause.end);
+            pause.end = at;
+        }
+
+        let (first_paused_at, second_paused_at) = match self.paused_at {
+            Some(paused_at) if paused_at < at => (Some(paused_at), Some(at)),
+            paused_at => (None, paused_at),
+        };
+        if let Some(paused_at) = first_paused_at {
+            first_pauses.push(paused_at..at);
+        }
+
+        (
+            MaybeFinishedSessionTZ {
+                start: self.start,
+                end: Some(at),
+                pauses: first_pauses,
+                paused_at: None,
+                description: self.description.clone(),
+                host: self.host.clone(),
+                id: self.id,
+            },
+            MaybeFinishedSessionTZ {
+                start: at,
+                end: self.end,
+                pauses: second_pauses,
+                paused_at: second_paused_at,
+                description: self.description,
+                host: self.host,
+                id: None,
+            },
+        )
+    }
 }
 
-impl Iterator for SessionIterator {
-    type Item = MaybeFinishedSessionTZ<FixedOffset>;
+impl<R: BufRead> Iterator for SessionIterator<R> {
+    type Item = Result<MaybeFinishedSessionTZ<FixedOffset>, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let start = 'a: {
-            loop {
-                let line = self.lines.next()?.unwrap();
-                if let Some(m) = extract_macro(&line, '-') {
-                    break 'a m;
+        loop {
+            match self.next_session() {
+                Ok(None) => {
+                    let (path, reader) = self.year_files.pop_front()?;
+                    self.lines = reader.lines().enumerate().peekable();
+                    self.year_file = Some(path);
                 }
+                Err(err) => {
+                    return Some(Err(match &self.year_file {
+                        Some(path) => ParseError::YearFile {
+                            path: path.clone(),
+                            source: Box::new(err),
+                        },
+                        None => err,
+                    }));
+                }
+                Ok(Some(session)) => return Some(Ok(session)),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> SessionIterator<R> {
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn next_session(&mut self) -> Result<Option<MaybeFinishedSessionTZ<FixedOffset>>, ParseError> {
+        let start = loop {
+            let Some(line) = self.lines.next() else {
+                return Ok(None);
+            };
+            let (index, line) = read_line(line)?;
+            if let Some(m) = parse_macro((index, &line), '-')? {
+                break m;
             }
         };
 
         let mut description = String::new();
         let mut end = None;
+        let mut pauses = vec![];
+        let mut paused_at = None;
+        let mut host = None;
+        let mut id = None;
 
-        loop {
-            let Some(line) = self.lines.next() else {
-                break;
-            };
-            let line = line.unwrap();
-            if let Some(m) = extract_macro(&line, '+') {
+        // a start macro without a preceding end leaves the current session unfinished
+        while let Some(line) = self
+            .lines
+            .next_if(|(_, line)| !line.as_ref().is_ok_and(|line| is_macro_line(line, '-')))
+        {
+            let (index, line) = read_line(line)?;
+            if let Some(m) = parse_macro((index, &line), '+')? {
                 end.replace(m);
                 break;
-            } else {
+            } else if let Some(m) = parse_macro((index, &line), 'p')? {
+                paused_at.get_or_insert(m);
+            } else if let Some(m) = parse_macro((index, &line), 'r')? {
+                if let Some(paused_at) = paused_at.take() {
+                    pauses.push(paused_at..m);
+                }
+            } else if let Some(name) = line.strip_prefix("%h ") {
+                host = Some(name.trim().to_owned());
+            } else if let Some(explicit) = line.strip_prefix("%id ") {
+                id = Some(explicit.trim().to_owned());
+            } else if !is_comment_line(&line) {
                 description.push_str(&line);
                 description.push('\n');
             }
@@ -103,23 +461,44 @@ impl Iterator for SessionIterator {
         // remove last newline
         assert!(description.pop().is_none_or(|ch| ch == '\n'));
 
-        Some(MaybeFinishedSessionTZ {
+        Ok(Some(MaybeFinishedSessionTZ {
             start,
             end,
+            pauses,
+            paused_at,
             description: description.to_owned(),
-        })
+            host,
+            id,
+        }))
     }
 }
 
 pub trait SessionIteratorClosingExt {
-    fn as_finished_now(self) -> impl Iterator<Item = Session>;
+    fn into_finished_now(self) -> impl Iterator<Item = Session>;
 }
 impl<I: Iterator<Item = MaybeFinishedSessionTZ<FixedOffset>>> SessionIteratorClosingExt for I {
-    fn as_finished_now(self) -> impl Iterator<Item = Session> {
+    fn into_finished_now(self) -> impl Iterator<Item = Session> {
         self.map(|s| s.into_finished_now())
     }
 }
 
+pub trait SessionMergingExt {
+    fn merge_gaps(self, max_gap: TimeDelta) -> impl Iterator<Item = Session>;
+}
+impl<I: Iterator<Item = Session>> SessionMergingExt for I {
+    #[allow(clippy::result_large_err)]
+    fn merge_gaps(self, max_gap: TimeDelta) -> impl Iterator<Item = Session> {
+        // sessions of different machines are kept apart so they can still be told apart
+        self.coalesce(move |a, b| {
+            if b.start >= a.end && b.start - a.end < max_gap && a.host == b.host {
+                Ok(a.merge(b))
+            } else {
+                Err((a, b))
+            }
+        })
+    }
+}
+
 pub trait NaiveSessionIteratorExt {
     fn cut_at_days(self) -> impl Iterator<Item = NaiveSession>;
     fn and_local_timezone<TZ: TimeZone>(self, tz: TZ) -> impl Iterator<Item = SessionTZ<TZ>>;
@@ -148,28 +527,186 @@ impl<TZ: TimeZone, I: Iterator<Item = SessionTZ<TZ>>> SessionIteratorExt for I {
     }
 }
 
-pub fn parse_file(path: impl AsRef<Path>) -> Result<SessionIterator> {
-    let file = BufReader::new(File::open(path)?);
+/// Reads the header block and returns an iterator over the sessions after it.
+pub fn parse_reader<R: BufRead>(reader: R) -> Result<SessionIterator<R>, ParseError> {
+    let mut lines = reader.lines().enumerate().peekable();
+    let mut metadata = Metadata::default();
+    while let Some(line) = lines.next_if(|(_, line)| {
+        line.as_ref()
+            .is_ok_and(|line| is_header_line(clean_line(line)))
+    }) {
+        let (index, line) = read_line(line)?;
+        metadata.parse_line((index, &line))?;
+    }
+
     Ok(SessionIterator {
-        lines: file.lines(),
+        lines,
+        metadata,
+        year_file: None,
+        year_files: VecDeque::new(),
     })
 }
 
+/// Reads the project file followed by its yearly files. The errors in a yearly file name it and
+/// count the lines from its start.
+pub fn parse_file(path: impl AsRef<Path>) -> Result<SessionIterator> {
+    let path = path.as_ref();
+    let mut sessions = parse_reader(crypt::open(path)?)
+        .with_context(|| format!("error while parsing {}", path.display()))?;
+    for year_file in file::session_files(path)?.into_iter().skip(1) {
+        let reader = crypt::open(&year_file)?;
+        sessions.year_files.push_back((year_file, reader));
+    }
+    Ok(sessions)
+}
+
+pub fn parse_metadata(path: impl AsRef<Path>) -> Result<Metadata> {
+    Ok(parse_file(path)?.metadata().clone())
+}
+
+pub fn parse_sessions(path: impl AsRef<Path>) -> Result<Vec<MaybeFinishedSessionTZ<FixedOffset>>> {
+    let path = path.as_ref();
+    parse_file(path)?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("error while parsing {}", path.display()))
+}
+
+/// Sessions starting within the dates, running ones are considered finished now.
+pub fn sessions_in_range(
+    path: &Path,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: &FixedOffset,
+) -> Result<impl Iterator<Item = Session>> {
+    Ok(parse_sessions(path)?
+        .into_iter()
+        .into_finished_now()
+        .filter(move |s| range.contains(&s.start.with_timezone(timezone).date_naive())))
+}
+
+/// Index of the session with the id, or else of the only one whose id starts with it.
+pub fn find_session(
+    file: &Path,
+    sessions: &[MaybeFinishedSessionTZ<FixedOffset>],
+    id: &str,
+) -> Result<usize> {
+    let project = file::project_name(file)?;
+    let ids = sessions.iter().map(|s| s.short_id(&project)).collect_vec();
+    if let Some(index) = ids.iter().position(|other| other == id) {
+        return Ok(index);
+    }
+    match ids
+        .iter()
+        .positions(|other| other.starts_with(id))
+        .collect_vec()[..]
+    {
+        [index] => Ok(index),
+        [] => bail!("there is no session with the id {id}"),
+        ref matches => bail!("{} sessions have an id starting with {id}", matches.len()),
+    }
+}
+
+/// The `--grep` argument as a regular expression.
+pub fn parse_grep(grep: Option<String>) -> Result<Option<Regex>> {
+    grep.map(|grep| Regex::new(&grep).with_context(|| format!("invalid --grep {grep:?}")))
+        .transpose()
+}
+
+pub fn last_session(path: impl AsRef<Path>) -> Result<Option<MaybeFinishedSessionTZ<FixedOffset>>> {
+    Ok(parse_sessions(path)?.pop())
+}
+
+/// Like [`last_session`], reading only the end of the file, for the commands that have to answer
+/// right away no matter how many sessions there are.
+pub fn last_session_from_tail(
+    path: impl AsRef<Path>,
+) -> Result<Option<MaybeFinishedSessionTZ<FixedOffset>>> {
+    // the last yearly files may have no sessions yet
+    for path in file::session_files(path.as_ref())?.iter().rev() {
+        let mut file = File::open(path)?;
+        let mut head = [0; 64];
+        let read = file.read(&mut head)?;
+        // an encrypted file has to be read whole, so only its last session is parsed
+        let last = if crypt::encrypted_with(&head[..read]).is_some() {
+            let content = crypt::read(path)?;
+            last_session_in_tail(Cursor::new(content), 4096)
+        } else {
+            last_session_in_tail(file, 4096)
+        }
+        .with_context(|| format!("error while parsing {}", path.display()))?;
+        if last.is_some() {
+            return Ok(last);
+        }
+    }
+    Ok(None)
+}
+
+/// Reads a larger end of the file each time until it has the start of a session, line numbers of
+/// errors are counted from there.
+fn last_session_in_tail(
+    mut file: impl Read + Seek,
+    mut length: u64,
+) -> Result<Option<MaybeFinishedSessionTZ<FixedOffset>>> {
+    let file_length = file.seek(SeekFrom::End(0))?;
+    loop {
+        let offset = file_length.saturating_sub(length);
+        file.seek(SeekFrom::Start(offset))?;
+        let mut tail = vec![];
+        file.read_to_end(&mut tail)?;
+        let session_start = if offset == 0 {
+            Some(0)
+        } else {
+            // the first line may be cut, so it is never taken as a start
+            tail.windows(3)
+                .rposition(|w| w == b"\n%-")
+                .map(|position| position + 1)
+        };
+        if let Some(session_start) = session_start {
+            let mut last = None;
+            for session in parse_reader(&tail[session_start..])? {
+                last = Some(session?);
+            }
+            return Ok(last);
+        }
+        length *= 2;
+    }
+}
+
+pub fn is_session_running(path: impl AsRef<Path>) -> Result<bool> {
+    Ok(last_session(path)?.is_some_and(|s| !s.is_finished()))
+}
+
 impl<TZ: TimeZone> SessionTZ<TZ> {
     pub fn with_timezone<TZ2: TimeZone>(self, tz2: &TZ2) -> SessionTZ<TZ2> {
         SessionTZ {
             start: self.start.with_timezone(tz2),
             end: self.end.with_timezone(tz2),
+            pauses: self
+                .pauses
+                .into_iter()
+                .map(|p| p.start.with_timezone(tz2)..p.end.with_timezone(tz2))
+                .collect(),
             description: self.description,
+            host: self.host,
+            id: self.id,
         }
     }
 
     pub fn naive_local(self) -> NaiveSession {
+        // use start timezone just in case it differs
+        let tz = self.start.timezone();
         NaiveSession {
             start: self.start.naive_local(),
-            // use start timezone just in case it differs
-            end: self.end.with_timezone(&self.start.timezone()).naive_local(),
+            end: self.end.with_timezone(&tz).naive_local(),
+            pauses: self
+                .pauses
+                .into_iter()
+                .map(|p| {
+                    p.start.with_timezone(&tz).naive_local()..p.end.with_timezone(&tz).naive_local()
+                })
+                .collect(),
             description: self.description,
+            host: self.host,
+            id: self.id,
         }
     }
 }
@@ -182,31 +719,69 @@ impl NaiveSession {
             .iter_days()
             .zip(date_start.iter_days().skip(1))
             .take_while(move |(d, _tmrw)| d.and_time(NaiveTime::MIN) < self.end)
-            .map(move |(d, tmrw)| Self {
-                start: self.start.max(d.and_time(NaiveTime::MIN)),
-                end: self.end.min(tmrw.and_time(NaiveTime::MIN)),
-                description: self.description.clone(),
+            .map(move |(d, tmrw)| {
+                let start = self.start.max(d.and_time(NaiveTime::MIN));
+                let end = self.end.min(tmrw.and_time(NaiveTime::MIN));
+                Self {
+                    pauses: self
+                        .pauses
+                        .iter()
+                        .map(|p| p.start.max(start).min(end)..p.end.max(start).min(end))
+                        .filter(|p| !p.is_empty())
+                        .collect(),
+                    start,
+                    end,
+                    description: self.description.clone(),
+                    host: self.host.clone(),
+                    id: self.id.clone(),
+                }
             })
     }
 
     pub fn and_local_timezone<TZ: TimeZone>(self, tz: TZ) -> SessionTZ<TZ> {
         SessionTZ::<TZ> {
             start: self.start.and_local_timezone(tz.clone()).unwrap(),
-            end: self.end.and_local_timezone(tz).unwrap(),
+            end: self.end.and_local_timezone(tz.clone()).unwrap(),
+            pauses: self
+                .pauses
+                .into_iter()
+                .map(|p| {
+                    p.start.and_local_timezone(tz.clone()).unwrap()
+                        ..p.end.and_local_timezone(tz.clone()).unwrap()
+                })
+                .collect(),
             description: self.description,
+            host: self.host,
+            id: self.id,
         }
     }
 
+    pub fn active_intervals(&self) -> Vec<Range<NaiveDateTime>> {
+        active_intervals(&self.start, &self.end, &self.pauses)
+    }
+
     pub fn duration(&self) -> TimeDelta {
-        self.end - self.start
+        self.active_intervals()
+            .into_iter()
+            .map(|i| i.end - i.start)
+            .sum()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+    use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+
+    use std::{fs, io::Cursor};
 
-    use crate::parser::NaiveSession;
+    use crate::{
+        parser::{
+            NaiveSession, SessionIteratorClosingExt, SessionMergingExt, last_session_from_tail,
+            last_session_in_tail, parse_metadata, parse_reader, parse_sessions,
+        },
+        testing::session,
+        writer,
+    };
 
     #[test]
     fn split_at_days() {
@@ -219,7 +794,10 @@ mod tests {
         let sess = |from, to| NaiveSession {
             start: from,
             end: to,
+            pauses: vec![],
             description: String::new(),
+            host: None,
+            id: None,
         };
 
         assert_eq!(
@@ -250,4 +828,248 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn pauses() {
+        let dt = |day, h, m| {
+            NaiveDateTime::new(
+                NaiveDate::from_ymd_opt(2000, 1, day).unwrap(),
+                NaiveTime::from_hms_opt(h, m, 0).unwrap(),
+            )
+        };
+        let sess = NaiveSession {
+            start: dt(1, 22, 0),
+            end: dt(2, 2, 0),
+            pauses: vec![dt(1, 23, 0)..dt(2, 1, 0)],
+            description: String::new(),
+            host: None,
+            id: None,
+        };
+
+        assert_eq!(sess.duration(), TimeDelta::hours(2));
+        assert_eq!(
+            sess.active_intervals(),
+            vec![dt(1, 22, 0)..dt(1, 23, 0), dt(2, 1, 0)..dt(2, 2, 0)],
+        );
+
+        let days = sess.split_at_days().collect::<Vec<_>>();
+        assert_eq!(days[0].pauses, vec![dt(1, 23, 0)..dt(2, 0, 0)]);
+        assert_eq!(days[1].pauses, vec![dt(2, 0, 0)..dt(2, 1, 0)]);
+        assert_eq!(
+            days.iter().map(|d| d.duration()).collect::<Vec<_>>(),
+            vec![TimeDelta::hours(1), TimeDelta::hours(1)],
+        );
+    }
+
+    #[test]
+    fn foreign_line_endings_and_comments() {
+        let file = "\u{feff}%-2000-01-01T10:00:00+00:00\r\n\
+                    # not part of the description\r\n\
+                    #tag work\r\n\
+                    %+2000-01-01T11:00:00+00:00\r\n";
+        let sessions = parse_reader(file.as_bytes())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].description, "#tag work");
+        assert!(sessions[0].is_finished());
+    }
+
+    #[test]
+    fn merge_gaps() {
+        let file = "%-2000-01-01T10:00:00+00:00\n\
+                    backend: auth\nlogin form\n\
+                    %+2000-01-01T11:00:00+00:00\n\
+                    %-2000-01-01T11:05:00+00:00\n\
+                    backend: auth\nlogin form\n\
+                    %+2000-01-01T12:00:00+00:00\n\
+                    %-2000-01-01T12:01:00+00:00\n\
+                    review\n\
+                    %+2000-01-01T13:00:00+00:00\n\
+                    %-2000-01-01T15:00:00+00:00\n\
+                    %+2000-01-01T16:00:00+00:00\n";
+        let sessions = parse_reader(file.as_bytes())
+            .unwrap()
+            .map(Result::unwrap)
+            .into_finished_now()
+            .merge_gaps(TimeDelta::minutes(10))
+            .collect::<Vec<_>>();
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].description, "backend: auth\nlogin form\nreview");
+        assert_eq!(sessions[0].duration(), TimeDelta::hours(3));
+
+        let sessions = [
+            session("2000-01-01T10:00:00Z")
+                .end("2000-01-01T11:00:00Z")
+                .host("laptop")
+                .build(),
+            session("2000-01-01T11:05:00Z")
+                .end("2000-01-01T12:00:00Z")
+                .host("desk")
+                .build(),
+        ];
+        assert_eq!(
+            sessions
+                .into_iter()
+                .merge_gaps(TimeDelta::minutes(10))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn hosts() {
+        let file = "%-2000-01-01T10:00:00+00:00\n\
+                    %h  laptop \n\
+                    backend: fix auth\n\
+                    %+2000-01-01T11:00:00+00:00\n\
+                    %-2000-01-02T10:00:00+00:00\n\
+                    %header\n\
+                    %+2000-01-02T11:00:00+00:00\n";
+        let sessions = parse_reader(file.as_bytes())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(sessions[0].host.as_deref(), Some("laptop"));
+        assert_eq!(sessions[0].description, "backend: fix auth");
+        assert_eq!(sessions[1].host, None);
+        assert_eq!(sessions[1].description, "%header");
+        assert!(writer::fmt_session(&sessions[0]).contains("%h laptop\n"));
+    }
+
+    #[test]
+    fn ids() {
+        let file = "%-2000-01-01T10:00:00+00:00\n\
+                    %id standup\n\
+                    %+2000-01-01T11:00:00+00:00\n\
+                    %-2000-01-01T12:00:00+00:00\n\
+                    %+2000-01-01T13:00:00+00:00\n";
+        let sessions = parse_reader(file.as_bytes())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(sessions[0].short_id("acme"), "standup");
+        assert_eq!(sessions[0].description, "");
+        assert_eq!(sessions[1].short_id("acme"), "6dea15d");
+        assert_ne!(sessions[1].short_id("other"), sessions[1].short_id("acme"));
+
+        let at = sessions[0].start + TimeDelta::minutes(30);
+        let (first, second) = sessions[0].clone().split_at(at);
+        assert_eq!(first.short_id("acme"), "standup");
+        assert_ne!(second.short_id("acme"), "standup");
+        assert!(writer::fmt_session(&first).contains("%id standup\n"));
+    }
+
+    #[test]
+    fn header() {
+        let file = "%!clockin v2\n\
+                    %meta client = ACME\n\
+                    %meta timezone=-03:00\n\
+                    %meta rate=25.5\n\
+                    %meta color=blue\n\
+                    \n\
+                    %-2000-01-01T10:00:00+00:00\n";
+        let sessions = parse_reader(file.as_bytes()).unwrap();
+        let metadata = sessions.metadata().clone();
+
+        assert_eq!(metadata.version, Some(2));
+        assert_eq!(metadata.client.as_deref(), Some("ACME"));
+        assert_eq!(metadata.timezone, FixedOffset::west_opt(3 * 3600));
+        assert_eq!(metadata.rate, Some(25.5));
+        assert_eq!(metadata.extra["color"], "blue");
+        assert_eq!(sessions.count(), 1);
+
+        assert!(parse_reader("%!clockin v9\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn last_session_in_the_tail() {
+        let file = "%-2000-01-01T10:00:00+00:00\n\
+                    first\n\
+                    %+2000-01-01T11:00:00+00:00\n\
+                    \n\
+                    %-2000-01-02T10:00:00+00:00\n\
+                    second, with a long description to read the tail a few times\n\
+                    %p2000-01-02T10:30:00+00:00\n";
+        for length in [1, 16, 1024] {
+            let session = last_session_in_tail(Cursor::new(file), length)
+                .unwrap()
+                .unwrap();
+            assert_eq!(session.start.to_rfc3339(), "2000-01-02T10:00:00+00:00");
+            assert!(session.is_paused());
+            assert_eq!(
+                session.description,
+                "second, with a long description to read the tail a few times"
+            );
+        }
+        assert_eq!(last_session_in_tail(Cursor::new(""), 16).unwrap(), None);
+    }
+
+    #[test]
+    fn yearly_files() {
+        let dir = std::env::temp_dir().join(format!("clockin-yearly-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let project = dir.join("acme");
+        // the project file does not end in a newline
+        fs::write(
+            &project,
+            "%!clockin v1\n%-2023-06-01T10:00:00+00:00\n%+2023-06-01T11:00:00+00:00",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("acme.2025"),
+            "%-2025-01-01T10:00:00+00:00\n%p2025-01-01T10:30:00+00:00\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("acme.2024"),
+            "%-2024-01-01T10:00:00+00:00\n%+2024-01-01T11:00:00+00:00\n\n",
+        )
+        .unwrap();
+        fs::write(dir.join("acme.toml"), "").unwrap();
+        fs::write(dir.join("acme.20245"), "").unwrap();
+
+        let starts = parse_sessions(&project)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.start.to_rfc3339())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            starts,
+            [
+                "2023-06-01T10:00:00+00:00",
+                "2024-01-01T10:00:00+00:00",
+                "2025-01-01T10:00:00+00:00"
+            ]
+        );
+        assert_eq!(parse_metadata(&project).unwrap().version, Some(1));
+        assert!(
+            last_session_from_tail(&project)
+                .unwrap()
+                .unwrap()
+                .is_paused()
+        );
+
+        // errors name the yearly file and count its own lines
+        fs::write(
+            dir.join("acme.2024"),
+            "%-2024-01-01T10:00:00+00:00\n%+2024-01-01T11:00\n",
+        )
+        .unwrap();
+        let err = parse_sessions(&project).unwrap_err();
+        assert!(
+            format!("{:#}", err).ends_with(&format!(
+                "{}: line 2: invalid timestamp in \"%+2024-01-01T11:00\": premature end of input",
+                dir.join("acme.2024").display()
+            )),
+            "{err:#}"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
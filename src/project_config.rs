@@ -0,0 +1,254 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    budget::Budget,
+    cli::{Encryption, ProjectCommand, ProjectKey},
+    file,
+    flexitime::Flexitime,
+    format_util::fmt_duration,
+    parser,
+    schedule::Schedule,
+    status, writer,
+};
+
+/// Settings of a project kept in `<project>.toml` next to the project file.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProjectConfig {
+    pub client: Option<String>,
+    pub rate: Option<f64>,
+    pub currency: Option<String>,
+    pub weekly_hours: Option<f64>,
+    pub schedule: Option<Schedule>,
+    /// days without expected work, whatever the schedule says
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub holidays: BTreeSet<NaiveDate>,
+    /// time allowed for each sub-project
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub budgets: BTreeMap<String, Budget>,
+    pub flexitime: Option<Flexitime>,
+    /// URLs notified with a POST request when a session starts or ends
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<String>,
+    /// sessions are started in `<project>.<year>` instead of the project file
+    pub yearly_files: Option<bool>,
+    /// left out of the listings and of the reports of every project
+    pub archived: Option<bool>,
+    /// the project files are kept encrypted with this tool, see `clockin link --encrypt`
+    pub encryption: Option<Encryption>,
+    /// age recipient or gpg key the project files are encrypted for
+    pub recipient: Option<String>,
+}
+
+impl ProjectConfig {
+    /// The configuration of the project, empty if it has none.
+    pub fn load(project_file: &Path) -> Result<Self> {
+        let path = file::project_config_file(project_file)?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("error while reading {}", path.display()));
+            }
+        };
+        let config: Self = toml::from_str(&content)
+            .with_context(|| format!("error while parsing {}", path.display()))?;
+        if let Some(hours) = config.weekly_hours {
+            check_weekly_hours(hours).with_context(|| format!("error in {}", path.display()))?;
+        }
+        Ok(config)
+    }
+
+    /// Like [`ProjectConfig::load`], filling the settings that are missing with the ones in the
+    /// header of the project file.
+    pub fn load_with_metadata(project_file: &Path) -> Result<Self> {
+        let mut config = Self::load(project_file)?;
+        let metadata = parser::parse_metadata(project_file)?;
+        config.client = config.client.or(metadata.client);
+        config.rate = config.rate.or(metadata.rate);
+        Ok(config)
+    }
+
+    /// The schedule of the project, or one spreading the weekly hours from monday to friday,
+    /// without the holidays.
+    pub fn schedule(&self) -> Result<Schedule> {
+        let schedule = self
+            .schedule
+            .clone()
+            .or(self.weekly_hours.map(Schedule::from_weekly_hours))
+            .context("the project has no schedule, set it with `clockin project set schedule`")?;
+        Ok(schedule.with_holidays(self.holidays.clone()))
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived == Some(true)
+    }
+
+    pub fn save(&self, project_file: &Path) -> Result<()> {
+        let path = file::project_config_file(project_file)?;
+        writer::write_atomically(&path, toml::to_string(self)?)
+            .with_context(|| format!("error while writing {}", path.display()))
+    }
+
+    pub fn set(&mut self, key: ProjectKey, value: &str) -> Result<()> {
+        let number = || {
+            value
+                .parse()
+                .with_context(|| format!("invalid number {value:?}"))
+        };
+        let boolean = || {
+            value
+                .parse()
+                .with_context(|| format!("invalid boolean {value:?}"))
+        };
+        match key {
+            ProjectKey::Client => self.client = Some(value.to_owned()),
+            ProjectKey::Rate => self.rate = Some(number()?),
+            ProjectKey::Currency => self.currency = Some(value.to_owned()),
+            ProjectKey::WeeklyHours => self.weekly_hours = Some(check_weekly_hours(number()?)?),
+            ProjectKey::Schedule => self.schedule = Some(value.parse()?),
+            ProjectKey::YearlyFiles => self.yearly_files = Some(boolean()?),
+            ProjectKey::Archived => self.archived = Some(boolean()?),
+        }
+        Ok(())
+    }
+
+    pub fn unset(&mut self, key: ProjectKey) {
+        match key {
+            ProjectKey::Client => self.client = None,
+            ProjectKey::Rate => self.rate = None,
+            ProjectKey::Currency => self.currency = None,
+            ProjectKey::WeeklyHours => self.weekly_hours = None,
+            ProjectKey::Schedule => self.schedule = None,
+            ProjectKey::YearlyFiles => self.yearly_files = None,
+            ProjectKey::Archived => self.archived = None,
+        }
+    }
+}
+
+/// The weekly hours if a week can hold them.
+fn check_weekly_hours(hours: f64) -> Result<f64> {
+    if !(0.0..=168.0).contains(&hours) {
+        bail!("invalid weekly-hours {hours}, expected between 0 and 168");
+    }
+    Ok(hours)
+}
+
+/// Files of the projects in the data directory, sorted by name, without the archived ones unless
+/// `include_archived` is set.
+pub fn list_project_files(include_archived: bool) -> Result<Vec<PathBuf>> {
+    let mut projects = vec![];
+    for path in file::list_project_files()? {
+        if include_archived || !ProjectConfig::load(&path)?.is_archived() {
+            projects.push(path);
+        }
+    }
+    Ok(projects)
+}
+
+/// Prints a line per project with its last session, total time and state, as in
+/// `clockin projects`.
+pub fn print_projects(include_archived: bool) -> Result<()> {
+    let overviews = list_project_files(include_archived)?
+        .iter()
+        .map(|path| {
+            let archived = ProjectConfig::load(path)?.is_archived();
+            Ok((status::overview(path)?, archived))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let width = overviews
+        .iter()
+        .map(|(o, _)| o.project.chars().count())
+        .max()
+        .unwrap_or_default();
+    for (overview, archived) in overviews {
+        let last_session = overview
+            .last_session
+            .map_or("-".to_owned(), |start| start.format("%Y-%m-%d").to_string());
+        println!(
+            "{:width$}  {last_session:10}  {}  {}{}",
+            overview.project,
+            fmt_duration(&overview.total),
+            overview.state.as_str(),
+            if archived { "  archived" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+/// Shows or changes the settings of the project, as in `clockin project`.
+pub fn run(path: &Path, command: ProjectCommand) -> Result<()> {
+    let mut config = ProjectConfig::load(path)?;
+    match command {
+        ProjectCommand::Show => print!("{}", toml::to_string(&config)?),
+        ProjectCommand::Set { key, value } => {
+            config.set(key, &value)?;
+            config.save(path)?;
+        }
+        ProjectCommand::Unset { key } => {
+            config.unset(key);
+            config.save(path)?;
+        }
+        ProjectCommand::AddWebhook { url } => {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                bail!("invalid webhook {url:?}, expected an http or https URL");
+            }
+            if !config.webhooks.contains(&url) {
+                config.webhooks.push(url);
+                config.save(path)?;
+            }
+        }
+        ProjectCommand::RemoveWebhook { url } => {
+            let count = config.webhooks.len();
+            config.webhooks.retain(|webhook| *webhook != url);
+            if config.webhooks.len() == count {
+                bail!("there is no webhook {url:?}");
+            }
+            config.save(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProjectConfig;
+    use crate::cli::ProjectKey;
+
+    #[test]
+    fn set_and_serialize() {
+        let mut config = ProjectConfig::default();
+        config.set(ProjectKey::Client, "ACME").unwrap();
+        config.set(ProjectKey::Rate, "25.5").unwrap();
+        config.set(ProjectKey::WeeklyHours, "40").unwrap();
+        config.set(ProjectKey::YearlyFiles, "true").unwrap();
+        config.set(ProjectKey::Archived, "false").unwrap();
+        assert!(!config.is_archived());
+        assert!(config.set(ProjectKey::Rate, "a lot").is_err());
+        assert!(config.set(ProjectKey::YearlyFiles, "yes").is_err());
+        for hours in ["nan", "inf", "-1", "200"] {
+            assert!(
+                config.set(ProjectKey::WeeklyHours, hours).is_err(),
+                "{hours}"
+            );
+        }
+
+        let content = toml::to_string(&config).unwrap();
+        assert_eq!(
+            content,
+            "client = \"ACME\"\nrate = 25.5\nweekly-hours = 40.0\nyearly-files = true\narchived = false\n"
+        );
+        assert_eq!(toml::from_str::<ProjectConfig>(&content).unwrap(), config);
+
+        config.unset(ProjectKey::Rate);
+        assert_eq!(config.rate, None);
+    }
+}
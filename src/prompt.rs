@@ -0,0 +1,47 @@
+use std::io::{self, Write};
+
+use anyhow::{Context, Result, bail};
+use chrono::FixedOffset;
+use clockin::{cli::OverlapPolicy, log, parser::MaybeFinishedSessionTZ};
+
+pub fn confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("error while reading answer")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Asks which side to keep when a session of the other copy overlaps with sessions of ours.
+pub fn ask_overlap(
+    project: &str,
+    ours: &[&MaybeFinishedSessionTZ<FixedOffset>],
+    theirs: &MaybeFinishedSessionTZ<FixedOffset>,
+) -> Result<OverlapPolicy> {
+    println!("this copy:");
+    for session in ours {
+        println!("  {}", log::fmt_session((*session).clone(), project));
+    }
+    println!("the other copy:");
+    println!("  {}", log::fmt_session(theirs.clone(), project));
+    loop {
+        print!("keep [o]urs, [t]heirs or [b]oth? ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if io::stdin()
+            .read_line(&mut answer)
+            .context("error while reading answer")?
+            == 0
+        {
+            bail!("no answer, use --overlaps to merge without asking");
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "o" | "ours" => return Ok(OverlapPolicy::Ours),
+            "t" | "theirs" => return Ok(OverlapPolicy::Theirs),
+            "b" | "both" => return Ok(OverlapPolicy::Both),
+            _ => {}
+        }
+    }
+}
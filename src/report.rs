@@ -0,0 +1,208 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    ops::Bound,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{Datelike, FixedOffset, Local, NaiveDate};
+use itertools::Itertools;
+
+use crate::{
+    binnacle_2::{self, BinnacleData},
+    config,
+    format_util::{escape_html, fmt_duration, fmt_month, fmt_weekday},
+    i18n::catalog,
+    parser,
+    summary::{self, Summary},
+    writer::ReportOutput,
+};
+
+const STYLE: &str = "
+body { font-family: sans-serif; max-width: 48em; margin: 2em auto; color: #222; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { text-align: left; padding: 0.2em 0.6em; border-bottom: 1px solid #ddd; }
+td.time { font-variant-numeric: tabular-nums; white-space: nowrap; }
+td.chart { width: 50%; }
+.bar { background: #4a7bd0; height: 0.8em; }
+";
+
+/// Cell with a bar as long as the fraction of the largest value.
+fn bar(duration: Duration, max: Duration) -> String {
+    let percentage = if max.is_zero() {
+        0.0
+    } else {
+        100.0 * duration.as_secs_f64() / max.as_secs_f64()
+    };
+    format!("<td class=\"chart\"><div class=\"bar\" style=\"width: {percentage:.1}%\"></div></td>")
+}
+
+/// Self-contained page with the totals of each month, each sub-project and each day.
+pub fn format_html(
+    summary: &Summary,
+    data: &BinnacleData,
+    (from, to): (NaiveDate, NaiveDate),
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let catalog = catalog();
+    let total: Duration = data.months.iter().map(|m| m.total_time).sum();
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", catalog.report_title)?;
+    writeln!(out, "<style>{STYLE}</style>\n</head>\n<body>")?;
+    writeln!(out, "<h1>{}</h1>", catalog.report_title)?;
+    writeln!(
+        out,
+        "<p>{} {} {} {}, {} {} {}</p>",
+        catalog.from,
+        from.format(catalog.date_format),
+        catalog.to,
+        to.format(catalog.date_format),
+        catalog.total.to_lowercase(),
+        fmt_duration(&total),
+        catalog.hours_abbreviation
+    )?;
+
+    writeln!(out, "<h2>{}</h2>\n<table>", catalog.months_heading)?;
+    writeln!(
+        out,
+        "<tr><th>{}</th><th>{}</th><th></th></tr>",
+        catalog.month, catalog.hours
+    )?;
+    let max = data
+        .months
+        .iter()
+        .map(|m| m.total_time)
+        .max()
+        .unwrap_or_default();
+    for month in &data.months {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"time\">{}</td>{}</tr>",
+            fmt_month(month.id),
+            fmt_duration(&month.total_time),
+            bar(month.total_time, max)
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    let mut sub_projects = BTreeMap::<&[String], Duration>::new();
+    for sub_project in data
+        .months
+        .iter()
+        .flat_map(|m| &m.days)
+        .flat_map(|d| &d.sub_projects)
+    {
+        *sub_projects.entry(&sub_project.path).or_default() += sub_project.info.total_time;
+    }
+    writeln!(out, "<h2>{}</h2>\n<table>", catalog.sub_projects)?;
+    writeln!(
+        out,
+        "<tr><th>{}</th><th>{}</th><th></th></tr>",
+        catalog.sub_project, catalog.hours
+    )?;
+    for (path, time) in &sub_projects {
+        writeln!(
+            out,
+            "<tr><td style=\"padding-left: {}em\">{}</td><td class=\"time\">{}</td>{}</tr>",
+            0.6 + 1.5 * (path.len() - 1) as f64,
+            escape_html(path.last().unwrap()),
+            fmt_duration(time),
+            bar(*time, total)
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    writeln!(out, "<h2>{}</h2>\n<table>", catalog.days)?;
+    writeln!(
+        out,
+        "<tr><th>{}</th><th>{}</th><th></th></tr>",
+        catalog.date, catalog.hours
+    )?;
+    let days = summary.days.range(from..=to);
+    let max = days
+        .clone()
+        .map(|(_, d)| d.duration)
+        .max()
+        .unwrap_or_default();
+    for (date, day) in days {
+        writeln!(
+            out,
+            "<tr><td>{} {}</td><td class=\"time\">{}</td>{}</tr>",
+            fmt_weekday(date.weekday()),
+            date.format(catalog.date_format),
+            fmt_duration(&day.duration),
+            bar(day.duration, max)
+        )?;
+    }
+    writeln!(out, "</table>\n</body>\n</html>")
+}
+
+/// Writes the page of `clockin report html`.
+pub fn write_html(
+    path: &Path,
+    (from, to): (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let sessions = parser::sessions_in_range(path, (from, to), &timezone)?.collect_vec();
+    let summary = Summary::summarize(sessions.clone().into_iter(), &timezone);
+    let data = binnacle_2::process(sessions.into_iter(), from, to, &timezone);
+
+    let today = Local::now().with_timezone(&timezone).date_naive();
+    let first = *summary.days.keys().next().unwrap_or(&today);
+    let last = *summary.days.keys().last().unwrap_or(&today);
+    let dates = summary::resolve_dates((from, to), first, last);
+    let mut out = ReportOutput::new(output);
+    format_html(&summary, &data, dates, &mut out)?;
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use chrono::{NaiveDate, Utc};
+
+    use super::format_html;
+    use crate::{binnacle_2, summary::Summary, testing::session};
+
+    #[test]
+    fn html() {
+        let sessions = [
+            session("2000-01-03T10:00:00Z")
+                .end("2000-01-03T14:00:00Z")
+                .description("backend/api: auth")
+                .build(),
+            session("2000-01-04T10:00:00Z")
+                .end("2000-01-04T12:00:00Z")
+                .description("<ui>: form")
+                .build(),
+        ];
+        let summary = Summary::summarize(sessions.clone().into_iter(), &Utc);
+        let data = binnacle_2::process(
+            sessions.into_iter(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+            &Utc,
+        );
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+
+        let mut out = vec![];
+        format_html(&summary, &data, (date(1), date(31)), &mut out).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.contains("<p>Del 01/01/2000 al 31/01/2000, total 06:00:00 hs</p>"));
+        assert!(html.contains(
+            "<tr><td>Enero 2000</td><td class=\"time\">06:00:00</td>\
+             <td class=\"chart\"><div class=\"bar\" style=\"width: 100.0%\"></div></td></tr>"
+        ));
+        // nested sub-projects are indented under their parent
+        assert!(html.contains("<td style=\"padding-left: 2.1em\">api</td>"));
+        assert!(html.contains("&lt;ui&gt;"));
+        assert!(html.contains("<tr><td>Martes 04/01/2000</td><td class=\"time\">02:00:00</td>"));
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use chrono::TimeDelta;
+
+use crate::{
+    cli::{Rounding, RoundingMode, RoundingScope},
+    parser::Session,
+};
+
+pub fn round(duration: TimeDelta, rounding: &Rounding) -> TimeDelta {
+    let step = rounding.step.num_seconds();
+    let seconds = duration.num_seconds();
+    let steps = match rounding.mode {
+        RoundingMode::Up => seconds.div_euclid(step) + (seconds.rem_euclid(step) > 0) as i64,
+        RoundingMode::Nearest => (seconds + step / 2).div_euclid(step),
+        RoundingMode::Down => seconds.div_euclid(step),
+    };
+    TimeDelta::seconds(steps * step)
+}
+
+pub fn round_std(duration: Duration, rounding: &Rounding) -> Duration {
+    round(TimeDelta::from_std(duration).unwrap(), rounding)
+        .to_std()
+        .unwrap()
+}
+
+/// Moves the end of the session so its duration is rounded.
+pub fn round_session(session: Session, rounding: &Rounding) -> Session {
+    let duration = round(session.duration(), rounding);
+    session.with_duration(duration)
+}
+
+/// Rounds each session when rounding per session, days are rounded by the reports themselves.
+pub fn round_sessions(
+    sessions: impl Iterator<Item = Session>,
+    round: Option<Rounding>,
+    per: RoundingScope,
+) -> impl Iterator<Item = Session> {
+    let round = round.filter(|_| matches!(per, RoundingScope::Session));
+    sessions.map(move |s| match &round {
+        Some(round) => round_session(s, round),
+        None => s,
+    })
+}
+
+pub fn day_rounding(round: Option<Rounding>, per: RoundingScope) -> Option<Rounding> {
+    round.filter(|_| matches!(per, RoundingScope::Day))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::{round, round_session};
+    use crate::{
+        cli::{Rounding, RoundingMode},
+        testing::{session, time},
+    };
+
+    #[test]
+    fn rounding() {
+        let quarter = |mode| Rounding {
+            step: TimeDelta::minutes(15),
+            mode,
+        };
+        let minutes = TimeDelta::minutes;
+
+        assert_eq!(round(minutes(16), &quarter(RoundingMode::Up)), minutes(30));
+        assert_eq!(round(minutes(30), &quarter(RoundingMode::Up)), minutes(30));
+        assert_eq!(
+            round(minutes(22), &quarter(RoundingMode::Nearest)),
+            minutes(15)
+        );
+        assert_eq!(
+            round(minutes(23), &quarter(RoundingMode::Nearest)),
+            minutes(30)
+        );
+        assert_eq!(
+            round(minutes(29), &quarter(RoundingMode::Down)),
+            minutes(15)
+        );
+        let session = session("2000-01-01T10:00:00Z")
+            .end("2000-01-01T11:10:00Z")
+            .pause("2000-01-01T10:50:00Z", "2000-01-01T11:05:00Z")
+            .build();
+        let down = round_session(session.clone(), &quarter(RoundingMode::Down));
+        assert_eq!(down.end, time("2000-01-01T10:45:00Z"));
+        assert!(down.pauses.is_empty());
+        let up = round_session(session, &quarter(RoundingMode::Up));
+        assert_eq!(up.duration(), minutes(60));
+        assert_eq!(up.end, time("2000-01-01T11:15:00Z"));
+    }
+}
@@ -0,0 +1,167 @@
+use std::{collections::BTreeSet, fmt, str::FromStr, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::parse_time;
+
+/// Time expected to be worked on each weekday, written like `mon-fri 8h, sat 4h`. Days that are
+/// not mentioned are free, and so are the holidays.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Schedule {
+    /// indexed by the number of days from monday
+    days: [Duration; 7],
+    /// not part of the written form, they are kept apart in the project settings
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl Schedule {
+    /// A schedule spreading the weekly hours from monday to friday.
+    pub fn from_weekly_hours(hours: f64) -> Self {
+        let day = Duration::from_secs_f64(hours * 3600.0 / 5.0);
+        let mut days = [Duration::ZERO; 7];
+        days[..5].fill(day);
+        Schedule {
+            days,
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_holidays(self, holidays: BTreeSet<NaiveDate>) -> Self {
+        Schedule { holidays, ..self }
+    }
+
+    pub fn expected(&self, weekday: Weekday) -> Duration {
+        self.days[weekday.num_days_from_monday() as usize]
+    }
+
+    pub fn expected_on(&self, date: NaiveDate) -> Duration {
+        if self.holidays.contains(&date) {
+            Duration::ZERO
+        } else {
+            self.expected(date.weekday())
+        }
+    }
+
+    pub fn is_workday(&self, date: NaiveDate) -> bool {
+        !self.expected_on(date).is_zero()
+    }
+}
+
+fn parse_weekday(text: &str) -> Result<Weekday> {
+    text.trim()
+        .parse()
+        .ok()
+        .with_context(|| format!("invalid weekday {text:?}"))
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut schedule = Schedule::default();
+        for entry in s.split(',').filter(|e| !e.trim().is_empty()) {
+            let (days, time) = entry
+                .trim()
+                .split_once(char::is_whitespace)
+                .with_context(|| format!("invalid schedule {entry:?}, expected like mon-fri 8h"))?;
+            let time = parse_time(time)?;
+            let (first, last) = match days.split_once('-') {
+                Some((first, last)) => (parse_weekday(first)?, parse_weekday(last)?),
+                None => (parse_weekday(days)?, parse_weekday(days)?),
+            };
+            if first.days_since(Weekday::Mon) > last.days_since(Weekday::Mon) {
+                bail!("invalid range of weekdays {days:?}, weeks start on monday");
+            }
+            let mut day = first;
+            loop {
+                schedule.days[day.num_days_from_monday() as usize] = time;
+                if day == last {
+                    break;
+                }
+                day = day.succ();
+            }
+        }
+        Ok(schedule)
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+fn weekday_name(weekday: Weekday) -> String {
+    weekday.to_string().to_lowercase()
+}
+
+/// Consecutive days with the same time are written as a range.
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entries = vec![];
+        let mut index = 0;
+        while index < 7 {
+            let time = self.days[index];
+            let mut last = index;
+            while last + 1 < 7 && self.days[last + 1] == time {
+                last += 1;
+            }
+            if !time.is_zero() {
+                let first = Weekday::try_from(index as u8).unwrap();
+                let hours = time.as_secs_f64() / 3600.0;
+                entries.push(if last == index {
+                    format!("{} {hours}h", weekday_name(first))
+                } else {
+                    let last = Weekday::try_from(last as u8).unwrap();
+                    format!("{}-{} {hours}h", weekday_name(first), weekday_name(last))
+                });
+            }
+            index = last + 1;
+        }
+        write!(f, "{}", entries.join(", "))
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{NaiveDate, Weekday};
+
+    use super::Schedule;
+
+    #[test]
+    fn schedule() {
+        let schedule: Schedule = "mon-fri 8h, sat 4.5h".parse().unwrap();
+        assert_eq!(
+            schedule.expected(Weekday::Wed),
+            Duration::from_secs(8 * 3600)
+        );
+        assert_eq!(
+            schedule.expected(Weekday::Sat),
+            Duration::from_secs(4 * 3600 + 1800)
+        );
+        assert_eq!(schedule.expected(Weekday::Sun), Duration::ZERO);
+        assert_eq!(schedule.to_string(), "mon-fri 8h, sat 4.5h");
+
+        assert_eq!(Schedule::from_weekly_hours(40.0).to_string(), "mon-fri 8h");
+        let christmas = NaiveDate::from_ymd_opt(2000, 12, 25).unwrap();
+        let schedule = schedule.with_holidays([christmas].into());
+        assert!(!schedule.is_workday(christmas));
+        assert!(schedule.is_workday(christmas.succ_opt().unwrap()));
+
+        assert!("fri-mon 8h".parse::<Schedule>().is_err());
+        assert!("mon 8".parse::<Schedule>().is_err());
+    }
+}
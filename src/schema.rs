@@ -0,0 +1,58 @@
+use serde::Serialize;
+
+/// Version of the JSON outputs, raised whenever a field changes meaning or goes away.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON Schema of every JSON output.
+pub const SCHEMA: &str = include_str!("schema.json");
+
+/// A JSON output along with the version of the schema it follows.
+#[derive(Debug, Serialize)]
+pub struct Versioned<T> {
+    schema_version: u32,
+    #[serde(flatten)]
+    data: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn new(data: T) -> Self {
+        Versioned {
+            schema_version: SCHEMA_VERSION,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use serde_json::Value;
+
+    use super::{SCHEMA, SCHEMA_VERSION, Versioned};
+    use crate::status::{State, Status};
+
+    #[test]
+    fn schema() {
+        let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+        assert_eq!(schema["$defs"]["schema_version"]["const"], SCHEMA_VERSION);
+
+        let status = Status {
+            project: "p".to_owned(),
+            state: State::Running,
+            start: Some(DateTime::parse_from_rfc3339("2000-01-01T10:00:00Z").unwrap()),
+            elapsed_seconds: Some(60),
+            description: Some(String::new()),
+        };
+        let status = serde_json::to_value(Versioned::new(status)).unwrap();
+        let mut fields: Vec<_> = status.as_object().unwrap().keys().collect();
+        let mut required: Vec<_> = schema["$defs"]["status"]["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field.as_str().unwrap())
+            .collect();
+        fields.sort();
+        required.sort();
+        assert_eq!(fields, required);
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    io::{Cursor, Read},
+    ops::Bound,
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{FixedOffset, Local, NaiveDate};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response};
+
+use clockin::{
+    file, i18n, log, metrics,
+    parser::{self, SessionIteratorClosingExt},
+    status,
+    summary::{self, Summary},
+};
+
+use crate::control::{self, ControlError};
+
+const DASHBOARD: &str = include_str!("dashboard.html");
+/// Bodies only carry a description and a time, anything larger is rejected.
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
+
+fn json(status: u16, value: &impl Serialize) -> HttpResponse {
+    Response::from_string(serde_json::to_string(value).unwrap())
+        .with_status_code(status)
+        .with_header(content_type("application/json"))
+}
+
+/// The dashboard with its texts in the language of the reports.
+fn dashboard() -> String {
+    let catalog = i18n::catalog();
+    [
+        ("this_month", catalog.this_month),
+        ("running", catalog.running),
+        ("paused", catalog.paused),
+        ("stopped", catalog.stopped),
+        ("total", catalog.total),
+        ("no_sessions", catalog.no_sessions),
+    ]
+    .into_iter()
+    .fold(DASHBOARD.to_owned(), |page, (key, text)| {
+        page.replace(&format!("{{{{{key}}}}}"), text)
+    })
+}
+
+fn error(status: u16, message: impl ToString) -> HttpResponse {
+    #[derive(Serialize)]
+    struct Error {
+        error: String,
+    }
+    json(
+        status,
+        &Error {
+            error: message.to_string(),
+        },
+    )
+}
+
+/// The dates of the `from` and `to` parameters of the query, both included and optional.
+fn query_range(url: &str) -> Result<(Bound<NaiveDate>, Bound<NaiveDate>), String> {
+    let mut range = (Bound::Unbounded, Bound::Unbounded);
+    let Some((_, query)) = url.split_once('?') else {
+        return Ok(range);
+    };
+    for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+        let bound = match key {
+            "from" => &mut range.0,
+            "to" => &mut range.1,
+            _ => continue,
+        };
+        let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| format!("invalid date {value:?} for {key}, expected YYYY-MM-DD"))?;
+        *bound = Bound::Included(date);
+    }
+    Ok(range)
+}
+
+/// Whether the `Authorization` header carries the bearer token, compared in constant time.
+fn authorized(header: Option<&str>, token: &str) -> bool {
+    let Some(given) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// What the server allows besides reading the sessions.
+pub struct Options {
+    /// bearer token of the POST requests, which are rejected without one
+    pub token: Option<String>,
+    /// whether to expose the Prometheus metrics on `/metrics`
+    pub metrics: bool,
+}
+
+/// Answers the requests reading the sessions.
+fn query(
+    path: &Path,
+    timezone: &FixedOffset,
+    options: &Options,
+    url: &str,
+) -> Result<HttpResponse> {
+    let range = match query_range(url) {
+        Ok(range) => range,
+        Err(message) => return Ok(error(400, message)),
+    };
+    let endpoint = url.split_once('?').map_or(url, |(endpoint, _)| endpoint);
+
+    Ok(match endpoint {
+        "/" => {
+            Response::from_string(dashboard()).with_header(content_type("text/html; charset=utf-8"))
+        }
+        "/api/status" => json(200, &status::status(path)?),
+        "/api/summary" => {
+            let sessions = parser::parse_sessions(path)?
+                .into_iter()
+                .into_finished_now();
+            let summary = Summary::summarize(sessions, timezone);
+            let today = Local::now().with_timezone(timezone).date_naive();
+            json(200, &summary::report(&summary, range, today))
+        }
+        "/api/sessions" => {
+            let project = file::project_name(path)?;
+            let records: Vec<_> =
+                log::session_records(parser::parse_file(path)?, &project, range, timezone)
+                    .try_collect()?;
+            json(200, &records)
+        }
+        "/metrics" if options.metrics => {
+            let sessions = parser::parse_sessions(path)?
+                .into_iter()
+                .into_finished_now();
+            let summary = Summary::summarize(sessions, timezone);
+            let today = Local::now().with_timezone(timezone).date_naive();
+            let mut out = vec![];
+            metrics::format(&status::status(path)?, &summary, today, &mut out)?;
+            Response::from_data(out).with_header(content_type("text/plain; version=0.0.4"))
+        }
+        _ => error(404, "not found"),
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Body {
+    message: Option<String>,
+}
+
+/// Starts, stops or annotates the session, answering with the resulting status.
+fn control(path: &Path, url: &str, body: Body) -> Result<HttpResponse> {
+    let result = match url {
+        "/api/in" => control::clock_in(path, body.message.as_deref()),
+        "/api/out" => control::clock_out(path),
+        "/api/pause" => control::pause(path),
+        "/api/resume" => control::resume(path),
+        "/api/annotate" => match &body.message {
+            Some(message) => control::annotate(path, message),
+            None => return Ok(error(400, "missing message")),
+        },
+        _ => return Ok(error(404, "not found")),
+    };
+    match result {
+        Ok(()) => Ok(json(200, &status::status(path)?)),
+        Err(ControlError::Other(err)) => Err(err),
+        Err(err) => Ok(error(409, err)),
+    }
+}
+
+fn respond(
+    path: &Path,
+    timezone: &FixedOffset,
+    options: &Options,
+    request: &mut Request,
+) -> Result<HttpResponse> {
+    match request.method() {
+        Method::Get => return query(path, timezone, options, request.url()),
+        Method::Post => {}
+        _ => return Ok(error(405, "method not allowed")),
+    }
+
+    let Some(token) = &options.token else {
+        return Ok(error(403, "start the server with a token to allow changes"));
+    };
+    let header = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .map(|h| h.value.as_str());
+    if !authorized(header, token) {
+        return Ok(error(401, "invalid or missing bearer token"));
+    }
+
+    let mut content = String::new();
+    // one byte over the limit tells a body that is too large from one that just fits
+    request
+        .as_reader()
+        .take(MAX_BODY_SIZE + 1)
+        .read_to_string(&mut content)
+        .context("reading request body")?;
+    if content.len() as u64 > MAX_BODY_SIZE {
+        return Ok(error(413, "request body too large"));
+    }
+    let body = if content.trim().is_empty() {
+        Body::default()
+    } else {
+        match serde_json::from_str(&content) {
+            Ok(body) => body,
+            Err(err) => return Ok(error(400, format!("invalid body: {err}"))),
+        }
+    };
+    control(path, request.url(), body)
+}
+
+fn handle(path: &Path, timezone: &FixedOffset, options: &Options, mut request: Request) {
+    let response = respond(path, timezone, options, &mut request)
+        .unwrap_or_else(|err| error(500, format!("{err:#}")));
+    if let Err(err) = request.respond(response) {
+        eprintln!("unable to respond: {err}");
+    }
+}
+
+/// Serves the API and the dashboard until cancelled, reading the file on every request.
+pub fn serve(
+    path: PathBuf,
+    timezone: FixedOffset,
+    address: &str,
+    port: u16,
+    options: Options,
+    cancel: Receiver<()>,
+) -> Result<()> {
+    let server = tiny_http::Server::http((address, port))
+        .map_err(|err| anyhow!("unable to listen on {address}:{port}: {err}"))?;
+    eprintln!("listening on http://{address}:{port}");
+    while cancel.try_recv().is_err() {
+        if let Some(request) = server.recv_timeout(Duration::from_millis(200))? {
+            handle(&path, &timezone, &options, request);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use chrono::NaiveDate;
+
+    use super::{authorized, dashboard, query_range};
+
+    #[test]
+    fn query() {
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+        assert_eq!(
+            query_range("/api/summary"),
+            Ok((Bound::Unbounded, Bound::Unbounded))
+        );
+        assert_eq!(
+            query_range("/api/summary?to=2000-01-31&from=2000-01-01"),
+            Ok((Bound::Included(date(1)), Bound::Included(date(31))))
+        );
+        assert_eq!(
+            query_range("/api/sessions?from=2000-01-05&other=1"),
+            Ok((Bound::Included(date(5)), Bound::Unbounded))
+        );
+        assert!(query_range("/api/summary?from=january").is_err());
+    }
+
+    #[test]
+    fn dashboard_texts() {
+        let page = dashboard();
+        assert!(page.contains("<h2>Este mes</h2>"));
+        assert!(!page.contains("{{"));
+    }
+
+    #[test]
+    fn authorization() {
+        assert!(authorized(Some("Bearer secret"), "secret"));
+        assert!(!authorized(Some("Bearer secreT"), "secret"));
+        assert!(!authorized(Some("Bearer secret2"), "secret"));
+        assert!(!authorized(Some("secret"), "secret"));
+        assert!(!authorized(None, "secret"));
+    }
+}
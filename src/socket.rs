@@ -0,0 +1,284 @@
+use std::{
+    fs::{self, Permissions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::Receiver,
+    },
+    thread,
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+
+use clockin::{
+    file,
+    parser::{self, MaybeFinishedSessionTZ},
+    status::{self, Status},
+};
+
+use crate::control::{self, ControlError};
+
+/// A request per line, answered with a [`Response`] line.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase", deny_unknown_fields)]
+enum Request {
+    Status,
+    In { message: Option<String> },
+    Out,
+    Pause,
+    Resume,
+    Annotate { message: String },
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<Status>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Response {
+    fn error(message: impl ToString) -> Self {
+        Response {
+            ok: false,
+            status: None,
+            error: Some(message.to_string()),
+        }
+    }
+}
+
+struct CachedSession {
+    /// modification time and size of the file when it was parsed
+    version: (SystemTime, u64),
+    session: Option<MaybeFinishedSessionTZ<FixedOffset>>,
+}
+
+/// The last session of the project, parsed again only when the file changes.
+struct Daemon {
+    path: PathBuf,
+    project: String,
+    cache: Mutex<Option<CachedSession>>,
+}
+
+impl Daemon {
+    fn status(&self) -> Result<Status> {
+        let metadata = fs::metadata(&self.path).context("reading clockin file metadata")?;
+        let version = (metadata.modified()?, metadata.len());
+        let mut cache = self.cache.lock().unwrap();
+        let session = match &*cache {
+            Some(cached) if cached.version == version => cached.session.clone(),
+            _ => {
+                let session = parser::last_session(&self.path)?;
+                *cache = Some(CachedSession {
+                    version,
+                    session: session.clone(),
+                });
+                session
+            }
+        };
+        Ok(status::from_last_session(self.project.clone(), session))
+    }
+
+    fn respond(&self, line: &str) -> Response {
+        let request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(err) => return Response::error(format!("invalid request: {err}")),
+        };
+        let path = self.path.as_path();
+        let result = match request {
+            Request::Status => Ok(()),
+            Request::In { message } => control::clock_in(path, message.as_deref()),
+            Request::Out => control::clock_out(path),
+            Request::Pause => control::pause(path),
+            Request::Resume => control::resume(path),
+            Request::Annotate { message } => control::annotate(path, &message),
+        };
+        match result.map_err(|err| match err {
+            ControlError::Other(err) => err,
+            err => err.into(),
+        }) {
+            Ok(()) => match self.status() {
+                Ok(status) => Response {
+                    ok: true,
+                    status: Some(status),
+                    error: None,
+                },
+                Err(err) => Response::error(format!("{err:#}")),
+            },
+            Err(err) => Response::error(format!("{err:#}")),
+        }
+    }
+
+    fn handle(&self, stream: UnixStream) -> Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            serde_json::to_writer(&mut writer, &self.respond(&line))?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/clockin.sock`, or a per-user socket in the temporary directory.
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("clockin.sock"),
+        None => {
+            // SAFETY: getuid has no preconditions and can not fail
+            let uid = unsafe { libc::getuid() };
+            std::env::temp_dir().join(format!("clockin-{uid}.sock"))
+        }
+    }
+}
+
+/// Binds the socket, replacing it when it was left behind by a daemon that is not running.
+fn bind(socket: &Path) -> Result<UnixListener> {
+    match UnixListener::bind(socket) {
+        Err(err) if err.kind() == ErrorKind::AddrInUse => {
+            if UnixStream::connect(socket).is_ok() {
+                bail!("another daemon is listening on {}", socket.display());
+            }
+            fs::remove_file(socket).context("removing stale socket")?;
+            Ok(UnixListener::bind(socket)?)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Answers the requests on the socket until cancelled, each connection in its own thread.
+pub fn run(path: PathBuf, socket: &Path, cancel: Receiver<()>) -> Result<()> {
+    let listener = bind(socket).with_context(|| format!("binding {}", socket.display()))?;
+    fs::set_permissions(socket, Permissions::from_mode(0o600))?;
+    eprintln!("listening on {}", socket.display());
+
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = stop.clone();
+        let socket = socket.to_owned();
+        thread::spawn(move || {
+            let _ = cancel.recv();
+            stop.store(true, Ordering::SeqCst);
+            // wake up the listener so it notices
+            let _ = UnixStream::connect(socket);
+        });
+    }
+
+    let daemon = Arc::new(Daemon {
+        project: file::project_name(&path)?,
+        path,
+        cache: Mutex::new(None),
+    });
+    for stream in listener.incoming() {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("connection error: {err}");
+                continue;
+            }
+        };
+        let daemon = daemon.clone();
+        thread::spawn(move || {
+            if let Err(err) = daemon.handle(stream) {
+                eprintln!("connection error: {err:#}");
+            }
+        });
+    }
+    fs::remove_file(socket).context("removing socket")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixStream,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    use serde_json::Value;
+
+    use super::run;
+
+    #[test]
+    fn protocol() {
+        let dir = std::env::temp_dir().join(format!("clockin-socket-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // the commands read the configuration and write backups, keep them away from the user's
+        // SAFETY: the other tests of the binary only read the environment through std, which
+        // locks it
+        unsafe {
+            std::env::set_var("XDG_DATA_HOME", dir.join("data"));
+            std::env::set_var("XDG_CONFIG_HOME", dir.join("config"));
+        }
+        let path = dir.join("project");
+        fs::write(&path, "").unwrap();
+        let socket = dir.join("clockin.sock");
+
+        let (cancel, cancelled) = mpsc::channel();
+        let daemon = {
+            let (path, socket) = (path.clone(), socket.clone());
+            thread::spawn(move || run(path, &socket, cancelled).unwrap())
+        };
+        while !socket.exists() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let stream = UnixStream::connect(&socket).unwrap();
+        let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+        let mut request = |line: &str| -> Value {
+            writeln!(&stream, "{line}").unwrap();
+            serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap()
+        };
+
+        let response = request(r#"{"command": "status"}"#);
+        assert_eq!(response["status"]["state"], "stopped");
+        assert_eq!(response["status"]["project"], "project");
+
+        let response = request(r#"{"command": "in", "message": "work"}"#);
+        assert_eq!(response["ok"], true);
+        assert_eq!(response["status"]["state"], "running");
+        assert_eq!(response["status"]["description"], "work");
+
+        let response = request(r#"{"command": "in"}"#);
+        assert_eq!(response["ok"], false);
+        assert_eq!(
+            response["error"],
+            "a session is already running, clock out first"
+        );
+
+        assert_eq!(
+            request(r#"{"command": "pause"}"#)["status"]["state"],
+            "paused"
+        );
+        assert_eq!(
+            request(r#"{"command": "out"}"#)["status"]["state"],
+            "stopped"
+        );
+        assert_eq!(request(r#"{"command": "dance"}"#)["ok"], false);
+
+        cancel.send(()).unwrap();
+        daemon.join().unwrap();
+        assert!(!socket.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
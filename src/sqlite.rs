@@ -0,0 +1,241 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, TimeDelta, TimeZone};
+use rusqlite::{Connection, params};
+
+use crate::{
+    binnacle_body_parser,
+    parser::{NaiveSessionIteratorExt, Session, SessionIteratorExt},
+};
+
+const SCHEMA: &str = "
+    DROP TABLE IF EXISTS pauses;
+    DROP TABLE IF EXISTS sessions;
+    DROP TABLE IF EXISTS days;
+    DROP TABLE IF EXISTS sub_project_days;
+
+    CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY,
+        start TEXT NOT NULL,
+        end TEXT NOT NULL,
+        date TEXT NOT NULL,
+        duration_seconds INTEGER NOT NULL,
+        sub_project TEXT,
+        subject TEXT NOT NULL,
+        description TEXT NOT NULL
+    );
+    CREATE INDEX sessions_date ON sessions (date);
+
+    CREATE TABLE pauses (
+        session_id INTEGER NOT NULL REFERENCES sessions (id),
+        start TEXT NOT NULL,
+        end TEXT NOT NULL
+    );
+    CREATE INDEX pauses_session ON pauses (session_id);
+
+    CREATE TABLE days (
+        date TEXT PRIMARY KEY,
+        duration_seconds INTEGER NOT NULL
+    );
+
+    -- time of each sub-project includes the time of its children
+    CREATE TABLE sub_project_days (
+        date TEXT NOT NULL,
+        sub_project TEXT NOT NULL,
+        duration_seconds INTEGER NOT NULL,
+        PRIMARY KEY (date, sub_project)
+    );
+    CREATE INDEX sub_project_days_sub_project ON sub_project_days (sub_project);
+";
+
+/// Replaces the contents of the database with the sessions and their per day aggregates, days
+/// being split at midnight of the given timezone.
+pub fn write_database<Tz: TimeZone>(
+    conn: &mut Connection,
+    sessions: impl Iterator<Item = Session>,
+    timezone: &Tz,
+) -> Result<()> {
+    let sessions = sessions.collect::<Vec<_>>();
+    let tx = conn.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+
+    for session in &sessions {
+        let body = binnacle_body_parser::parse(&session.description)
+            .unwrap()
+            .to_owned();
+        let sub_project = body.sub_project_path().join("/");
+        tx.execute(
+            "INSERT INTO sessions
+                (start, end, date, duration_seconds, sub_project, subject, description)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                session.start.to_rfc3339(),
+                session.end.to_rfc3339(),
+                session
+                    .start
+                    .with_timezone(timezone)
+                    .date_naive()
+                    .to_string(),
+                session.duration().num_seconds(),
+                (!sub_project.is_empty()).then_some(sub_project),
+                body.subject,
+                session.description,
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        for pause in &session.pauses {
+            tx.execute(
+                "INSERT INTO pauses (session_id, start, end) VALUES (?1, ?2, ?3)",
+                params![id, pause.start.to_rfc3339(), pause.end.to_rfc3339()],
+            )?;
+        }
+    }
+
+    let mut days = BTreeMap::<NaiveDate, TimeDelta>::new();
+    let mut sub_project_days = BTreeMap::<(NaiveDate, String), TimeDelta>::new();
+    for session in sessions
+        .into_iter()
+        .with_timezone(timezone)
+        .naive_local()
+        .cut_at_days()
+    {
+        let date = session.start.date();
+        let duration = session.duration();
+        *days.entry(date).or_default() += duration;
+
+        let body = binnacle_body_parser::parse(&session.description)
+            .unwrap()
+            .to_owned();
+        let path = body.sub_project_path();
+        for depth in 1..=path.len() {
+            *sub_project_days
+                .entry((date, path[..depth].join("/")))
+                .or_default() += duration;
+        }
+    }
+    for (date, duration) in days {
+        tx.execute(
+            "INSERT INTO days (date, duration_seconds) VALUES (?1, ?2)",
+            params![date.to_string(), duration.num_seconds()],
+        )?;
+    }
+    for ((date, sub_project), duration) in sub_project_days {
+        tx.execute(
+            "INSERT INTO sub_project_days (date, sub_project, duration_seconds)
+                VALUES (?1, ?2, ?3)",
+            params![date.to_string(), sub_project, duration.num_seconds()],
+        )?;
+    }
+
+    Ok(tx.commit()?)
+}
+
+/// Reads back the sessions written by [`write_database`].
+pub fn read_database(conn: &Connection) -> Result<Vec<Session>> {
+    let parse = |text: String| {
+        DateTime::parse_from_rfc3339(&text).with_context(|| format!("invalid timestamp {text:?}"))
+    };
+
+    let mut pauses = conn.prepare("SELECT start, end FROM pauses WHERE session_id = ?1")?;
+    let mut sessions = conn.prepare("SELECT id, start, end, description FROM sessions")?;
+    let rows = sessions.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut result = vec![];
+    for row in rows {
+        let (id, start, end, description) = row?;
+        let session_pauses = pauses
+            .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .map(|pause| {
+                let (start, end) = pause?;
+                Ok(parse(start)?..parse(end)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        result.push(Session {
+            start: parse(start)?,
+            end: parse(end)?,
+            pauses: session_pauses,
+            description,
+            host: None,
+            id: None,
+        });
+    }
+    result.sort_by_key(|s| s.start);
+    Ok(result)
+}
+
+pub fn export<Tz: TimeZone>(
+    path: &Path,
+    sessions: impl Iterator<Item = Session>,
+    timezone: &Tz,
+) -> Result<()> {
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("error while opening {}", path.display()))?;
+    write_database(&mut conn, sessions, timezone)
+}
+
+pub fn import(path: &Path) -> Result<Vec<Session>> {
+    let conn = Connection::open(path)
+        .with_context(|| format!("error while opening {}", path.display()))?;
+    read_database(&conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use rusqlite::Connection;
+
+    use super::{read_database, write_database};
+    use crate::testing::session;
+
+    #[test]
+    fn round_trip() {
+        let sessions = vec![
+            session("2000-01-01T22:00:00+00:00")
+                .end("2000-01-02T02:00:00+00:00")
+                .pause("2000-01-01T23:00:00+00:00", "2000-01-02T00:30:00+00:00")
+                .description("backend/api: fix auth")
+                .build(),
+            session("2000-01-02T10:00:00-03:00")
+                .end("2000-01-02T11:00:00-03:00")
+                .description("backend: deploy")
+                .build(),
+        ];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        write_database(&mut conn, sessions.clone().into_iter(), &Utc).unwrap();
+        assert_eq!(read_database(&conn).unwrap(), sessions);
+
+        let days = conn
+            .prepare("SELECT date, duration_seconds FROM days")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<(String, i64)>, _>>()
+            .unwrap();
+        assert_eq!(
+            days,
+            [
+                ("2000-01-01".to_owned(), 3600),
+                ("2000-01-02".to_owned(), 3600 + 5400)
+            ]
+        );
+
+        let backend: i64 = conn
+            .query_row(
+                "SELECT duration_seconds FROM sub_project_days
+                    WHERE date = '2000-01-02' AND sub_project = 'backend'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(backend, 5400 + 3600);
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    io::{self, Write},
+    ops::Bound,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use chrono::{FixedOffset, NaiveDate, TimeDelta};
+
+use crate::{
+    config,
+    format_util::fmt_bar,
+    parser::{self, Session},
+    writer::ReportOutput,
+};
+
+/// Upper bound (exclusive) in minutes and label of each bucket of session lengths.
+const LENGTH_BUCKETS: [(i64, &str); 7] = [
+    (15, "<15m"),
+    (30, "15-30m"),
+    (60, "30-60m"),
+    (120, "1-2h"),
+    (240, "2-4h"),
+    (480, "4-8h"),
+    (i64::MAX, ">=8h"),
+];
+
+/// How many sessions fall in each bucket of lengths, shortest first.
+pub fn length_histogram(sessions: impl Iterator<Item = Session>) -> Vec<(&'static str, usize)> {
+    let mut counts = [0; LENGTH_BUCKETS.len()];
+    for session in sessions {
+        let duration = session.duration().max(TimeDelta::zero());
+        let bucket = LENGTH_BUCKETS
+            .iter()
+            .position(|(max, _)| duration.num_minutes() < *max)
+            .unwrap();
+        counts[bucket] += 1;
+    }
+    LENGTH_BUCKETS
+        .iter()
+        .zip(counts)
+        .map(|((_, label), count)| (*label, count))
+        .collect()
+}
+
+/// A line per bucket, bars are relative to the largest count.
+pub fn format_histogram(
+    histogram: &[(&str, usize)],
+    bar_width: usize,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let max = histogram.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    for (label, count) in histogram {
+        let fraction = if max == 0 {
+            0.0
+        } else {
+            *count as f64 / max as f64
+        };
+        writeln!(
+            out,
+            "{label:>6} {count:>5} {}",
+            fmt_bar(fraction, bar_width as f64)
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints the histogram of `clockin stats lengths`.
+pub fn print_lengths(
+    path: &Path,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    bar_width: usize,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let sessions = parser::sessions_in_range(path, range, &timezone)?;
+    let mut out = ReportOutput::new(output);
+    format_histogram(&length_histogram(sessions), bar_width, &mut out)?;
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::{format_histogram, length_histogram};
+    use crate::testing::session;
+
+    #[test]
+    fn lengths() {
+        let histogram = length_histogram([5, 14, 15, 90, 600].into_iter().map(|minutes| {
+            session("2000-01-01T10:00:00Z")
+                .lasting(TimeDelta::minutes(minutes))
+                .build()
+        }));
+        assert_eq!(
+            histogram,
+            [
+                ("<15m", 2),
+                ("15-30m", 1),
+                ("30-60m", 0),
+                ("1-2h", 1),
+                ("2-4h", 0),
+                ("4-8h", 0),
+                (">=8h", 1)
+            ]
+        );
+
+        let mut out = vec![];
+        format_histogram(&histogram[..2], 4, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "  <15m     2 ####\n15-30m     1 ##\n"
+        );
+    }
+}
@@ -0,0 +1,427 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use serde::Serialize;
+
+use crate::{
+    config, file, flexitime,
+    format_util::{fmt_duration, fmt_signed_duration},
+    i18n::catalog,
+    parser::{self, MaybeFinishedSessionTZ, SessionIteratorClosingExt},
+    project_config::ProjectConfig,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Stopped,
+    Running,
+    Paused,
+}
+
+impl State {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            State::Stopped => "stopped",
+            State::Running => "running",
+            State::Paused => "paused",
+        }
+    }
+}
+
+/// The last session of a project, for the tools that show it without parsing the output of
+/// `clockin status`.
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub project: String,
+    pub state: State,
+    /// the rest are only set while there is a session, running or paused
+    pub start: Option<DateTime<FixedOffset>>,
+    pub elapsed_seconds: Option<i64>,
+    pub description: Option<String>,
+}
+
+pub fn status(path: &Path) -> Result<Status> {
+    Ok(from_last_session(
+        file::project_name(path)?,
+        parser::last_session(path)?,
+    ))
+}
+
+/// The status of the project with the latest session, preferring the running or paused ones.
+pub fn status_of_all(project_files: &[PathBuf]) -> Result<Status> {
+    let mut last_sessions = vec![];
+    for path in project_files {
+        last_sessions.push((file::project_name(path)?, parser::last_session(path)?));
+    }
+    most_recent(last_sessions).context("there are no projects")
+}
+
+fn most_recent(
+    last_sessions: Vec<(String, Option<MaybeFinishedSessionTZ<FixedOffset>>)>,
+) -> Option<Status> {
+    last_sessions
+        .into_iter()
+        .max_by_key(|(_, session)| {
+            session.as_ref().map(|session| match session.end {
+                Some(end) => (false, end),
+                None => (true, session.start),
+            })
+        })
+        .map(|(project, session)| from_last_session(project, session))
+}
+
+/// The status given the last session of the project, with the time elapsed until now.
+pub fn from_last_session(
+    project: String,
+    last_session: Option<MaybeFinishedSessionTZ<FixedOffset>>,
+) -> Status {
+    let Some(session) = last_session.filter(|s| !s.is_finished()) else {
+        return Status {
+            project,
+            state: State::Stopped,
+            start: None,
+            elapsed_seconds: None,
+            description: None,
+        };
+    };
+
+    let state = if session.is_paused() {
+        State::Paused
+    } else {
+        State::Running
+    };
+    let session = session.into_finished_now();
+    Status {
+        project,
+        state,
+        start: Some(session.start),
+        elapsed_seconds: Some(session.duration().num_seconds()),
+        description: Some(session.description),
+    }
+}
+
+/// A short segment for shell prompts like `⏱ 01:12`, nothing while stopped.
+pub fn prompt(last_session: Option<MaybeFinishedSessionTZ<FixedOffset>>) -> Option<String> {
+    let session = last_session.filter(|s| !s.is_finished())?;
+    let icon = if session.is_paused() { '⏸' } else { '⏱' };
+    let minutes = session.into_finished_now().duration().num_minutes();
+    Some(format!("{icon} {:02}:{:02}", minutes / 60, minutes % 60))
+}
+
+/// A project of the data directory at a glance, for `clockin projects`.
+#[derive(Debug, PartialEq)]
+pub struct Overview {
+    pub project: String,
+    pub state: State,
+    /// start of the last session
+    pub last_session: Option<DateTime<FixedOffset>>,
+    /// of every session, the running one until now
+    pub total: Duration,
+}
+
+impl Overview {
+    pub fn new(project: String, sessions: Vec<MaybeFinishedSessionTZ<FixedOffset>>) -> Self {
+        let state = match sessions.last().filter(|s| !s.is_finished()) {
+            None => State::Stopped,
+            Some(session) if session.is_paused() => State::Paused,
+            Some(_) => State::Running,
+        };
+        let last_session = sessions.last().map(|s| s.start);
+        let total: TimeDelta = sessions
+            .into_iter()
+            .into_finished_now()
+            .map(|s| s.duration())
+            .sum();
+        Self {
+            project,
+            state,
+            last_session,
+            total: total.to_std().unwrap(),
+        }
+    }
+}
+
+pub fn overview(path: &Path) -> Result<Overview> {
+    Ok(Overview::new(
+        file::project_name(path)?,
+        parser::parse_sessions(path)?,
+    ))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Placeholder {
+    State,
+    Project,
+    Elapsed,
+    Start,
+    Description,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// A line like `{state} {elapsed} {project}` for the status bars that show whatever a command
+/// prints. Braces are written twice to show them as they are.
+#[derive(Debug, Clone)]
+pub struct Template(Vec<Part>);
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let Some((name, rest)) = chars.as_str().split_once('}') else {
+                        bail!("unclosed placeholder in template {s:?}");
+                    };
+                    let placeholder = match name {
+                        "state" => Placeholder::State,
+                        "project" => Placeholder::Project,
+                        "elapsed" => Placeholder::Elapsed,
+                        "start" => Placeholder::Start,
+                        "description" => Placeholder::Description,
+                        _ => bail!(
+                            "unknown placeholder {{{name}}}, expected {{state}}, {{project}}, {{elapsed}}, {{start}} or {{description}}"
+                        ),
+                    };
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                    parts.push(Part::Placeholder(placeholder));
+                    chars = rest.chars();
+                }
+                '}' => bail!("unmatched }} in template {s:?}, write }}}} to show it"),
+                c => text.push(c),
+            }
+        }
+        parts.push(Part::Text(text));
+        Ok(Template(parts))
+    }
+}
+
+/// The text between single quotes for `sh`, which leaves everything in them as it is except
+/// the quotes themselves.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+impl Template {
+    /// The session placeholders are left empty while stopped, the description shows only its
+    /// first line.
+    pub fn render(&self, status: &Status) -> String {
+        self.render_with(status, str::to_owned)
+    }
+
+    /// Like [`Template::render`] for a shell command, with every value quoted so a description
+    /// can't run commands of its own.
+    pub fn render_for_shell(&self, status: &Status) -> String {
+        self.render_with(status, shell_quote)
+    }
+
+    fn render_with(&self, status: &Status, value: impl Fn(&str) -> String) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Placeholder(placeholder) => value(&match placeholder {
+                    Placeholder::State => status.state.as_str().to_owned(),
+                    Placeholder::Project => status.project.clone(),
+                    Placeholder::Elapsed => status
+                        .elapsed_seconds
+                        .map(|seconds| fmt_duration(&Duration::from_secs(seconds.max(0) as u64)))
+                        .unwrap_or_default(),
+                    Placeholder::Start => status
+                        .start
+                        .map(|start| start.format("%H:%M").to_string())
+                        .unwrap_or_default(),
+                    Placeholder::Description => status
+                        .description
+                        .as_deref()
+                        .and_then(|description| description.lines().next())
+                        .unwrap_or_default()
+                        .to_owned(),
+                }),
+            })
+            .collect()
+    }
+}
+
+/// Prints the running session of the project and the flexitime balance, as in `clockin status`.
+/// Returns whether there is a running session.
+pub fn print_status(path: &Path, timezone: Option<FixedOffset>) -> Result<bool> {
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let balance = flexitime::project_balance(path, &ProjectConfig::load(path)?, &timezone)?;
+    let Some(session) = parser::last_session(path)?.filter(|s| !s.is_finished()) else {
+        println!("no running session");
+        if let Some(balance) = balance {
+            println!(
+                "{}: {}",
+                catalog().flexitime_balance.to_lowercase(),
+                fmt_signed_duration(&balance)
+            );
+        }
+        return Ok(false);
+    };
+
+    let paused = session.is_paused();
+    let session = session.into_finished_now();
+    let elapsed = session.duration().to_std().unwrap_or_default();
+    println!("session {}", if paused { "paused" } else { "running" });
+    println!(
+        "started: {}",
+        session
+            .start
+            .with_timezone(&timezone)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+    );
+    println!("elapsed: {}", fmt_duration(&elapsed));
+    if let Some(balance) = balance {
+        println!(
+            "{}: {}",
+            catalog().flexitime_balance.to_lowercase(),
+            fmt_signed_duration(&balance)
+        );
+    }
+    if !session.description.is_empty() {
+        println!("description:\n{}", session.description);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Overview, State, Status, Template, most_recent, prompt};
+    use crate::testing::{session, time};
+
+    #[test]
+    fn template() {
+        let mut status = Status {
+            project: "backend".to_owned(),
+            state: State::Running,
+            start: Some(time("2000-01-01T10:00:00Z")),
+            elapsed_seconds: Some(3725),
+            description: Some("fix auth\n\nmore details".to_owned()),
+        };
+        let template: Template = "{{{state}}} {elapsed} since {start} {project}: {description}"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            template.render(&status),
+            "{running} 01:02:05 since 10:00 backend: fix auth"
+        );
+
+        status.state = State::Stopped;
+        status.start = None;
+        status.elapsed_seconds = None;
+        status.description = None;
+        assert_eq!(template.render(&status), "{stopped}  since  backend: ");
+
+        status.description = Some("it's $(rm -rf ~)".to_owned());
+        assert_eq!(
+            "notify-send {description}"
+                .parse::<Template>()
+                .unwrap()
+                .render_for_shell(&status),
+            r"notify-send 'it'\''s $(rm -rf ~)'"
+        );
+
+        assert!("{elapsed".parse::<Template>().is_err());
+        assert!("{unknown}".parse::<Template>().is_err());
+        assert!("}".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn prompt_segment() {
+        let mut session = session("2000-01-01T08:00:00Z")
+            .paused_at("2000-01-01T09:12:59Z")
+            .maybe_finished();
+        assert_eq!(prompt(Some(session.clone())).unwrap(), "⏸ 01:12");
+        session.end = Some(time("2000-01-01T10:00:00Z"));
+        assert_eq!(prompt(Some(session)), None);
+        assert_eq!(prompt(None), None);
+    }
+
+    #[test]
+    fn most_recent_project() {
+        let finished = vec![
+            (
+                "a".to_owned(),
+                Some(
+                    session("2000-01-01T08:00:00Z")
+                        .end("2000-01-01T12:00:00Z")
+                        .maybe_finished(),
+                ),
+            ),
+            (
+                "b".to_owned(),
+                Some(
+                    session("2000-01-01T13:00:00Z")
+                        .end("2000-01-01T14:00:00Z")
+                        .maybe_finished(),
+                ),
+            ),
+            ("c".to_owned(), None),
+        ];
+        let status = most_recent(finished.clone()).unwrap();
+        assert_eq!(
+            (status.project.as_str(), status.state),
+            ("b", State::Stopped)
+        );
+
+        let mut running = finished;
+        running.push((
+            "d".to_owned(),
+            Some(session("2000-01-01T07:00:00Z").maybe_finished()),
+        ));
+        let status = most_recent(running).unwrap();
+        assert_eq!(
+            (status.project.as_str(), status.state),
+            ("d", State::Running)
+        );
+    }
+
+    #[test]
+    fn overview() {
+        let finished = session("2000-01-01T08:00:00Z")
+            .end("2000-01-01T10:00:00Z")
+            .maybe_finished();
+        let paused = session("2000-01-02T08:00:00Z")
+            .paused_at("2000-01-02T09:00:00Z")
+            .maybe_finished();
+        assert_eq!(
+            Overview::new("a".to_owned(), vec![finished.clone(), paused]),
+            Overview {
+                project: "a".to_owned(),
+                state: State::Paused,
+                last_session: Some(time("2000-01-02T08:00:00Z")),
+                total: Duration::from_secs(3 * 3600),
+            }
+        );
+        assert_eq!(
+            Overview::new("b".to_owned(), vec![finished]).state,
+            State::Stopped
+        );
+        assert_eq!(Overview::new("c".to_owned(), vec![]).last_session, None);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::Result;
+use chrono::{FixedOffset, Local, NaiveDate};
+
+use crate::{
+    config,
+    i18n::catalog,
+    parser::{self, SessionIteratorClosingExt},
+    project_config::ProjectConfig,
+    summary::Summary,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Streak {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+    pub days: usize,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Streaks {
+    /// the streak that is still going, today counts only once it reaches the minimum
+    pub current: Option<Streak>,
+    pub longest: Option<Streak>,
+}
+
+/// Streaks of consecutive days with at least `min` worked. Days rejected by `counted` (like
+/// the ones off the schedule) neither extend nor break a streak.
+pub fn streaks(
+    summary: &Summary,
+    min: Duration,
+    today: NaiveDate,
+    counted: impl Fn(NaiveDate) -> bool,
+) -> Streaks {
+    let Some(&first) = summary.days.keys().next() else {
+        return Streaks::default();
+    };
+
+    let mut streaks = Streaks::default();
+    for date in first.iter_days().take_while(|date| *date <= today) {
+        if !counted(date) {
+            continue;
+        }
+        let worked = summary
+            .days
+            .get(&date)
+            .map_or(Duration::ZERO, |d| d.duration);
+        if worked >= min {
+            let streak = streaks.current.get_or_insert(Streak {
+                first: date,
+                last: date,
+                days: 0,
+            });
+            streak.last = date;
+            streak.days += 1;
+            if streaks
+                .longest
+                .is_none_or(|longest| streak.days > longest.days)
+            {
+                streaks.longest = Some(*streak);
+            }
+        } else if date != today {
+            streaks.current = None;
+        }
+    }
+    streaks
+}
+
+/// Prints the current and longest streaks of `clockin streaks`, skipping the days off of the
+/// schedule with `workdays`.
+pub fn print_streaks(
+    path: &Path,
+    min_minutes: u64,
+    workdays: bool,
+    timezone: Option<FixedOffset>,
+) -> Result<()> {
+    let schedule = if workdays {
+        Some(ProjectConfig::load(path)?.schedule()?)
+    } else {
+        None
+    };
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now();
+    let summary = Summary::summarize(sessions, &timezone);
+    let today = Local::now().with_timezone(&timezone).date_naive();
+
+    let streaks = streaks(
+        &summary,
+        Duration::from_secs(min_minutes * 60),
+        today,
+        |date| schedule.as_ref().is_none_or(|s| s.is_workday(date)),
+    );
+    let catalog = catalog();
+    for (name, streak) in [
+        (catalog.current_streak, streaks.current),
+        (catalog.longest_streak, streaks.longest),
+    ] {
+        match streak {
+            Some(streak) => println!(
+                "{name}: {} {} ({} - {})",
+                streak.days,
+                catalog.days.to_lowercase(),
+                streak.first,
+                streak.last
+            ),
+            None => println!("{name}: 0 {}", catalog.days.to_lowercase()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{Datelike, NaiveDate, TimeDelta, Utc, Weekday};
+
+    use super::{Streak, streaks};
+    use crate::{summary::Summary, testing::session};
+
+    #[test]
+    fn streaks_of_days() {
+        // worked from monday 3 to thursday 13 except the weekend, a short day on friday 14
+        let summary = Summary::summarize(
+            [3, 4, 5, 6, 7, 10, 11, 12, 13]
+                .into_iter()
+                .map(|day| {
+                    session(&format!("2000-01-{day:02}T10:00:00Z"))
+                        .lasting(TimeDelta::hours(1))
+                        .build()
+                })
+                .chain([session("2000-01-14T10:00:00Z")
+                    .lasting(TimeDelta::minutes(10))
+                    .build()]),
+            &Utc,
+        );
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+        let min = Duration::from_secs(30 * 60);
+
+        let every_day = streaks(&summary, min, date(14), |_| true);
+        assert_eq!(
+            every_day.longest,
+            Some(Streak {
+                first: date(3),
+                last: date(7),
+                days: 5
+            })
+        );
+        // today is not over, so its short time does not break the streak yet
+        assert_eq!(
+            every_day.current,
+            Some(Streak {
+                first: date(10),
+                last: date(13),
+                days: 4
+            })
+        );
+
+        let workdays = streaks(&summary, min, date(17), |date| {
+            !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+        });
+        assert_eq!(workdays.longest.unwrap().days, 9);
+        assert_eq!(workdays.current, None);
+    }
+}
@@ -1,26 +1,51 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process,
     sync::mpsc::{self, Receiver},
     thread,
     time::Duration,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use notify::{
     EventKind, RecursiveMode,
-    event::{AccessKind, AccessMode},
+    event::{AccessKind, AccessMode, ModifyKind},
 };
 use notify_debouncer_full::new_debouncer;
 
-use crate::parser;
+use serde::Serialize;
 
-fn watch_file(path: &PathBuf, mut f: impl FnMut(), cancel: Receiver<()>) -> Result<()> {
+use clockin::{
+    cli::SubscribeFormat,
+    file,
+    format_util::fmt_duration,
+    schema::Versioned,
+    status::{self, State, Status, Template},
+};
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Calls `f` every time the project file or one of its yearly files is written, until cancelled.
+pub fn watch_file(path: &Path, f: impl FnMut(), cancel: Receiver<()>) -> Result<()> {
+    watch(
+        path.parent().context("unable to find path parent")?,
+        |written| file::is_session_file(path, written),
+        f,
+        cancel,
+    )
+}
+
+/// Calls `f` every time a file in the directory accepted by `is_watched` is written, until
+/// cancelled.
+fn watch(
+    dir: &Path,
+    is_watched: impl Fn(&Path) -> bool,
+    mut f: impl FnMut(),
+    cancel: Receiver<()>,
+) -> Result<()> {
     let (tx, rx) = mpsc::channel();
     let mut debouncer = new_debouncer(Duration::from_millis(200), None, tx)?;
-    debouncer.watch(
-        path.parent().context("unable to find path parent")?,
-        RecursiveMode::Recursive,
-    )?;
+    debouncer.watch(dir, RecursiveMode::Recursive)?;
 
     thread::spawn(move || {
         cancel.recv().unwrap();
@@ -35,11 +60,13 @@ fn watch_file(path: &PathBuf, mut f: impl FnMut(), cancel: Receiver<()>) -> Resu
                 });
                 event
                     .into_iter()
-                    .filter(|e| e.paths.contains(path))
+                    .filter(|e| e.paths.iter().any(|path| is_watched(path)))
                     .filter(|e| {
+                        // the rewrites replace the file by renaming another one over it
                         matches!(
                             e.kind,
                             EventKind::Access(AccessKind::Close(AccessMode::Write))
+                                | EventKind::Modify(ModifyKind::Name(_))
                         )
                     })
                     .for_each(|_e| f());
@@ -51,30 +78,282 @@ fn watch_file(path: &PathBuf, mut f: impl FnMut(), cancel: Receiver<()>) -> Resu
     Ok(())
 }
 
-enum SessionStatus {
-    Finished,
-    Started,
+/// A line like `started 01:23 — backend: fix auth`, with the start of the session and the first
+/// line of its description.
+fn text(status: &Status) -> String {
+    let event = match status.state {
+        State::Running => "started",
+        State::Paused => "paused",
+        State::Stopped => "finished",
+    };
+    let mut line = match status.start {
+        Some(start) => format!("{event} {} — {}", start.format("%H:%M"), status.project),
+        None => format!("{event} — {}", status.project),
+    };
+    if let Some(description) = status
+        .description
+        .as_deref()
+        .and_then(|description| description.lines().next())
+        .filter(|line| !line.is_empty())
+    {
+        line.push_str(": ");
+        line.push_str(description);
+    }
+    line
+}
+
+/// What `subscribe` prints on every change.
+#[derive(Debug, Clone)]
+pub enum Output {
+    Text,
+    Json,
+    Waybar,
+    Plain(Template),
+}
+
+impl Output {
+    pub fn new(format: SubscribeFormat, template: &str) -> Result<Self> {
+        Ok(match format {
+            SubscribeFormat::Text => Output::Text,
+            SubscribeFormat::Json => Output::Json,
+            SubscribeFormat::Waybar => Output::Waybar,
+            SubscribeFormat::Plain => Output::Plain(template.parse()?),
+        })
+    }
+
+    /// Whether the output shows the elapsed time, which has to be printed again as it goes by.
+    fn ticks(&self) -> bool {
+        matches!(self, Output::Waybar | Output::Plain(_))
+    }
+}
+
+fn print_status(status: &Status, output: &Output) {
+    match output {
+        Output::Text => println!("{}", text(status)),
+        Output::Json => println!(
+            "{}",
+            serde_json::to_string(&Versioned::new(status)).unwrap()
+        ),
+        Output::Waybar => println!("{}", serde_json::to_string(&Waybar::new(status)).unwrap()),
+        Output::Plain(template) => println!("{}", template.render(status)),
+    }
+}
+
+/// What `subscribe` follows.
+#[derive(Debug, Clone)]
+pub enum Source {
+    Project(PathBuf),
+    /// every project in the data directory, shown as the one with the latest session
+    All,
+}
+
+impl Source {
+    fn status(&self) -> Result<Status> {
+        match self {
+            Source::Project(path) => status::status(path),
+            Source::All => status::status_of_all(&file::list_project_files()?),
+        }
+    }
+
+    fn print(&self, output: &Output) {
+        match self.status() {
+            Ok(status) => print_status(&status, output),
+            Err(err) => eprintln!("error: {err:#}"),
+        }
+    }
+
+    fn watch(&self, f: impl FnMut(), cancel: Receiver<()>) -> Result<()> {
+        match self {
+            Source::Project(path) => watch_file(path, f, cancel),
+            Source::All => watch(
+                &file::get_data_dir(),
+                |written| {
+                    file::list_project_files().is_ok_and(|projects| {
+                        projects
+                            .iter()
+                            .any(|project| file::is_session_file(project, written))
+                    })
+                },
+                f,
+                cancel,
+            ),
+        }
+    }
+}
+
+/// A shell command run when the state of the project changes, its placeholders are replaced like
+/// in the templates but quoted, and the event is described by `CLOCKIN_*` environment variables.
+pub struct Hook {
+    command: Template,
+    state: Option<State>,
+}
+
+impl Hook {
+    pub fn new(command: &str) -> Result<Self> {
+        Ok(Hook {
+            command: command.parse()?,
+            state: None,
+        })
+    }
+
+    fn environment(status: &Status, previous: State) -> Vec<(&'static str, String)> {
+        vec![
+            ("CLOCKIN_STATE", status.state.as_str().to_owned()),
+            ("CLOCKIN_PREVIOUS_STATE", previous.as_str().to_owned()),
+            ("CLOCKIN_PROJECT", status.project.clone()),
+            (
+                "CLOCKIN_START",
+                status.start.map(|s| s.to_rfc3339()).unwrap_or_default(),
+            ),
+            (
+                "CLOCKIN_ELAPSED_SECONDS",
+                status
+                    .elapsed_seconds
+                    .map(|s| s.to_string())
+                    .unwrap_or_default(),
+            ),
+            (
+                "CLOCKIN_DESCRIPTION",
+                status.description.clone().unwrap_or_default(),
+            ),
+        ]
+    }
+
+    /// Runs the command if the state is not the same as last time, the first call only takes
+    /// note of it.
+    fn on_change(&mut self, source: &Source) -> Result<()> {
+        let status = source.status()?;
+        let previous = self.state.replace(status.state);
+        let Some(previous) = previous.filter(|previous| *previous != status.state) else {
+            return Ok(());
+        };
+        let exit = process::Command::new("sh")
+            .arg("-c")
+            .arg(self.command.render_for_shell(&status))
+            .envs(Hook::environment(&status, previous))
+            .status()
+            .context("unable to run the hook")?;
+        if !exit.success() {
+            bail!("the hook failed with {exit}");
+        }
+        Ok(())
+    }
 }
 
-fn get_last_session_status(path: &PathBuf) -> Result<SessionStatus> {
-    let parser = parser::parse_file(path)?;
-    let was_last_session_finished = parser.last().map(|s| s.is_finished()).unwrap_or(true);
+/// Output of a Waybar custom module with `"return-type": "json"`, the class is the state so it
+/// can be styled. The text is empty when stopped, which hides the module.
+#[derive(Serialize)]
+struct Waybar {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+}
 
-    Ok(if was_last_session_finished {
-        SessionStatus::Finished
-    } else {
-        SessionStatus::Started
-    })
+impl Waybar {
+    fn new(status: &Status) -> Self {
+        let elapsed = status
+            .elapsed_seconds
+            .map(|seconds| fmt_duration(&Duration::from_secs(seconds.max(0) as u64)));
+        let (text, mut tooltip) = match (elapsed, status.start) {
+            (Some(elapsed), Some(start)) => (
+                match status.state {
+                    State::Paused => format!("{elapsed} (paused)"),
+                    _ => elapsed.clone(),
+                },
+                format!(
+                    "{}: {} {elapsed}, started at {}",
+                    status.project,
+                    status.state.as_str(),
+                    start.format("%H:%M")
+                ),
+            ),
+            _ => (
+                String::new(),
+                format!("{}: no running session", status.project),
+            ),
+        };
+        if let Some(description) = status.description.as_deref().filter(|d| !d.is_empty()) {
+            tooltip.push('\n');
+            tooltip.push_str(description);
+        }
+        Waybar {
+            text,
+            tooltip,
+            class: status.state.as_str(),
+        }
+    }
 }
 
-fn print_last_session_status(path: &PathBuf) {
-    match get_last_session_status(path).unwrap() {
-        SessionStatus::Started => println!("started"),
-        SessionStatus::Finished => println!("finished"),
+pub fn subscribe(
+    source: Source,
+    output: Output,
+    mut hook: Option<Hook>,
+    cancel: Receiver<()>,
+) -> Result<()> {
+    let mut on_change = |source: &Source| {
+        if let Some(hook) = &mut hook
+            && let Err(err) = hook.on_change(source)
+        {
+            eprintln!("hook error: {err:#}");
+        }
+        source.print(&output);
+    };
+    on_change(&source);
+    if output.ticks() {
+        let source = source.clone();
+        let output = output.clone();
+        thread::spawn(move || {
+            loop {
+                thread::sleep(TICK_INTERVAL);
+                source.print(&output);
+            }
+        });
     }
+    source.watch(|| on_change(&source), cancel)
 }
 
-pub fn subscribe(path: &PathBuf, cancel: Receiver<()>) -> Result<()> {
-    print_last_session_status(path);
-    watch_file(path, || print_last_session_status(path), cancel)
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::{Hook, Waybar, text};
+    use clockin::status::{State, Status};
+
+    #[test]
+    fn output() {
+        let mut status = Status {
+            project: "p".to_owned(),
+            state: State::Running,
+            start: Some(DateTime::parse_from_rfc3339("2000-01-01T10:00:00Z").unwrap()),
+            elapsed_seconds: Some(3725),
+            description: Some("fix auth".to_owned()),
+        };
+        assert_eq!(text(&status), "started 10:00 — p: fix auth");
+        assert_eq!(
+            serde_json::to_string(&Waybar::new(&status)).unwrap(),
+            r#"{"text":"01:02:05","tooltip":"p: running 01:02:05, started at 10:00\nfix auth","class":"running"}"#
+        );
+
+        assert_eq!(
+            Hook::environment(&status, State::Paused),
+            [
+                ("CLOCKIN_STATE", "running".to_owned()),
+                ("CLOCKIN_PREVIOUS_STATE", "paused".to_owned()),
+                ("CLOCKIN_PROJECT", "p".to_owned()),
+                ("CLOCKIN_START", "2000-01-01T10:00:00+00:00".to_owned()),
+                ("CLOCKIN_ELAPSED_SECONDS", "3725".to_owned()),
+                ("CLOCKIN_DESCRIPTION", "fix auth".to_owned()),
+            ]
+        );
+
+        status.state = State::Stopped;
+        status.start = None;
+        status.elapsed_seconds = None;
+        status.description = None;
+        assert_eq!(
+            serde_json::to_string(&Waybar::new(&status)).unwrap(),
+            r#"{"text":"","tooltip":"p: no running session","class":"stopped"}"#
+        );
+        assert_eq!(text(&status), "finished — p");
+    }
 }
@@ -1,13 +1,41 @@
 use std::{
     cmp::Ordering,
     collections::{BTreeMap, HashSet},
-    ops::RangeBounds,
+    io::{self, Write},
+    ops::{Bound, RangeBounds},
+    path::{Path, PathBuf},
     time::Duration,
 };
 
-use chrono::{Datelike, Days, Months, NaiveDate, NaiveWeek, TimeZone};
+use anyhow::{Result, bail};
+use chrono::{
+    Datelike, Days, FixedOffset, IsoWeek, Local, Months, NaiveDate, NaiveWeek, TimeZone, Weekday,
+};
+use itertools::Itertools;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, de::Error};
+use serde::{Serialize, Serializer};
 
-use crate::parser::{NaiveSessionIteratorExt, Session, SessionIteratorExt};
+use crate::{
+    binnacle_2, binnacle_body_parser,
+    cli::{DateRange, Rounding, SummaryArgs, SummaryFormat, SummaryGrouping},
+    config::{self, Config},
+    file, flexitime,
+    format_util::{
+        fmt_duration, fmt_duration_uncertain, fmt_month, fmt_signed_duration, fmt_sparkline,
+        fmt_weekday,
+    },
+    i18n::catalog,
+    parser::{
+        self, NaiveSessionIteratorExt, Session, SessionIteratorClosingExt, SessionIteratorExt,
+        SessionMergingExt,
+    },
+    project_config::{self, ProjectConfig},
+    rounding,
+    schema::Versioned,
+    totals::{self, sessions_between, worked_time_by_day},
+    writer::ReportOutput,
+};
 
 #[derive(Debug, Clone, Copy, Eq)]
 pub struct FixedWeek(NaiveWeek);
@@ -16,6 +44,11 @@ impl FixedWeek {
     pub fn first_day(&self) -> NaiveDate {
         self.0.first_day()
     }
+
+    /// The ISO week sharing most of its days with the week, whatever day the week starts on.
+    pub fn iso_week(&self) -> IsoWeek {
+        (self.0.first_day() + Days::new(3)).iso_week()
+    }
 }
 
 impl PartialEq for FixedWeek {
@@ -67,9 +100,44 @@ impl MonthId {
     }
 }
 
+/// Serialized as `YYYY-MM`.
+impl Serialize for MonthId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{:04}-{:02}", self.year, self.month + 1))
+    }
+}
+
+/// Serializes a duration as whole seconds, for reports meant to be read by other programs.
+pub fn serialize_seconds<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+/// Parsed from `YYYY-MM`.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for MonthId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        NaiveDate::parse_from_str(&format!("{text}-01"), "%Y-%m-%d")
+            .map(|date| date.month_id())
+            .map_err(|_| D::Error::custom(format!("invalid month {text:?}, expected YYYY-MM")))
+    }
+}
+
+/// The counterpart of [`serialize_seconds`].
+#[cfg(feature = "serde")]
+pub fn deserialize_seconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    Ok(Duration::from_secs(u64::deserialize(deserializer)?))
+}
+
 pub trait NaiveDateExt {
     fn month_id(&self) -> MonthId;
     fn real_week(&self) -> FixedWeek;
+    fn week_starting(&self, start: Weekday) -> FixedWeek;
 }
 
 impl NaiveDateExt for NaiveDate {
@@ -80,16 +148,33 @@ impl NaiveDateExt for NaiveDate {
     }
 
     fn real_week(&self) -> FixedWeek {
-        FixedWeek(self.week(chrono::Weekday::Mon))
+        self.week_starting(Weekday::Mon)
+    }
+
+    fn week_starting(&self, start: Weekday) -> FixedWeek {
+        FixedWeek(self.week(start))
     }
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Day {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            rename = "duration_seconds",
+            serialize_with = "serialize_seconds",
+            deserialize_with = "deserialize_seconds"
+        )
+    )]
     pub duration: Duration,
     pub descriptions: Vec<String>,
+    /// only needed while summarizing, to skip repeated descriptions
+    #[cfg_attr(feature = "serde", serde(skip))]
     descriptions_set: HashSet<String>,
 }
 
+/// Worked time and descriptions of each day, see [`Summary::summarize`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Summary {
     pub days: BTreeMap<NaiveDate, Day>,
 }
@@ -104,6 +189,12 @@ impl Summary {
     pub fn week_duration(&self, week: FixedWeek) -> Duration {
         self.duration(week.0.first_day()..=week.0.last_day())
     }
+
+    pub fn round_days(&mut self, rounding: &Rounding) {
+        for day in self.days.values_mut() {
+            day.duration = rounding::round_std(day.duration, rounding);
+        }
+    }
 }
 
 impl Summary {
@@ -144,3 +235,672 @@ impl Summary {
         summary
     }
 }
+
+#[derive(Debug, Serialize)]
+pub struct DayReport {
+    pub date: NaiveDate,
+    pub weekday: Weekday,
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+    /// whether the day is over, so its duration can not change anymore
+    pub complete: bool,
+    pub descriptions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthReport {
+    pub month: MonthId,
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+    pub complete: bool,
+    pub days: Vec<DayReport>,
+}
+
+/// Worked time of one of the projects in a report spanning all of them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectTotal {
+    pub project: String,
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+}
+
+/// The JSON output of the summary, shaped like [`BinnacleData`](crate::BinnacleData).
+#[derive(Debug, Serialize)]
+pub struct MonthReports {
+    pub months: Vec<MonthReport>,
+    /// only with `--all-projects`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projects: Option<Vec<ProjectTotal>>,
+}
+
+/// The days of the summary in range grouped by month. Month totals always cover the whole month.
+pub fn report(
+    summary: &Summary,
+    range: impl RangeBounds<NaiveDate>,
+    current_date: NaiveDate,
+) -> Vec<MonthReport> {
+    summary
+        .days
+        .range(range)
+        .chunk_by(|(date, _day)| date.month_id())
+        .into_iter()
+        .map(|(month, days)| MonthReport {
+            month,
+            duration: summary.duration(month.first_day()..=month.last_day()),
+            complete: current_date > month.last_day(),
+            days: days
+                .map(|(&date, day)| DayReport {
+                    date,
+                    weekday: date.weekday(),
+                    duration: day.duration,
+                    complete: current_date > date,
+                    descriptions: day.descriptions.clone(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+struct CsvRow {
+    date: NaiveDate,
+    weekday: Weekday,
+    duration_seconds: u64,
+    descriptions: String,
+}
+
+/// One row per day, with the descriptions of the day joined by `; `.
+pub fn format_csv(months: &[MonthReport], out: &mut impl Write) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    for day in months.iter().flat_map(|m| &m.days) {
+        writer.serialize(CsvRow {
+            date: day.date,
+            weekday: day.weekday,
+            duration_seconds: day.duration.as_secs(),
+            descriptions: day.descriptions.join("; "),
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn format(
+    summary: &Summary,
+    range: impl RangeBounds<NaiveDate>,
+    current_date: NaiveDate,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut last_month = None;
+    for (date, day) in summary.days.range(range) {
+        let month = date.month_id();
+
+        let month_changed = last_month.is_none_or(|last_month| last_month != month);
+        if month_changed {
+            last_month = Some(month);
+            writeln!(
+                out,
+                "## {} ({})\n",
+                fmt_month(month),
+                fmt_duration_uncertain(
+                    &summary.duration(month.first_day()..=month.last_day()),
+                    current_date > month.last_day()
+                )
+            )?;
+        }
+
+        writeln!(
+            out,
+            "- {} {} ({})\n",
+            fmt_weekday(date.weekday()),
+            date.format(catalog().short_date_format),
+            fmt_duration_uncertain(&day.duration, &current_date > date)
+        )?;
+        for description in &day.descriptions {
+            writeln!(out, "\t- {}\n", description)?;
+        }
+    }
+    Ok(())
+}
+
+/// Worked time of a week, a month or a sub-project.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct GroupTotal {
+    /// like `2000-W01` for weeks, `2000-01` for months or the name of the sub-project
+    pub group: String,
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+    /// whether the period is over, so its duration can not change anymore
+    pub complete: bool,
+}
+
+/// The JSON output of the summary grouped by week, month or sub-project.
+#[derive(Debug, Serialize)]
+pub struct GroupTotals {
+    pub groups: Vec<GroupTotal>,
+    /// only with `--all-projects`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projects: Option<Vec<ProjectTotal>>,
+}
+
+/// The days of the summary in range added up by week, labeled with the ISO week.
+pub fn week_totals(
+    summary: &Summary,
+    range: impl RangeBounds<NaiveDate>,
+    week_start: Weekday,
+    current_date: NaiveDate,
+) -> Vec<GroupTotal> {
+    group_days(summary, range, current_date, |date| {
+        let week = date.week_starting(week_start);
+        let iso_week = week.iso_week();
+        let label = format!("{}-W{:02}", iso_week.year(), iso_week.week());
+        (label, week.0.last_day())
+    })
+}
+
+/// The days of the summary in range added up by month.
+pub fn month_totals(
+    summary: &Summary,
+    range: impl RangeBounds<NaiveDate>,
+    current_date: NaiveDate,
+) -> Vec<GroupTotal> {
+    group_days(summary, range, current_date, |date| {
+        let month = date.month_id();
+        let label = format!("{:04}-{:02}", month.year(), month.month() + 1);
+        (label, month.last_day())
+    })
+}
+
+/// Adds up the consecutive days sharing a label, `period` giving the label and last day of the
+/// period of each date.
+fn group_days(
+    summary: &Summary,
+    range: impl RangeBounds<NaiveDate>,
+    current_date: NaiveDate,
+    period: impl Fn(&NaiveDate) -> (String, NaiveDate),
+) -> Vec<GroupTotal> {
+    summary
+        .days
+        .range(range)
+        .chunk_by(|(date, _day)| period(date))
+        .into_iter()
+        .map(|((group, last_day), days)| GroupTotal {
+            group,
+            duration: days.map(|(_date, day)| day.duration).sum(),
+            complete: current_date > last_day,
+        })
+        .collect()
+}
+
+pub fn format_groups(groups: &[GroupTotal], out: &mut impl Write) -> io::Result<()> {
+    for group in groups {
+        writeln!(
+            out,
+            "{}: {}",
+            group.group,
+            fmt_duration_uncertain(&group.duration, group.complete)
+        )?;
+    }
+    Ok(())
+}
+
+/// The months of [`month_totals`] followed by the total of the range.
+pub fn format_totals(months: &[GroupTotal], out: &mut impl Write) -> io::Result<()> {
+    format_groups(months, out)?;
+    let total = months.iter().map(|month| month.duration).sum();
+    let complete = months.iter().all(|month| month.complete);
+    writeln!(
+        out,
+        "{}: {}",
+        catalog().total,
+        fmt_duration_uncertain(&total, complete)
+    )
+}
+
+/// The subtotal of each project, after a report spanning all of them.
+pub fn format_project_totals(projects: &[ProjectTotal], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "\n## {}\n", catalog().projects)?;
+    for project in projects {
+        writeln!(
+            out,
+            "- {}: {}",
+            project.project,
+            fmt_duration(&project.duration)
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct GroupCsvRow<'a> {
+    group: &'a str,
+    duration_seconds: u64,
+    complete: bool,
+}
+
+pub fn format_groups_csv(groups: &[GroupTotal], out: &mut impl Write) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    for group in groups {
+        writer.serialize(GroupCsvRow {
+            group: &group.group,
+            duration_seconds: group.duration.as_secs(),
+            complete: group.complete,
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// First and last dates of the range, using the defaults for the unbounded ends.
+pub fn resolve_dates(
+    (from, to): (Bound<NaiveDate>, Bound<NaiveDate>),
+    first: NaiveDate,
+    last: NaiveDate,
+) -> (NaiveDate, NaiveDate) {
+    let from = match from {
+        Bound::Included(from) => from,
+        Bound::Excluded(from) => from.succ_opt().unwrap(),
+        Bound::Unbounded => first,
+    };
+    let to = match to {
+        Bound::Included(to) => to,
+        Bound::Excluded(to) => to.pred_opt().unwrap(),
+        Bound::Unbounded => last,
+    };
+    (from, to)
+}
+
+/// Prints the summary of `clockin summary`, as set by its arguments.
+pub fn print_summary(args: SummaryArgs) -> Result<()> {
+    let SummaryArgs {
+        from,
+        to,
+        month,
+        week,
+        timezone,
+        version,
+        format,
+        group_by,
+        totals,
+        all_projects,
+        include_archived,
+        merge_gap,
+        tag,
+        sub_project,
+        host,
+        grep,
+        round,
+        round_per,
+        output,
+    } = args;
+    let (from, to) = month.or(week).map_or((from, to), DateRange::bounds);
+    let (round, round_per) = config::resolve_rounding(round, round_per)?;
+    let grep = parser::parse_grep(grep)?;
+    let merge_gap = config::resolve_merge_gap(merge_gap)?;
+    let day_rounding = rounding::day_rounding(round, round_per);
+    let load_sessions = |path: &Path| -> Result<Vec<Session>> {
+        let sessions = parser::parse_sessions(path)?
+            .into_iter()
+            .into_finished_now()
+            .filter(|s| binnacle_body_parser::has_tags(&s.description, &tag))
+            .filter(|s| binnacle_body_parser::in_sub_projects(&s.description, &sub_project))
+            .filter(|s| totals::on_hosts(s, &host))
+            .filter(|s| {
+                grep.as_ref()
+                    .is_none_or(|grep| grep.is_match(&s.description))
+            })
+            .merge_gaps(merge_gap);
+        Ok(rounding::round_sessions(sessions, round, round_per).collect())
+    };
+
+    let (sessions, timezone, project_totals) = if all_projects {
+        let timezone = config::resolve_global_timezone(timezone)?;
+        let mut sessions = vec![];
+        let mut project_totals = vec![];
+        for path in project_config::list_project_files(include_archived)? {
+            let project_sessions = load_sessions(&path)?;
+            let in_range =
+                sessions_between(project_sessions.iter().cloned(), (from, to), &timezone);
+            project_totals.push(ProjectTotal {
+                project: file::project_name(&path)?,
+                duration: worked_time_by_day(&in_range, day_rounding).to_std()?,
+            });
+            sessions.extend(project_sessions);
+        }
+        sessions.sort_by_key(|s| s.start);
+        (sessions, timezone, Some(project_totals))
+    } else {
+        let path = file::require_clockin_file()?;
+        let timezone = config::resolve_timezone(&path, timezone)?;
+        (load_sessions(&path)?, timezone, None)
+    };
+    let sessions = sessions.into_iter();
+    let current_date = Local::now().with_timezone(&timezone).date_naive();
+    let binnacle_data = |sessions| {
+        let mut data = binnacle_2::process(sessions, from, to, &timezone);
+        if let Some(round) = day_rounding {
+            data.round_days(&round);
+        }
+        data
+    };
+
+    let mut out = ReportOutput::new(output);
+    match (version, format) {
+        (1, SummaryFormat::Text) if totals => {
+            let mut summary = Summary::summarize(sessions, &timezone);
+            if let Some(round) = day_rounding {
+                summary.round_days(&round);
+            }
+            let months = month_totals(&summary, (from, to), current_date);
+            format_totals(&months, &mut out)?;
+        }
+        (_, _) if totals => bail!("--totals needs --version 1 and the text format"),
+        (1, format) if group_by != SummaryGrouping::Day => {
+            let groups = match group_by {
+                SummaryGrouping::SubProject | SummaryGrouping::Host => {
+                    let sessions = sessions_between(sessions, (from, to), &timezone);
+                    let complete = matches!(to, Bound::Included(to) if current_date > to);
+                    let groups = if group_by == SummaryGrouping::Host {
+                        totals::group_by_host(&sessions)
+                    } else {
+                        totals::group_by_sub_project(&sessions)
+                    };
+                    groups
+                        .into_iter()
+                        .map(|(group, sessions)| {
+                            Ok(GroupTotal {
+                                group,
+                                duration: worked_time_by_day(sessions, day_rounding).to_std()?,
+                                complete,
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?
+                        .into_iter()
+                        .sorted_by(|a, b| b.duration.cmp(&a.duration))
+                        .collect()
+                }
+                period => {
+                    let mut summary = Summary::summarize(sessions, &timezone);
+                    if let Some(round) = day_rounding {
+                        summary.round_days(&round);
+                    }
+                    if period == SummaryGrouping::Week {
+                        let week_start = Config::get()?.week_start.unwrap_or(Weekday::Mon);
+                        week_totals(&summary, (from, to), week_start, current_date)
+                    } else {
+                        month_totals(&summary, (from, to), current_date)
+                    }
+                }
+            };
+            match format {
+                SummaryFormat::Text => format_groups(&groups, &mut out)?,
+                SummaryFormat::Json => {
+                    let report = Versioned::new(GroupTotals {
+                        groups,
+                        projects: project_totals.clone(),
+                    });
+                    serde_json::to_writer_pretty(&mut out, &report)?;
+                    writeln!(out)?;
+                }
+                SummaryFormat::Csv => format_groups_csv(&groups, &mut out)?,
+            }
+        }
+        (1, format) => {
+            let mut summary = Summary::summarize(sessions, &timezone);
+            if let Some(round) = day_rounding {
+                summary.round_days(&round);
+            }
+            match format {
+                SummaryFormat::Text => self::format(&summary, (from, to), current_date, &mut out)?,
+                SummaryFormat::Json => {
+                    let months = report(&summary, (from, to), current_date);
+                    let report = Versioned::new(MonthReports {
+                        months,
+                        projects: project_totals.clone(),
+                    });
+                    serde_json::to_writer_pretty(&mut out, &report)?;
+                    writeln!(out)?;
+                }
+                SummaryFormat::Csv => {
+                    let report = report(&summary, (from, to), current_date);
+                    format_csv(&report, &mut out)?;
+                }
+            }
+        }
+        (_, _) if all_projects => bail!("--all-projects needs --version 1"),
+        (2, SummaryFormat::Csv) => bail!("csv output needs --version 1"),
+        (2, _) if group_by != SummaryGrouping::Day => bail!("--group-by needs --version 1"),
+        (2, SummaryFormat::Text) => {
+            let path = file::require_clockin_file()?;
+            let budgets = ProjectConfig::load(&path)?.budgets;
+            binnacle_2::format(binnacle_data(sessions), &budgets, current_date, &mut out)?
+        }
+        (2, SummaryFormat::Json) => {
+            serde_json::to_writer_pretty(&mut out, &Versioned::new(binnacle_data(sessions)))?;
+            writeln!(out)?;
+        }
+        _ => bail!("unknown version {version}"),
+    }
+    if let (Some(projects), SummaryFormat::Text) = (&project_totals, format) {
+        format_project_totals(projects, &mut out)?;
+    }
+    out.finish()
+}
+
+/// Prints the worked time of each week and of its days, as in `clockin week-summary`.
+pub fn print_week_summary(
+    path: &Path,
+    merge_gap: Option<i64>,
+    timezone: Option<FixedOffset>,
+    totals_only: bool,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now()
+        .merge_gaps(config::resolve_merge_gap(merge_gap)?);
+    let summary = Summary::summarize(sessions, &timezone);
+    let config = ProjectConfig::load(path)?;
+    let expected = config
+        .weekly_hours
+        .map(|hours| Duration::from_secs_f64(hours * 3600.0));
+
+    let week_start = Config::get()?.week_start.unwrap_or(Weekday::Mon);
+
+    let mut out = ReportOutput::new(output);
+    let mut last_week = None;
+    for (date, day) in &summary.days {
+        let week = date.week_starting(week_start);
+
+        if last_week.is_none_or(|last_week| last_week != week) {
+            last_week = Some(week);
+            let days = week
+                .first_day()
+                .iter_days()
+                .take(7)
+                .map(|date| summary.duration(date..=date))
+                .collect_vec();
+            let iso_week = week.iso_week();
+            write!(
+                out,
+                "W{:02} {} ({}): {} [{}]",
+                iso_week.week(),
+                iso_week.year(),
+                week.first_day(),
+                fmt_duration(&summary.week_duration(week)),
+                fmt_sparkline(&days)
+            )?;
+            match expected {
+                Some(expected) => writeln!(out, " / {}", fmt_duration(&expected))?,
+                None => writeln!(out)?,
+            }
+        }
+
+        if !totals_only {
+            writeln!(out, "- {}: {}", date, fmt_duration(&day.duration))?;
+        }
+    }
+    if let Some(balance) = flexitime::project_balance(path, &config, &timezone)? {
+        writeln!(
+            out,
+            "{}: {}",
+            catalog().flexitime_balance,
+            fmt_signed_duration(&balance)
+        )?;
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{NaiveDate, Utc, Weekday};
+
+    use super::{
+        GroupTotal, NaiveDateExt, ProjectTotal, Summary, format_csv, format_groups,
+        format_project_totals, format_totals, month_totals, report, week_totals,
+    };
+    use crate::testing::session;
+
+    #[test]
+    fn iso_weeks() {
+        // saturday 1st of january 2000 belongs to the last ISO week of 1999
+        let date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let iso_week = date.real_week().iso_week();
+        assert_eq!((iso_week.year(), iso_week.week()), (1999, 52));
+        // a week from sunday 2nd to saturday 8th is mostly the first ISO week of 2000
+        let sunday = date.succ_opt().unwrap();
+        let iso_week = sunday.week_starting(Weekday::Sun).iso_week();
+        assert_eq!((iso_week.year(), iso_week.week()), (2000, 1));
+    }
+
+    #[test]
+    fn month_report() {
+        let summary = Summary::summarize(
+            [
+                session("2000-01-30T10:00:00+00:00")
+                    .end("2000-01-30T11:00:00+00:00")
+                    .description("work")
+                    .build(),
+                session("2000-01-31T10:00:00+00:00")
+                    .end("2000-01-31T12:00:00+00:00")
+                    .description("work")
+                    .build(),
+                session("2000-02-01T10:00:00+00:00")
+                    .end("2000-02-01T10:30:00+00:00")
+                    .description("work")
+                    .build(),
+            ]
+            .into_iter(),
+            &Utc,
+        );
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+
+        let months = report(&summary, date(31).., date(31));
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].duration, Duration::from_secs(3 * 3600));
+        assert_eq!(months[0].days.len(), 1);
+        assert!(!months[0].complete && !months[0].days[0].complete);
+        assert_eq!(months[1].days[0].duration, Duration::from_secs(1800));
+
+        let mut csv = vec![];
+        format_csv(&months, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "date,weekday,duration_seconds,descriptions\n\
+             2000-01-31,Mon,7200,work\n\
+             2000-02-01,Tue,1800,work\n"
+        );
+    }
+
+    #[test]
+    fn group_totals() {
+        // sunday 30th, monday 31st and tuesday 1st
+        let summary = Summary::summarize(
+            [
+                session("2000-01-30T10:00:00+00:00")
+                    .end("2000-01-30T11:00:00+00:00")
+                    .description("work")
+                    .build(),
+                session("2000-01-31T10:00:00+00:00")
+                    .end("2000-01-31T12:00:00+00:00")
+                    .description("work")
+                    .build(),
+                session("2000-02-01T10:00:00+00:00")
+                    .end("2000-02-01T10:30:00+00:00")
+                    .description("work")
+                    .build(),
+            ]
+            .into_iter(),
+            &Utc,
+        );
+        let today = NaiveDate::from_ymd_opt(2000, 2, 1).unwrap();
+        let group = |group: &str, minutes: u64, complete| GroupTotal {
+            group: group.to_owned(),
+            duration: Duration::from_secs(minutes * 60),
+            complete,
+        };
+
+        assert_eq!(
+            week_totals(&summary, .., Weekday::Mon, today),
+            [group("2000-W04", 60, true), group("2000-W05", 150, false)]
+        );
+        assert_eq!(
+            week_totals(&summary, .., Weekday::Sun, today),
+            [group("2000-W05", 210, false)]
+        );
+        let months = month_totals(&summary, ..today, today);
+        assert_eq!(months, [group("2000-01", 180, true)]);
+
+        let mut text = vec![];
+        format_groups(&months, &mut text).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), "2000-01: 03:00:00\n");
+
+        let mut text = vec![];
+        format_totals(&month_totals(&summary, .., today), &mut text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "2000-01: 03:00:00\n2000-02: 00:30:00 (incompleto)\nTotal: 03:30:00 (incompleto)\n"
+        );
+
+        let mut text = vec![];
+        let project = ProjectTotal {
+            project: "acme".to_owned(),
+            duration: Duration::from_secs(90 * 60),
+        };
+        format_project_totals(&[project], &mut text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "\n## Proyectos\n\n- acme: 01:30:00\n"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let session = session("2000-01-01T23:00:00Z")
+            .end("2000-01-02T01:30:00Z")
+            .description("late")
+            .build();
+        let summary = Summary::summarize([session.clone()].into_iter(), &Utc);
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(
+            json,
+            r#"{"days":{"2000-01-01":{"duration_seconds":3600,"descriptions":["late"]},"2000-01-02":{"duration_seconds":5400,"descriptions":["late"]}}}"#
+        );
+        let summary: Summary = serde_json::from_str(&json).unwrap();
+        assert_eq!(summary.duration(..), Duration::from_secs(3600 + 5400));
+
+        let json = serde_json::to_string(&session).unwrap();
+        assert_eq!(
+            serde_json::from_str::<crate::parser::Session>(&json).unwrap(),
+            session
+        );
+        let month: super::MonthId = serde_json::from_str(r#""2000-02""#).unwrap();
+        assert_eq!(
+            month.first_day(),
+            NaiveDate::from_ymd_opt(2000, 2, 1).unwrap()
+        );
+    }
+}
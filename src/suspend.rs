@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::FixedOffset;
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::OwnedFd,
+};
+
+use clockin::{
+    cli::SuspendAction,
+    parser::{self, MaybeFinishedSessionTZ},
+    writer,
+};
+
+fn inhibit_sleep(manager: &Proxy) -> Result<OwnedFd> {
+    // a delay lock gives us a few seconds to write to the file before the system sleeps
+    Ok(manager.call(
+        "Inhibit",
+        &("sleep", "clockin", "closing the running session", "delay"),
+    )?)
+}
+
+fn on_sleep(
+    path: &PathBuf,
+    action: SuspendAction,
+) -> Result<Option<MaybeFinishedSessionTZ<FixedOffset>>> {
+    let Some(session) = parser::last_session(path)?.filter(|s| !s.is_finished() && !s.is_paused())
+    else {
+        return Ok(None);
+    };
+    match action {
+        SuspendAction::Pause => writer::write_date(path, false, 'p')?,
+        SuspendAction::Out => writer::write_date(path, true, '+')?,
+    }
+    Ok(Some(session))
+}
+
+fn on_wake(
+    path: &PathBuf,
+    action: SuspendAction,
+    session: MaybeFinishedSessionTZ<FixedOffset>,
+) -> Result<()> {
+    match action {
+        SuspendAction::Pause => {
+            if parser::last_session(path)?.is_some_and(|s| !s.is_finished() && s.is_paused()) {
+                writer::write_date(path, false, 'r')?;
+            }
+        }
+        SuspendAction::Out => {
+            if !parser::is_session_running(path)? {
+                writer::write_date(path, false, '-')?;
+                if !session.description.is_empty() {
+                    writer::write_description(path, &session.description)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn watch_suspend(path: &PathBuf, action: SuspendAction, resume_on_wake: bool) -> Result<()> {
+    let connection = Connection::system()?;
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+
+    let mut lock = Some(inhibit_sleep(&manager)?);
+    let mut suspended_session = None;
+    for signal in manager.receive_signal("PrepareForSleep")? {
+        let going_to_sleep: bool = signal.body().deserialize()?;
+        if going_to_sleep {
+            suspended_session = on_sleep(path, action)?;
+            // releasing the lock lets the system go to sleep
+            lock.take();
+        } else {
+            if let Some(session) = suspended_session.take()
+                && resume_on_wake
+            {
+                on_wake(path, action, session)?;
+            }
+            lock.get_or_insert(inhibit_sleep(&manager)?);
+        }
+    }
+
+    Ok(())
+}
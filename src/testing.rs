@@ -0,0 +1,66 @@
+use chrono::{DateTime, FixedOffset, TimeDelta};
+
+use crate::parser::{MaybeFinishedSessionTZ, Session};
+
+pub fn time(time: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(time).unwrap()
+}
+
+/// Starts building a session from its RFC 3339 start, with no end, pauses nor description.
+pub fn session(start: &str) -> SessionBuilder {
+    SessionBuilder(MaybeFinishedSessionTZ {
+        start: time(start),
+        end: None,
+        pauses: vec![],
+        paused_at: None,
+        description: String::new(),
+        host: None,
+        id: None,
+    })
+}
+
+pub struct SessionBuilder(MaybeFinishedSessionTZ<FixedOffset>);
+
+impl SessionBuilder {
+    pub fn end(mut self, end: &str) -> Self {
+        self.0.end = Some(time(end));
+        self
+    }
+
+    /// Ends the session this long after its start.
+    pub fn lasting(mut self, duration: TimeDelta) -> Self {
+        self.0.end = Some(self.0.start + duration);
+        self
+    }
+
+    pub fn pause(mut self, start: &str, end: &str) -> Self {
+        self.0.pauses.push(time(start)..time(end));
+        self
+    }
+
+    pub fn paused_at(mut self, paused_at: &str) -> Self {
+        self.0.paused_at = Some(time(paused_at));
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.0.description = description.to_owned();
+        self
+    }
+
+    pub fn host(mut self, host: &str) -> Self {
+        self.0.host = Some(host.to_owned());
+        self
+    }
+
+    /// The session as read from a file, finished or not.
+    pub fn maybe_finished(self) -> MaybeFinishedSessionTZ<FixedOffset> {
+        self.0
+    }
+
+    /// The finished session, an end has to be given.
+    pub fn build(self) -> Session {
+        assert!(self.0.is_finished(), "the session needs an end");
+        self.0.into_finished_now()
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    io::{self, Write},
+    ops::{Range, RangeBounds},
+    path::Path,
+};
+
+use anyhow::Result;
+use chrono::{NaiveTime, TimeDelta};
+
+use crate::{
+    cli::{DateRange, WorkTimeAnalysisArgs},
+    config,
+    format_util::{fmt_bar, fmt_hours_mins},
+    parser::{self, SessionIteratorClosingExt},
+    writer::ReportOutput,
+};
+
+/// Time worked in each slot of `interval` along the day, `interval` must divide the day.
+pub fn time_of_day(
+    intervals: impl Iterator<Item = Range<NaiveTime>>,
+    interval: TimeDelta,
+) -> Vec<TimeDelta> {
+    let slots = (TimeDelta::days(1).num_seconds() / interval.num_seconds()) as usize;
+    // one counter every interval
+    let mut results = vec![TimeDelta::zero(); slots];
+
+    for session in intervals {
+        for (i, result) in results.iter_mut().enumerate() {
+            let interval_start = NaiveTime::MIN + interval * (i as i32);
+            let interval_end = interval_start + interval;
+            // this fix is needed because "session end" is exclusive but NaiveTime wraps
+            // around at "24:00:00"
+            let fix_end = |t| {
+                if t == NaiveTime::MIN {
+                    NaiveTime::MIN - TimeDelta::nanoseconds(1)
+                } else {
+                    t
+                }
+            };
+            let overlap = (fix_end(session.end).min(fix_end(interval_end))
+                - session.start.max(interval_start))
+            .max(TimeDelta::zero());
+            *result += overlap;
+        }
+    }
+    results
+}
+
+/// A line per slot with its share of the worked time, or the hours when `absolute`. A bar of
+/// `bar_width` would hold all the worked time.
+pub fn format(
+    results: &[TimeDelta],
+    interval: TimeDelta,
+    bar_width: f64,
+    absolute: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let total: TimeDelta = results.iter().sum();
+
+    for (i, result) in results.iter().enumerate() {
+        let interval_start = NaiveTime::MIN + interval * (i as i32);
+        let interval_end = interval_start + interval;
+        let fraction = if total.is_zero() {
+            0.0
+        } else {
+            result.num_seconds() as f64 / total.num_seconds() as f64
+        };
+        let amount = if absolute {
+            format!("{:.02}h", result.num_seconds() as f64 / 3600.0)
+        } else {
+            format!("{:.02}%", 100.0 * fraction)
+        };
+        writeln!(
+            out,
+            "{}-{}: {} {}",
+            fmt_hours_mins(interval_start),
+            fmt_hours_mins(interval_end),
+            amount,
+            fmt_bar(fraction, bar_width)
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints the analysis of `clockin work-time-analysis`.
+pub fn print_analysis(path: &Path, args: WorkTimeAnalysisArgs) -> Result<()> {
+    let WorkTimeAnalysisArgs {
+        from,
+        to,
+        month,
+        week,
+        timezone,
+        interval,
+        bar_width,
+        absolute,
+        output,
+    } = args;
+    let (from, to) = month.or(week).map_or((from, to), DateRange::bounds);
+    let timezone = config::resolve_timezone(path, timezone)?;
+
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now()
+        .filter(|s| (from, to).contains(&s.start.with_timezone(&timezone).date_naive()))
+        .map(|s| s.naive_local())
+        .flat_map(|s| s.split_at_days())
+        .flat_map(|s| s.active_intervals())
+        .map(|i| i.start.time()..i.end.time());
+    let interval = TimeDelta::minutes(interval);
+    let results = time_of_day(sessions, interval);
+
+    let mut out = ReportOutput::new(output);
+    format(&results, interval, bar_width, absolute, &mut out)?;
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, TimeDelta};
+
+    use super::{format, time_of_day};
+
+    #[test]
+    fn slots() {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let interval = TimeDelta::hours(6);
+        let results = time_of_day(
+            [time(5, 0)..time(7, 30), time(22, 0)..NaiveTime::MIN].into_iter(),
+            interval,
+        );
+        assert_eq!(
+            results,
+            [
+                TimeDelta::hours(1),
+                TimeDelta::minutes(90),
+                TimeDelta::zero(),
+                // up to the last nanosecond of the day
+                TimeDelta::hours(2) - TimeDelta::nanoseconds(1)
+            ]
+        );
+
+        let mut out = vec![];
+        format(&results, interval, 10.0, false, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "00:00-06:00: 22.22% ##\n\
+             06:00-12:00: 33.34% ###\n\
+             12:00-18:00: 0.00% \n\
+             18:00-00:00: 44.44% ####\n"
+        );
+
+        let mut out = vec![];
+        format(&results[..2], interval, 10.0, true, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "00:00-06:00: 1.00h ####\n06:00-12:00: 1.50h ######\n"
+        );
+    }
+}
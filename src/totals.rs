@@ -0,0 +1,516 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    ops::{Bound, RangeBounds},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{Days, FixedOffset, Local, NaiveDate, TimeDelta, TimeZone, Weekday};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    binnacle_body_parser::{self, OwnedBody, SessionWithBody},
+    cli::{
+        GetWorkedTimeArgs, GetWorkedTimeCommand, Rounding, WorkedTimeFormat, WorkedTimeGrouping,
+    },
+    config::{self, Config},
+    file,
+    format_util::{fmt_duration, fmt_duration_human},
+    i18n::catalog,
+    parser::{
+        self, NaiveSession, NaiveSessionIteratorExt, Session, SessionIteratorClosingExt,
+        SessionIteratorExt, SessionMergingExt,
+    },
+    project_config, rounding,
+    schema::Versioned,
+    summary::{NaiveDateExt, ProjectTotal, serialize_seconds},
+    writer::ReportOutput,
+};
+
+/// Total time per key extracted from the session bodies (tags, tickets...), longest first.
+/// Sessions with several keys count towards each of them.
+pub fn totals_by(
+    sessions: impl Iterator<Item = Session>,
+    from: Bound<NaiveDate>,
+    to: Bound<NaiveDate>,
+    timezone: &impl TimeZone,
+    keys: impl Fn(OwnedBody) -> Vec<String>,
+) -> Vec<(String, TimeDelta)> {
+    let mut totals = HashMap::<String, TimeDelta>::new();
+    for session in sessions
+        .with_timezone(timezone)
+        .naive_local()
+        .cut_at_days()
+        .filter(|s| (from, to).contains(&s.start.date()))
+        .map(|s| SessionWithBody {
+            body: binnacle_body_parser::parse(&s.description)
+                .unwrap()
+                .to_owned(),
+            session: s,
+        })
+    {
+        let duration = session.session.duration();
+        for key in keys(session.body) {
+            *totals.entry(key).or_default() += duration;
+        }
+    }
+
+    totals
+        .into_iter()
+        .sorted_by(|(key_a, a), (key_b, b)| b.cmp(a).then_with(|| key_a.cmp(key_b)))
+        .collect()
+}
+
+/// [`totals_by`] over the sessions of the project, in the timezone given or else resolved for it.
+fn project_totals_by(
+    path: &Path,
+    (from, to): (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    keys: impl Fn(OwnedBody) -> Vec<String>,
+) -> Result<Vec<(String, TimeDelta)>> {
+    let timezone = config::resolve_timezone(path, timezone)?;
+    let sessions = parser::parse_sessions(path)?
+        .into_iter()
+        .into_finished_now();
+    Ok(totals_by(sessions, from, to, &timezone, keys))
+}
+
+/// Prints the tasks that took the most time, as in `clockin top`.
+pub fn print_top(
+    path: &Path,
+    count: usize,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let mut out = ReportOutput::new(output);
+    for (task, duration) in project_totals_by(path, range, timezone, task)?
+        .into_iter()
+        .take(count)
+    {
+        writeln!(out, "{}\t{task}", fmt_duration(&duration.to_std()?))?;
+    }
+    out.finish()
+}
+
+pub fn print_tags(
+    path: &Path,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let mut out = ReportOutput::new(output);
+    for (tag, duration) in project_totals_by(path, range, timezone, |b| b.tags)? {
+        writeln!(out, "{}\t#{tag}", fmt_duration(&duration.to_std()?))?;
+    }
+    out.finish()
+}
+
+pub fn print_tickets(
+    path: &Path,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    timezone: Option<FixedOffset>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let mut out = ReportOutput::new(output);
+    for (ticket, duration) in project_totals_by(path, range, timezone, |b| b.tickets)? {
+        writeln!(out, "{}\t{ticket}", fmt_duration(&duration.to_std()?))?;
+    }
+    out.finish()
+}
+
+/// The task of a session as `sub-project: subject`, the key of [`totals_by`] for the biggest time
+/// sinks. Sessions without a subject have none.
+pub fn task(body: OwnedBody) -> Vec<String> {
+    let subject = body.subject.trim();
+    if subject.is_empty() {
+        return vec![];
+    }
+    let task = match body.sub_project_path().as_slice() {
+        [] => subject.to_owned(),
+        path => format!("{}: {subject}", path.join("/")),
+    };
+    vec![task]
+}
+
+/// Sessions grouped by their top level sub-project, the ones without it under the uncategorized
+/// label of the reports.
+pub fn group_by_sub_project(sessions: &[NaiveSession]) -> BTreeMap<String, Vec<&NaiveSession>> {
+    let mut groups = BTreeMap::<String, Vec<&NaiveSession>>::new();
+    for session in sessions {
+        let body = binnacle_body_parser::parse(&session.description)
+            .unwrap()
+            .to_owned();
+        let sub_project = match body.sub_project_path().first() {
+            Some(sub_project) => sub_project.to_string(),
+            None => catalog().uncategorized.to_owned(),
+        };
+        groups.entry(sub_project).or_default().push(session);
+    }
+    groups
+}
+
+/// Sessions grouped by the machine they were recorded on, the ones without it under the unknown
+/// host label of the reports.
+pub fn group_by_host(sessions: &[NaiveSession]) -> BTreeMap<String, Vec<&NaiveSession>> {
+    let mut groups = BTreeMap::<String, Vec<&NaiveSession>>::new();
+    for session in sessions {
+        let host = session
+            .host
+            .clone()
+            .unwrap_or_else(|| catalog().unknown_host.to_owned());
+        groups.entry(host).or_default().push(session);
+    }
+    groups
+}
+
+/// Whether the session was recorded on one of the hosts, any session passes an empty list.
+pub fn on_hosts(session: &Session, hosts: &[String]) -> bool {
+    hosts.is_empty()
+        || session
+            .host
+            .as_ref()
+            .is_some_and(|host| hosts.contains(host))
+}
+
+/// Worked time of the sessions, adding up the (rounded) totals of each day.
+pub fn worked_time_by_day<'a>(
+    sessions: impl IntoIterator<Item = &'a NaiveSession>,
+    round: Option<Rounding>,
+) -> TimeDelta {
+    sessions
+        .into_iter()
+        .chunk_by(|s| s.start.date())
+        .into_iter()
+        .map(|(_date, day)| {
+            let duration = day.map(|s| s.duration()).sum();
+            round.map_or(duration, |round| rounding::round(duration, &round))
+        })
+        .sum()
+}
+
+/// The sessions within the dates, splitting the ones that cross midnight.
+pub fn sessions_between(
+    sessions: impl Iterator<Item = Session>,
+    range: impl RangeBounds<NaiveDate>,
+    timezone: &FixedOffset,
+) -> Vec<NaiveSession> {
+    sessions
+        .with_timezone(timezone)
+        .naive_local()
+        .cut_at_days()
+        .filter(|s| range.contains(&s.start.date()))
+        .collect()
+}
+
+/// Prints the time worked in the period of `clockin get-worked-time`, in total or broken down.
+pub fn print_worked_time(args: GetWorkedTimeArgs) -> Result<()> {
+    let GetWorkedTimeArgs {
+        merge_gap,
+        tag,
+        sub_project,
+        host,
+        round,
+        round_per,
+        format,
+        by,
+        all_projects,
+        include_archived,
+        specification,
+    } = args;
+    let (round, round_per) = config::resolve_rounding(round, round_per)?;
+    let merge_gap = config::resolve_merge_gap(merge_gap)?;
+    let day_rounding = rounding::day_rounding(round, round_per);
+    let paths = if all_projects {
+        project_config::list_project_files(include_archived)?
+    } else {
+        vec![file::require_clockin_file()?]
+    };
+    let load_sessions = |path: &Path| -> Result<Vec<Session>> {
+        let sessions = parser::parse_sessions(path)?
+            .into_iter()
+            .into_finished_now()
+            .filter(|s| binnacle_body_parser::has_tags(&s.description, &tag))
+            .filter(|s| binnacle_body_parser::in_sub_projects(&s.description, &sub_project))
+            .filter(|s| on_hosts(s, &host))
+            .merge_gaps(merge_gap);
+        Ok(rounding::round_sessions(sessions, round, round_per).collect())
+    };
+
+    let today = |timezone| -> Result<(FixedOffset, NaiveDate)> {
+        let timezone = match paths.as_slice() {
+            [path] if !all_projects => config::resolve_timezone(path, timezone)?,
+            _ => config::resolve_global_timezone(timezone)?,
+        };
+        Ok((timezone, Local::now().with_timezone(&timezone).date_naive()))
+    };
+    let until_today = |first, today| (Bound::Included(first), Bound::Included(today));
+
+    // the dates to count, none for the last session
+    let range = match specification {
+        GetWorkedTimeCommand::Today { timezone } => {
+            let (timezone, today) = today(timezone)?;
+            Some((until_today(today, today), timezone))
+        }
+        GetWorkedTimeCommand::Yesterday { timezone } => {
+            let (timezone, today) = today(timezone)?;
+            let yesterday = today.pred_opt().context("there is no day before today")?;
+            Some((until_today(yesterday, yesterday), timezone))
+        }
+        GetWorkedTimeCommand::ThisWeek { timezone } => {
+            let (timezone, today) = today(timezone)?;
+            let week_start = Config::get()?.week_start.unwrap_or(Weekday::Mon);
+            let first = today.week_starting(week_start).first_day();
+            Some((until_today(first, today), timezone))
+        }
+        GetWorkedTimeCommand::ThisMonth { timezone } => {
+            let (timezone, today) = today(timezone)?;
+            let first = today.month_id().first_day();
+            Some((until_today(first, today), timezone))
+        }
+        GetWorkedTimeCommand::LastNDays { n, timezone } => {
+            let (timezone, today) = today(timezone)?;
+            let first = today
+                .checked_sub_days(Days::new(n - 1))
+                .with_context(|| format!("can't count {n} days back from {today}"))?;
+            Some((until_today(first, today), timezone))
+        }
+        GetWorkedTimeCommand::ByDateRange { from, to, timezone } => {
+            let (timezone, _today) = today(timezone)?;
+            Some(((from, to), timezone))
+        }
+        GetWorkedTimeCommand::LastSession if all_projects => {
+            bail!("last-session needs a single project")
+        }
+        GetWorkedTimeCommand::LastSession => None,
+    };
+
+    let projects = paths
+        .iter()
+        .map(|path| {
+            let sessions = load_sessions(path)?;
+            let sessions = match range {
+                Some((range, timezone)) => sessions_between(sessions.into_iter(), range, &timezone),
+                None => sessions
+                    .into_iter()
+                    .last()
+                    .map(|s| s.naive_local())
+                    .into_iter()
+                    .collect(),
+            };
+            Ok((file::project_name(path)?, sessions))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let longest_first = |(name_a, a): &(String, TimeDelta), (name_b, b): &(String, TimeDelta)| {
+        b.cmp(a).then_with(|| name_a.cmp(name_b))
+    };
+    let project_times = projects
+        .iter()
+        .map(|(project, sessions)| (project.clone(), worked_time_by_day(sessions, day_rounding)))
+        .sorted_by(longest_first)
+        .collect_vec();
+    let sessions = projects
+        .into_iter()
+        .flat_map(|(_project, sessions)| sessions)
+        .sorted_by_key(|s| s.start)
+        .collect_vec();
+
+    let worked_time = worked_time_by_day(&sessions, day_rounding);
+    let breakdown = by.map(|by| match by {
+        WorkedTimeGrouping::SubProject => group_by_sub_project(&sessions)
+            .into_iter()
+            .map(|(sub_project, sessions)| {
+                (sub_project, worked_time_by_day(sessions, day_rounding))
+            })
+            .sorted_by(longest_first)
+            .collect_vec(),
+        WorkedTimeGrouping::Project => project_times,
+        WorkedTimeGrouping::Host => group_by_host(&sessions)
+            .into_iter()
+            .map(|(host, sessions)| (host, worked_time_by_day(sessions, day_rounding)))
+            .sorted_by(longest_first)
+            .collect_vec(),
+    });
+
+    let fmt_worked_time: fn(&Duration) -> String = match format {
+        WorkedTimeFormat::Json => {
+            let mut worked_time = WorkedTime {
+                duration: worked_time.to_std()?,
+                sub_projects: matches!(by, Some(WorkedTimeGrouping::SubProject)).then(Vec::new),
+                projects: matches!(by, Some(WorkedTimeGrouping::Project)).then(Vec::new),
+                hosts: matches!(by, Some(WorkedTimeGrouping::Host)).then(Vec::new),
+            };
+            for (name, duration) in breakdown.into_iter().flatten() {
+                let duration = duration.to_std()?;
+                if let Some(sub_projects) = &mut worked_time.sub_projects {
+                    sub_projects.push(SubProjectTime {
+                        sub_project: name,
+                        duration,
+                    });
+                } else if let Some(projects) = &mut worked_time.projects {
+                    projects.push(ProjectTotal {
+                        project: name,
+                        duration,
+                    });
+                } else if let Some(hosts) = &mut worked_time.hosts {
+                    hosts.push(HostTime {
+                        host: name,
+                        duration,
+                    });
+                }
+            }
+            println!("{}", serde_json::to_string(&Versioned::new(worked_time))?);
+            return Ok(());
+        }
+        WorkedTimeFormat::Seconds => |time| time.as_secs().to_string(),
+        WorkedTimeFormat::Hms => fmt_duration,
+        WorkedTimeFormat::DecimalHours => |time| format!("{:.2}", time.as_secs_f64() / 3600.0),
+        WorkedTimeFormat::Human => fmt_duration_human,
+    };
+    match breakdown {
+        None => println!("{}", fmt_worked_time(&worked_time.to_std()?)),
+        Some(breakdown) => {
+            for (name, duration) in breakdown {
+                println!("{}\t{name}", fmt_worked_time(&duration.to_std()?));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Time worked in a period, printed by `get-worked-time --format json`.
+#[derive(Debug, Serialize)]
+pub struct WorkedTime {
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+    /// only with `--by sub-project`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_projects: Option<Vec<SubProjectTime>>,
+    /// only with `--by project`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projects: Option<Vec<ProjectTotal>>,
+    /// only with `--by host`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hosts: Option<Vec<HostTime>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubProjectTime {
+    pub sub_project: String,
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HostTime {
+    pub host: String,
+    #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use chrono::{TimeDelta, Utc};
+
+    use super::{group_by_sub_project, task, totals_by};
+    use crate::{parser::SessionIteratorExt, testing::session};
+
+    #[test]
+    fn tags() {
+        let sessions = [
+            session("2000-01-01T10:00:00+00:00")
+                .end("2000-01-01T11:00:00+00:00")
+                .description("#a #b")
+                .build(),
+            session("2000-01-02T10:00:00+00:00")
+                .end("2000-01-02T12:00:00+00:00")
+                .description("#b")
+                .build(),
+            session("2000-01-03T10:00:00+00:00")
+                .end("2000-01-03T13:00:00+00:00")
+                .description("untagged")
+                .build(),
+        ];
+
+        assert_eq!(
+            totals_by(
+                sessions.into_iter(),
+                Bound::Unbounded,
+                Bound::Unbounded,
+                &Utc,
+                |body| body.tags
+            ),
+            [
+                ("b".to_owned(), TimeDelta::hours(3)),
+                ("a".to_owned(), TimeDelta::hours(1))
+            ]
+        );
+    }
+
+    #[test]
+    fn sub_projects() {
+        let sessions = [
+            "backend/api: auth",
+            "backend: deploy",
+            "docs: readme",
+            "no sub-project",
+        ]
+        .map(|description| {
+            session("2000-01-01T10:00:00+00:00")
+                .end("2000-01-01T11:00:00+00:00")
+                .description(description)
+                .build()
+        })
+        .into_iter()
+        .naive_local()
+        .collect::<Vec<_>>();
+
+        let groups = group_by_sub_project(&sessions);
+        let sizes: Vec<_> = groups
+            .iter()
+            .map(|(name, sessions)| (name.as_str(), sessions.len()))
+            .collect();
+        assert_eq!(sizes, [("backend", 2), ("docs", 1), ("sin categoría", 1)]);
+    }
+
+    #[test]
+    fn tasks() {
+        let sessions = [
+            session("2000-01-01T10:00:00+00:00")
+                .end("2000-01-01T10:30:00+00:00")
+                .description("backend /api: auth")
+                .build(),
+            session("2000-01-01T11:00:00+00:00")
+                .end("2000-01-01T11:30:00+00:00")
+                .description("review")
+                .build(),
+            session("2000-01-01T12:00:00+00:00")
+                .end("2000-01-01T12:30:00+00:00")
+                .description("backend/api:auth ")
+                .build(),
+            session("2000-01-01T13:00:00+00:00")
+                .end("2000-01-01T13:30:00+00:00")
+                .description("docs:")
+                .build(),
+        ];
+
+        assert_eq!(
+            totals_by(
+                sessions.into_iter(),
+                Bound::Unbounded,
+                Bound::Unbounded,
+                &Utc,
+                task
+            ),
+            [
+                ("backend/api: auth".to_owned(), TimeDelta::hours(1)),
+                ("review".to_owned(), TimeDelta::minutes(30))
+            ]
+        );
+    }
+}
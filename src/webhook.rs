@@ -0,0 +1,110 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, Local};
+use serde::Serialize;
+
+use clockin::{
+    file,
+    parser::{self, MaybeFinishedSessionTZ},
+    project_config::ProjectConfig,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    ClockIn,
+    ClockOut,
+}
+
+/// Body of the POST request sent to every webhook.
+#[derive(Debug, Serialize)]
+pub struct Payload {
+    event: Event,
+    project: String,
+    time: DateTime<FixedOffset>,
+    start: DateTime<FixedOffset>,
+    /// `null` when clocking in
+    end: Option<DateTime<FixedOffset>>,
+    duration_seconds: i64,
+    description: String,
+}
+
+impl Payload {
+    pub fn new(
+        event: Event,
+        project: String,
+        time: DateTime<FixedOffset>,
+        session: MaybeFinishedSessionTZ<FixedOffset>,
+    ) -> Self {
+        let end = session.end;
+        let session = session.into_finished_now();
+        Payload {
+            event,
+            project,
+            time,
+            start: session.start,
+            end,
+            duration_seconds: session.duration().num_seconds(),
+            description: session.description,
+        }
+    }
+}
+
+fn post(url: &str, payload: &Payload) -> Result<()> {
+    let agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .new_agent();
+    agent.post(url).send_json(payload)?;
+    Ok(())
+}
+
+/// Tells the webhooks of the project about the last session, which just started or ended. They
+/// are best effort, failures are only reported.
+pub fn notify(path: &Path, event: Event) -> Result<()> {
+    let webhooks = ProjectConfig::load(path)?.webhooks;
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+    let session = parser::last_session(path)?.context("the project has no sessions")?;
+    let payload = Payload::new(
+        event,
+        file::project_name(path)?,
+        Local::now().fixed_offset(),
+        session,
+    );
+    for url in &webhooks {
+        if let Err(err) = post(url, &payload) {
+            eprintln!("webhook {url} failed: {err:#}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Event, Payload};
+    use crate::testing::{session, time};
+
+    #[test]
+    fn payload() {
+        let session = session("2000-01-01T10:00:00+01:00")
+            .end("2000-01-01T11:30:00+01:00")
+            .description("work")
+            .maybe_finished();
+        let payload = Payload::new(
+            Event::ClockOut,
+            "p".to_owned(),
+            time("2000-01-01T11:30:05+01:00"),
+            session,
+        );
+        assert_eq!(
+            serde_json::to_string(&payload).unwrap(),
+            r#"{"event":"clock-out","project":"p","time":"2000-01-01T11:30:05+01:00","start":"2000-01-01T10:00:00+01:00","end":"2000-01-01T11:30:00+01:00","duration_seconds":5400,"description":"work"}"#
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::FixedOffset;
+
+use clockin::{cli::WidgetStyle, parser::MaybeFinishedSessionTZ, status};
+
+/// The prompt segment followed by the project, green while running and yellow while paused.
+/// Nothing is shown while stopped.
+pub fn format(
+    style: WidgetStyle,
+    project: &str,
+    last_session: Option<MaybeFinishedSessionTZ<FixedOffset>>,
+) -> Option<String> {
+    let paused = last_session.as_ref().is_some_and(|s| s.is_paused());
+    let segment = status::prompt(last_session)?;
+    // a name with escape sequences could mess up the terminal
+    let project: String = project.chars().filter(|c| !c.is_control()).collect();
+    Some(match style {
+        WidgetStyle::Tmux => format!(
+            "#[fg={}]{segment} {}#[default]",
+            if paused { "yellow" } else { "green" },
+            project.replace('#', "##")
+        ),
+        WidgetStyle::Starship => format!(
+            "\x1b[{}m{segment} {project}\x1b[0m",
+            if paused { 33 } else { 32 }
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::format;
+    use crate::testing::session;
+    use clockin::cli::WidgetStyle;
+
+    #[test]
+    fn widget() {
+        let session = session("2000-01-01T08:00:00Z")
+            .paused_at("2000-01-01T09:12:00Z")
+            .maybe_finished();
+        assert_eq!(
+            format(WidgetStyle::Tmux, "issue#1", Some(session.clone())).unwrap(),
+            "#[fg=yellow]⏸ 01:12 issue##1#[default]"
+        );
+        assert_eq!(
+            format(WidgetStyle::Starship, "back\x1bend", Some(session)).unwrap(),
+            "\x1b[33m⏸ 01:12 backend\x1b[0m"
+        );
+        assert_eq!(format(WidgetStyle::Tmux, "p", None), None);
+    }
+}
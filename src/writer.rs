@@ -1,27 +1,749 @@
-use std::{fs::File, io::Write, path::Path};
+use std::{
+    cell::RefCell,
+    fs::{self, File},
+    io::{self, Write},
+    ops::Range,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    process, thread,
+    time::{Duration, Instant},
+};
 
-use anyhow::{Context, Result};
-use chrono::{DateTime, Local, TimeZone};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Datelike, FixedOffset, Local, TimeZone};
+use itertools::Itertools;
 
-fn fmt_datetime<Tz: TimeZone>(time: DateTime<Tz>) -> String {
-    time.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+use crate::{
+    cli::Encryption,
+    config::Config,
+    crypt, file,
+    parser::{self, MaybeFinishedSessionTZ, Session, SessionIteratorClosingExt},
+    project_config::ProjectConfig,
+};
+
+thread_local! {
+    /// Lock files held by this thread, taking one of them again does nothing.
+    static HELD_LOCKS: RefCell<Vec<PathBuf>> = const { RefCell::new(vec![]) };
 }
 
-pub fn write_date(path: impl AsRef<Path>, extra_return: bool, prefix: char) -> Result<()> {
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Advisory lock on a project, so two writers never interleave their changes. It is released
+/// when dropped.
+pub struct ProjectLock {
+    held: Option<(File, PathBuf)>,
+}
+
+impl Drop for ProjectLock {
+    fn drop(&mut self) {
+        if let Some((file, path)) = self.held.take() {
+            let _ = file.set_len(0);
+            HELD_LOCKS.with_borrow_mut(|held| held.retain(|p| *p != path));
+        }
+    }
+}
+
+/// Locks the project, waiting a little for another process to finish its write. The holder writes
+/// its pid and command into the lock file, so whoever waits for it can tell who it is.
+pub fn lock(path: impl AsRef<Path>) -> Result<ProjectLock> {
+    let lock_path = file::lock_file(path.as_ref())?;
+    if HELD_LOCKS.with_borrow(|held| held.contains(&lock_path)) {
+        return Ok(ProjectLock { held: None });
+    }
+
     let mut file = File::options()
+        .create(true)
         .append(true)
-        .open(path)
-        .context("opening clockin file")?;
+        .open(&lock_path)
+        .context("opening lock file")?;
+    let started = Instant::now();
+    // SAFETY: the descriptor stays open as long as `file`, which is kept by the lock
+    while unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::WouldBlock {
+            return Err(err).context("locking clockin file");
+        }
+        if started.elapsed() > LOCK_TIMEOUT {
+            let holder = fs::read_to_string(&lock_path).unwrap_or_default();
+            match holder.trim().split_once(' ') {
+                Some((pid, command)) => bail!(
+                    "the project is being written by `{command}` (pid {pid}), try again once it \
+                     finishes"
+                ),
+                None => bail!("the project is being written by another clockin"),
+            }
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let command = std::env::args().collect_vec().join(" ");
+    file.set_len(0)
+        .and_then(|()| writeln!(file, "{} {command}", process::id()))
+        .context("writing lock file")?;
+    HELD_LOCKS.with_borrow_mut(|held| held.push(lock_path.clone()));
+    Ok(ProjectLock {
+        held: Some((file, lock_path)),
+    })
+}
+
+/// Written in the offset the configuration records timestamps in, or else in their own one.
+fn fmt_datetime<Tz: TimeZone>(time: DateTime<Tz>) -> String {
+    match Config::get().ok().and_then(|config| config.record_timezone) {
+        Some(offset) => time
+            .with_timezone(&offset)
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        None => time.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+    }
+}
 
-    let start = Local::now();
+pub fn write_date(path: impl AsRef<Path>, extra_return: bool, prefix: char) -> Result<()> {
+    write_datetime(path, Local::now(), extra_return, prefix)
+}
 
-    let start_str = fmt_datetime(start);
-    file.write_all(format!("%{prefix}{start_str}\n").as_bytes())
-        .context("writing start time")?;
+/// Name of this machine, none if it can't be read.
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the length passed is the one of the buffer
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let host = String::from_utf8_lossy(&buf[..len]).trim().to_owned();
+    (!host.is_empty()).then_some(host)
+}
 
+/// The file sessions are appended to: the last one of the project, unless it keeps yearly files
+/// and a session starts, then the one of the year it starts in.
+fn append_target(path: &Path, start_year: Option<i32>) -> Result<PathBuf> {
+    if let Some(year) = start_year
+        && ProjectConfig::load(path)?.yearly_files == Some(true)
+    {
+        return file::year_file(path, year);
+    }
+    Ok(file::session_files(path)?.pop().unwrap())
+}
+
+pub fn write_datetime<Tz: TimeZone>(
+    path: impl AsRef<Path>,
+    start: DateTime<Tz>,
+    extra_return: bool,
+    prefix: char,
+) -> Result<()> {
+    let path = path.as_ref();
+    let _lock = lock(path)?;
+    let target = append_target(path, (prefix == '-').then(|| start.year()))?;
+
+    let mut text = format!("%{prefix}{}\n", fmt_datetime(start));
+    if prefix == '-'
+        && Config::get().ok().and_then(|config| config.record_host) == Some(true)
+        && let Some(host) = hostname()
+    {
+        text.push_str(&format!("%h {host}\n"));
+    }
     if extra_return {
-        file.write_all("\n".as_bytes())
-            .context("writing start time")?;
+        text.push('\n');
+    }
+    append(path, &target, &text).context("writing start time")
+}
+
+pub fn write_description(path: impl AsRef<Path>, description: &str) -> Result<()> {
+    let path = path.as_ref();
+    let _lock = lock(path)?;
+    append(
+        path,
+        &append_target(path, None)?,
+        &format!("{description}\n"),
+    )
+    .context("writing description")
+}
+
+/// Appends to a session file of the project, which for an encrypted project means rewriting it
+/// whole. Only the yearly files are created when missing.
+fn append(project: &Path, target: &Path, text: &str) -> Result<()> {
+    let config = ProjectConfig::load(project)?;
+    if config.encryption.is_none() {
+        let mut file = File::options()
+            .append(true)
+            .create(target != project)
+            .open(target)
+            .context("opening clockin file")?;
+        return Ok(file.write_all(text.as_bytes())?);
+    }
+
+    let mut content = if target == project || target.exists() {
+        crypt::read(target).context("opening clockin file")?
+    } else {
+        String::new()
+    };
+    content.push_str(text);
+    write_atomically(target, crypt::encrypt(content.into_bytes(), &config)?)
+}
+
+/// Replaces a session file of the project, encrypting it if the project is encrypted.
+pub fn write_session_file(project: &Path, path: &Path, content: String) -> Result<()> {
+    let content = crypt::encrypt(content.into_bytes(), &ProjectConfig::load(project)?)?;
+    write_atomically(path, content).context("writing clockin file")
+}
+
+/// Rewrites the session files of the project as its configuration says, encrypting them or
+/// leaving them in plain text. Every file is encrypted before any is replaced, so a failure leaves
+/// them as they were.
+pub fn reencrypt_project(path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let _lock = lock(path)?;
+    let config = ProjectConfig::load(path)?;
+    let files = read_session_files(path)?
+        .into_iter()
+        .map(|(file, content)| Ok((file, crypt::encrypt(content.into_bytes(), &config)?)))
+        .collect::<Result<Vec<_>>>()?;
+    for (file, content) in files {
+        write_atomically(file, content).context("writing clockin file")?;
+    }
+    Ok(())
+}
+
+/// Sets the encryption of the project and encrypts its files, going back to plain text if they
+/// can't be encrypted.
+pub fn encrypt_project(
+    path: &Path,
+    encryption: Encryption,
+    recipient: Option<String>,
+) -> Result<()> {
+    let mut config = ProjectConfig::load(path)?;
+    config.encryption = Some(encryption);
+    config.recipient = recipient;
+    config.save(path)?;
+    if let Err(err) = reencrypt_project(path) {
+        config.encryption = None;
+        config.recipient = None;
+        config.save(path)?;
+        return Err(err.context("error while encrypting the project"));
+    }
+    Ok(())
+}
+
+/// Appends to a project file the way the `clockin` commands do. Nothing is checked, starting a
+/// session while another one is running leaves the first one unfinished.
+pub struct SessionWriter {
+    path: PathBuf,
+}
+
+impl SessionWriter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        SessionWriter { path: path.into() }
+    }
+
+    pub fn start(&self, at: DateTime<FixedOffset>) -> Result<()> {
+        write_datetime(&self.path, at, false, '-')
+    }
+
+    /// Adds a line to the description of the last session.
+    pub fn describe(&self, line: &str) -> Result<()> {
+        write_description(&self.path, line)
+    }
+
+    pub fn pause(&self, at: DateTime<FixedOffset>) -> Result<()> {
+        write_datetime(&self.path, at, false, 'p')
+    }
+
+    pub fn resume(&self, at: DateTime<FixedOffset>) -> Result<()> {
+        write_datetime(&self.path, at, false, 'r')
+    }
+
+    /// Finishes the session, leaving a blank line before the next one.
+    pub fn finish(&self, at: DateTime<FixedOffset>) -> Result<()> {
+        write_datetime(&self.path, at, true, '+')
+    }
+
+    /// Adds a session in the past, in order with the others.
+    pub fn insert(&self, session: Session) -> Result<()> {
+        insert_session(&self.path, session)
+    }
+}
+
+pub fn fmt_session(session: &MaybeFinishedSessionTZ<FixedOffset>) -> String {
+    let mut out = format!("%-{}\n", fmt_datetime(session.start));
+    if let Some(id) = &session.id {
+        out.push_str(&format!("%id {id}\n"));
+    }
+    if let Some(host) = &session.host {
+        out.push_str(&format!("%h {host}\n"));
+    }
+    if !session.description.is_empty() {
+        out.push_str(&session.description);
+        out.push('\n');
+    }
+    for pause in &session.pauses {
+        out.push_str(&format!("%p{}\n", fmt_datetime(pause.start)));
+        out.push_str(&format!("%r{}\n", fmt_datetime(pause.end)));
+    }
+    if let Some(paused_at) = session.paused_at {
+        out.push_str(&format!("%p{}\n", fmt_datetime(paused_at)));
+    }
+    if let Some(end) = session.end {
+        out.push_str(&format!("%+{}\n\n", fmt_datetime(end)));
+    }
+    out
+}
+
+/// Inserts a finished session before the first one that starts after it.
+pub fn insert_session(path: impl AsRef<Path>, session: Session) -> Result<()> {
+    insert_sessions(path, vec![session])
+}
+
+/// The project file and its yearly files along with their contents.
+fn read_session_files(path: impl AsRef<Path>) -> Result<Vec<(PathBuf, String)>> {
+    file::session_files(path.as_ref())?
+        .into_iter()
+        .map(|path| {
+            let content = crypt::read(&path).context("reading clockin file")?;
+            Ok((path, content))
+        })
+        .collect()
+}
+
+/// Index of the file a session starting at `start` belongs to: the last one whose first session
+/// starts before it, or else the first one with sessions.
+fn file_for(firsts: &[Option<DateTime<FixedOffset>>], start: DateTime<FixedOffset>) -> usize {
+    firsts
+        .iter()
+        .rposition(|first| first.is_some_and(|first| first <= start))
+        .or_else(|| firsts.iter().position(Option::is_some))
+        .unwrap_or_default()
+}
+
+fn first_start(content: &str) -> Option<DateTime<FixedOffset>> {
+    content
+        .lines()
+        .find_map(|line| parser::extract_macro(parser::clean_line(line), '-')?.ok())
+}
+
+/// Inserts finished sessions before the first one that starts after each of them, rewriting each
+/// file only once. They go in the file holding the sessions around them.
+pub fn insert_sessions(path: impl AsRef<Path>, sessions: Vec<Session>) -> Result<()> {
+    let project = path.as_ref();
+    let _lock = lock(project)?;
+    let files = read_session_files(project)?;
+    let firsts = files
+        .iter()
+        .map(|(_, content)| first_start(content))
+        .collect::<Vec<_>>();
+    let mut by_file = vec![vec![]; files.len()];
+    for session in sessions {
+        by_file[file_for(&firsts, session.start)].push(session);
+    }
+    for ((path, content), sessions) in files.into_iter().zip(by_file) {
+        if !sessions.is_empty() {
+            insert_into(project, &path, &content, sessions)?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_into(
+    project: &Path,
+    path: &Path,
+    content: &str,
+    mut sessions: Vec<Session>,
+) -> Result<()> {
+    sessions.sort_by_key(|s| s.start);
+    let mut pending = sessions.into_iter().peekable();
+
+    let mut out = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        if let Some(Ok(start)) = parser::extract_macro(parser::clean_line(line), '-') {
+            while let Some(session) = pending.next_if(|s| s.start < start) {
+                out.push_str(&fmt_session(&session.into()));
+            }
+        }
+        out.push_str(line);
+    }
+
+    let mut rest = pending.map(|s| fmt_session(&s.into())).collect::<String>();
+    if !rest.is_empty() && !out.is_empty() && !out.ends_with('\n') {
+        rest.insert(0, '\n');
+    }
+    out.push_str(&rest);
+
+    write_session_file(project, path, out)
+}
+
+/// Byte ranges of the session blocks, from each start macro up to the blank line after its end
+/// macro, or up to the next start macro if the session was never finished.
+fn session_blocks(content: &str) -> Vec<Range<usize>> {
+    let mut blocks = vec![];
+    let mut current = None;
+    let mut finished_at = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = parser::clean_line(line);
+        if let Some(finished_at) = finished_at.take() {
+            let start = current.take().unwrap();
+            if trimmed.is_empty() {
+                // swallow the blank line separating sessions
+                offset += line.len();
+                blocks.push(start..offset);
+                continue;
+            }
+            blocks.push(start..finished_at);
+        }
+
+        if parser::is_macro_line(trimmed, '-') {
+            if let Some(start) = current.replace(offset) {
+                blocks.push(start..offset);
+            }
+        } else if current.is_some() && parser::is_macro_line(trimmed, '+') {
+            finished_at = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+    if let Some(start) = current {
+        blocks.push(start..finished_at.unwrap_or(offset));
+    }
+    blocks
+}
+
+/// Replaces the block of the session at `index` (in file order, counting the sessions of every
+/// file of the project) with `block`.
+fn rewrite_session(path: impl AsRef<Path>, mut index: usize, block: &str) -> Result<()> {
+    let project = path.as_ref();
+    let _lock = lock(project)?;
+    for (path, mut content) in read_session_files(project)? {
+        let blocks = session_blocks(&content);
+        let Some(range) = blocks.get(index) else {
+            index -= blocks.len();
+            continue;
+        };
+        content.replace_range(range.clone(), block);
+        return write_session_file(project, &path, content);
+    }
+    bail!("session not found in clockin file")
+}
+
+fn rewrite_last_session(path: impl AsRef<Path>, block: &str) -> Result<()> {
+    let _lock = lock(&path)?;
+    let count = read_session_files(&path)?
+        .iter()
+        .map(|(_, content)| session_blocks(content).len())
+        .sum::<usize>();
+    match count {
+        0 => Ok(()),
+        len => rewrite_session(path, len - 1, block),
+    }
+}
+
+pub fn remove_last_session(path: impl AsRef<Path>) -> Result<()> {
+    rewrite_last_session(path, "")
+}
+
+pub fn replace_last_session(
+    path: impl AsRef<Path>,
+    session: &MaybeFinishedSessionTZ<FixedOffset>,
+) -> Result<()> {
+    rewrite_last_session(path, &fmt_session(session))
+}
+
+pub fn replace_session(
+    path: impl AsRef<Path>,
+    index: usize,
+    sessions: &[MaybeFinishedSessionTZ<FixedOffset>],
+) -> Result<()> {
+    rewrite_session(
+        path,
+        index,
+        &sessions.iter().map(fmt_session).collect::<String>(),
+    )
+}
+
+/// Changes the start, end or description of a session, the last one when no id is given, as long
+/// as it stays in order with the others.
+pub fn amend_session(
+    path: &Path,
+    id: Option<String>,
+    start: Option<DateTime<FixedOffset>>,
+    end: Option<DateTime<FixedOffset>>,
+    message: Option<String>,
+) -> Result<()> {
+    let sessions = parser::parse_sessions(path)?;
+    let index = match id {
+        Some(id) => parser::find_session(path, &sessions, &id)?,
+        None if sessions.is_empty() => bail!("there is no session to amend"),
+        None => sessions.len() - 1,
+    };
+    let mut session = sessions[index].clone();
+
+    session.start = start.unwrap_or(session.start);
+    session.end = end.or(session.end);
+    session.description = message.unwrap_or(session.description);
+
+    if session.end.is_some_and(|end| end <= session.start) {
+        bail!("the session must end after it starts");
+    }
+    if session.end.unwrap_or(session.start) > Local::now() {
+        bail!("the session can't end in the future");
+    }
+    if let Some(previous_end) = index
+        .checked_sub(1)
+        .and_then(|previous| sessions[previous].end)
+        && session.start < previous_end
+    {
+        bail!("the session can't start before the previous one ended at {previous_end}");
+    }
+    if let Some(next) = sessions.get(index + 1)
+        && session.end.is_none_or(|end| next.start < end)
+    {
+        bail!(
+            "the session can't end after the next one started at {}",
+            next.start
+        );
+    }
+    let first_pause = session
+        .pauses
+        .first()
+        .map(|p| p.start)
+        .or(session.paused_at);
+    let last_pause = session.pauses.last().map(|p| p.end).or(session.paused_at);
+    if first_pause.is_some_and(|p| p < session.start)
+        || last_pause.is_some_and(|p| session.end.is_some_and(|end| end < p))
+    {
+        bail!("the session pauses must be inside the session");
+    }
+
+    if index + 1 == sessions.len() {
+        replace_last_session(path, &session)
+    } else {
+        replace_session(path, index, &[session])
+    }
+}
+
+/// Splits the session running at the time in two, the one with the id when given.
+pub fn split_session(path: &Path, id: Option<String>, at: DateTime<FixedOffset>) -> Result<()> {
+    if at > Local::now() {
+        bail!("can't split a session in the future");
+    }
+    let sessions = parser::parse_sessions(path)?;
+    let contains =
+        |s: &MaybeFinishedSessionTZ<FixedOffset>| s.start < at && s.end.is_none_or(|end| at < end);
+    let (index, session) = match id {
+        Some(id) => {
+            let index = parser::find_session(path, &sessions, &id)?;
+            if !contains(&sessions[index]) {
+                bail!("the session {id} is not running at {at}");
+            }
+            (index, sessions[index].clone())
+        }
+        None => match sessions.into_iter().find_position(contains) {
+            Some(found) => found,
+            None => bail!("there is no session at {at}"),
+        },
+    };
+
+    let (first, second) = session.split_at(at);
+    replace_session(path, index, &[first, second])
+}
+
+/// Adds a finished session in the past, refusing to overlap with the others unless `force` is set.
+pub fn add_session(
+    path: &Path,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    message: String,
+    force: bool,
+) -> Result<()> {
+    if start >= end {
+        bail!("the session must end after it starts");
+    }
+    if end > Local::now() {
+        bail!("the session can't end in the future");
+    }
+    if !force
+        && let Some(overlapping) = parser::parse_sessions(path)?
+            .into_iter()
+            .into_finished_now()
+            .find(|s| s.start < end && start < s.end)
+    {
+        bail!(
+            "the session overlaps with the one started at {}, use --force to add it anyway",
+            overlapping.start
+        );
+    }
+
+    insert_session(
+        path,
+        Session {
+            start,
+            end,
+            pauses: vec![],
+            description: message,
+            host: None,
+            id: None,
+        },
+    )
+}
+
+/// Writes through a temporary file in the same directory so a crash never leaves the file
+/// half written. The file is created if it does not exist.
+pub fn write_atomically(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> Result<()> {
+    let path = match fs::canonicalize(&path) {
+        Ok(path) => path,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => path.as_ref().to_owned(),
+        Err(err) => return Err(err).context("resolving file path"),
+    };
+    let name = path.file_name().context("file has no name")?;
+    let temp = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+
+    let replace = || -> Result<()> {
+        let mut file = File::create(&temp).context("creating temporary file")?;
+        file.write_all(content.as_ref())
+            .context("writing temporary file")?;
+        if let Ok(metadata) = fs::metadata(&path) {
+            fs::set_permissions(&temp, metadata.permissions())
+                .context("copying file permissions")?;
+        }
+        // the content has to be on disk before the rename makes it the file
+        file.sync_all().context("writing temporary file")?;
+        fs::rename(&temp, &path).context("replacing file")
+    };
+    let result = replace();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Destination of a report: standard output, or a file that is only written once the whole
+/// report is generated, so a failure never leaves it half written.
+pub enum ReportOutput {
+    Stdout(io::StdoutLock<'static>),
+    File { path: PathBuf, content: Vec<u8> },
+}
+
+impl ReportOutput {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        match path {
+            Some(path) => Self::File {
+                path,
+                content: vec![],
+            },
+            None => Self::Stdout(io::stdout().lock()),
+        }
+    }
+
+    pub fn finish(self) -> Result<()> {
+        match self {
+            Self::Stdout(mut stdout) => Ok(stdout.flush()?),
+            Self::File { path, content } => write_atomically(&path, content)
+                .with_context(|| format!("error while writing {}", path.display())),
+        }
+    }
+}
+
+impl Write for ReportOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::File { content, .. } => content.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::File { .. } => Ok(()),
+        }
+    }
+}
+
+/// The content of a session file with its sessions replaced, keeping whatever comes before the
+/// first one.
+pub fn replace_sessions(content: &str, sessions: &[MaybeFinishedSessionTZ<FixedOffset>]) -> String {
+    let header = session_blocks(content)
+        .first()
+        .map_or(content.len(), |b| b.start);
+    let mut content = content[..header].to_owned();
+    for session in sessions {
+        content.push_str(&fmt_session(session));
+    }
+    content
+}
+
+/// Replaces every session in the files of the project, keeping whatever comes before the first
+/// one of each file. Every session goes to the file whose sessions were around it.
+pub fn rewrite_sessions(
+    path: impl AsRef<Path>,
+    sessions: &[MaybeFinishedSessionTZ<FixedOffset>],
+) -> Result<()> {
+    let project = path.as_ref();
+    let _lock = lock(project)?;
+    let mut files = read_session_files(project)?;
+    let firsts = files
+        .iter()
+        .map(|(_, content)| first_start(content))
+        .collect::<Vec<_>>();
+    for (_, content) in &mut files {
+        let first_start = session_blocks(content)
+            .first()
+            .map_or(content.len(), |b| b.start);
+        content.truncate(first_start);
+    }
+    for session in sessions {
+        files[file_for(&firsts, session.start)]
+            .1
+            .push_str(&fmt_session(session));
+    }
+
+    for (file, content) in files {
+        write_session_file(project, &file, content)?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        os::unix::fs::{PermissionsExt, symlink},
+        thread,
+    };
+
+    use super::{lock, write_atomically};
+
+    #[test]
+    fn locking() {
+        let dir = std::env::temp_dir().join(format!("clockin-lock-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let project = dir.join("acme");
+        fs::write(&project, "").unwrap();
+
+        let held = lock(&project).unwrap();
+        // taking it again in the same thread, like a write under `clockin in`, is allowed
+        drop(lock(&project).unwrap());
+        let other = project.clone();
+        assert!(thread::spawn(move || lock(other).is_err()).join().unwrap());
+
+        drop(held);
+        let other = project.clone();
+        assert!(thread::spawn(move || lock(other).is_ok()).join().unwrap());
+        assert!(dir.join(".acme.lock").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_writes() {
+        let dir = std::env::temp_dir().join(format!("clockin-atomic-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (project, link) = (dir.join("acme"), dir.join(".clockin"));
+        fs::write(&project, "old").unwrap();
+        fs::set_permissions(&project, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&project, &link).unwrap();
+
+        write_atomically(&link, "new").unwrap();
+        assert_eq!(fs::read_to_string(&project).unwrap(), "new");
+        assert_eq!(fs::read_link(&link).unwrap(), project);
+        let mode = fs::metadata(&project).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!dir.join(".acme.tmp").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
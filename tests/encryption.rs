@@ -0,0 +1,83 @@
+use std::{fs, os::unix::fs::PermissionsExt, path::Path, process::Command};
+
+/// Stands in for age: the armored "ciphertext" is the base64 of the plain text, so the test does
+/// not need keys.
+const STUB_AGE: &str = r#"#!/bin/sh
+case "$1" in
+--encrypt)
+    echo "-----BEGIN AGE ENCRYPTED FILE-----"
+    base64
+    echo "-----END AGE ENCRYPTED FILE-----"
+    ;;
+--decrypt)
+    grep -v -- "-----" | base64 -d
+    ;;
+*)
+    exit 1
+    ;;
+esac
+"#;
+
+fn clockin(dir: &Path, args: &[&str]) -> String {
+    let path = format!(
+        "{}:{}",
+        dir.join("bin").display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_clockin"))
+        .args(args)
+        .current_dir(dir.join("work"))
+        .env("PATH", path)
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("TZ", "UTC")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "clockin {}: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn age_round_trip() {
+    let dir = std::env::temp_dir().join(format!("clockin-age-test-{}", std::process::id()));
+    for sub in ["bin", "work", "config/clockin"] {
+        fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    let age = dir.join("bin/age");
+    fs::write(&age, STUB_AGE).unwrap();
+    fs::set_permissions(&age, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(
+        dir.join("config/clockin/config.toml"),
+        "age-identity = \"/dev/null\"\n",
+    )
+    .unwrap();
+    let project = dir.join("data/clockin/acme");
+    let is_encrypted = || {
+        fs::read_to_string(&project)
+            .unwrap()
+            .starts_with("-----BEGIN AGE ENCRYPTED FILE-----")
+    };
+
+    clockin(
+        &dir,
+        &["link", "acme", "--encrypt", "age", "--recipient", "me"],
+    );
+    // appended
+    clockin(&dir, &["in", "--at", "2000-01-01T10:00:00Z", "-m", "first"]);
+    clockin(&dir, &["out"]);
+    assert!(is_encrypted());
+    // rewritten
+    clockin(&dir, &["split", "2000-01-01T10:30:00Z"]);
+    assert!(is_encrypted());
+
+    let log = clockin(&dir, &["log", "--format", "json"]);
+    assert_eq!(log.matches("\"first\"").count(), 2, "{log}");
+    assert!(log.contains("2000-01-01T10:30:00"), "{log}");
+
+    fs::remove_dir_all(dir).unwrap();
+}